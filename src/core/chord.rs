@@ -0,0 +1,204 @@
+//! # Chord
+//!
+//! A chord is a note which plays several notes at once. Its scale degree is calculated like the one
+//! of a single note, the other tones are stacked in thirds on top of it within the scale of the
+//! level. An inversion moves the lowest tones an octave up.
+
+use crate::core::game::spawn_note;
+use crate::core::model::{Chord, ChordKind, Note};
+use crate::core::modifier::{ModifierArea, modify_pitch};
+use crate::core::object::{ObjectAppExt, ObjectField, ObjectType, cycle};
+use crate::music::model::{Pitch, Scale};
+use bevy::prelude::*;
+
+pub(super) struct ChordPlugin;
+
+impl Plugin for ChordPlugin {
+    fn build(&self, app: &mut App) {
+        // chords are saved and hit as notes
+        app.register_object(
+            ObjectType::<Chord>::new()
+                .item("Chord", |assets| assets.note_icon.clone(), spawn_chord)
+                .draw(draw_chord_markers)
+                .field(KIND)
+                .field(INVERSION),
+        );
+    }
+}
+
+/// The pitches the chord plays on the given scale degree, a single note without a chord.
+pub fn chord_pitches(
+    chord: Option<&Chord>,
+    index: u8,
+    scale: &(impl Scale + ?Sized),
+) -> Vec<Pitch> {
+    let Some(chord) = chord else {
+        return vec![Pitch {
+            note: scale.get(index),
+            octave: 0,
+        }];
+    };
+
+    // degree 0 is the root as well
+    let root = index.max(1);
+    let mut pitch = Pitch {
+        note: scale.get(root),
+        octave: 0,
+    };
+    (0..chord.kind.tones())
+        .map(|tone| {
+            if tone > 0 {
+                // the samples wrap at A, wherever the scale starts, so the octave is carried by
+                // stepping up from the previous tone
+                let note = scale.get(root + 2 * tone);
+                pitch = pitch.transpose((note as i32 - pitch.note as i32).rem_euclid(12));
+            }
+            if tone < chord.inversion {
+                pitch.transpose(12)
+            } else {
+                pitch
+            }
+        })
+        .collect()
+}
+
+/// The pitches a note at the given position plays: the tones of its chord, each altered by the
+/// modifiers around the note.
+pub fn played_pitches(
+    chord: Option<&Chord>,
+    index: u8,
+    scale: &(impl Scale + ?Sized),
+    position: Vec2,
+    modifiers: &[ModifierArea],
+) -> Vec<Pitch> {
+    chord_pitches(chord, index, scale)
+        .into_iter()
+        .map(|it| modify_pitch(it, position, modifiers))
+        .collect()
+}
+
+/// Tones of the chord, an inversion is kept as far as the chord has the tones.
+const KIND: ObjectField = ObjectField {
+    name: "Chord",
+    steps: None,
+    value: |object| object.get::<Chord>().map(|it| format!("{:?}", it.kind)),
+    edit: |object, step| {
+        if let Some(mut chord) = object.get_mut::<Chord>() {
+            let kinds = [ChordKind::Triad, ChordKind::Seventh];
+            if let Some(next) = cycle(&kinds, Some(chord.kind), step) {
+                chord.kind = next;
+                chord.inversion = chord.inversion.min(next.tones() - 1);
+            }
+        }
+    },
+};
+
+/// Lowest tones of the chord which are played an octave higher.
+const INVERSION: ObjectField = ObjectField {
+    name: "Inversion",
+    steps: Some(&[-1.0, 1.0]),
+    value: |object| match object.get::<Chord>()?.inversion {
+        0 => Some("root".to_string()),
+        inversion => Some(format!("{inversion}.")),
+    },
+    edit: |object, step| {
+        if let Some(mut chord) = object.get_mut::<Chord>() {
+            let inversions: Vec<u8> = (0..chord.kind.tones()).collect();
+            if let Some(next) = cycle(&inversions, Some(chord.inversion), step) {
+                chord.inversion = next;
+            }
+        }
+    },
+};
+
+fn spawn_chord(entity: &mut EntityWorldMut) {
+    spawn_note(entity);
+    entity.insert(Chord::default());
+}
+
+/// Show a dot for every tone of a chord, stacked next to its icon.
+fn draw_chord_markers(chords: Query<(&Chord, &Transform), With<Note>>, mut gizmos: Gizmos) {
+    let color = Color::srgba(1.0, 1.0, 1.0, 0.6);
+    for (chord, transform) in &chords {
+        let center = transform.translation.xy();
+        for tone in 0..chord.kind.tones() {
+            let position = center + Vec2::new(16.0, tone as f32 * 7.0 - 10.0);
+            gizmos.circle_2d(Isometry2d::from_translation(position), 3.0, color);
+            if tone < chord.inversion {
+                // inverted tones are filled
+                gizmos.circle_2d(Isometry2d::from_translation(position), 1.5, color);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::model::Modifier;
+    use crate::music::model::NaturalMinorScale;
+    use crate::music::model::Note::*;
+
+    fn notes(pitches: Vec<Pitch>) -> Vec<(crate::music::model::Note, i32)> {
+        pitches.iter().map(|it| (it.note, it.octave)).collect()
+    }
+
+    #[test]
+    fn test_chords_stack_thirds_in_the_scale() {
+        let scale = NaturalMinorScale::new(A);
+        let triad = Chord::default();
+        assert_eq!(
+            vec![(A, 0), (C, 0), (E, 0)],
+            notes(chord_pitches(Some(&triad), 1, &scale))
+        );
+        // the tones above the scale are an octave higher
+        assert_eq!(
+            vec![(F, 0), (A, 1), (C, 1)],
+            notes(chord_pitches(Some(&triad), 6, &scale))
+        );
+
+        let seventh = Chord {
+            kind: ChordKind::Seventh,
+            inversion: 1,
+        };
+        assert_eq!(
+            vec![(D, 1), (F, 0), (A, 1), (C, 1)],
+            notes(chord_pitches(Some(&seventh), 4, &scale))
+        );
+        assert_eq!(vec![(G, 0)], notes(chord_pitches(None, 7, &scale)));
+    }
+
+    #[test]
+    fn test_chords_wrap_at_the_samples_in_other_keys() {
+        let scale = NaturalMinorScale::new(C);
+        let triad = Chord::default();
+        assert_eq!(
+            vec![(C, 0), (Ds, 0), (G, 0)],
+            notes(chord_pitches(Some(&triad), 1, &scale))
+        );
+        // the C above the G# is beyond the samples, although it is within the scale
+        assert_eq!(
+            vec![(F, 0), (Gs, 0), (C, 1)],
+            notes(chord_pitches(Some(&triad), 4, &scale))
+        );
+    }
+
+    #[test]
+    fn test_modifiers_alter_every_tone() {
+        let scale = NaturalMinorScale::new(A);
+        let sharp = ModifierArea {
+            modifier: Modifier::Sharp,
+            center: Vec2::ZERO,
+            radius: 40.0,
+        };
+        let pitches = played_pitches(
+            Some(&Chord::default()),
+            5,
+            &scale,
+            Vec2::new(10.0, 0.0),
+            &[sharp],
+        );
+        // the triad on E is shifted to F, G# and C
+        assert_eq!(vec![(F, 0), (Gs, 0), (C, 1)], notes(pitches));
+    }
+}
@@ -1,40 +1,124 @@
+use crate::core::chord::ChordPlugin;
+use crate::core::gate::{GatePlugin, inputs_at};
+use crate::core::hit::{Hittable, ObjectHitEvent, WaveHitEvent, hit_object};
 use crate::core::model::{
-    Activator, ActivatorColor, ActivatorSize, ActivatorState, ActivatorType, InactivatedObjects,
-    Note,
+    Activator, ActivatorColor, ActivatorLimit, ActivatorShape, ActivatorSize, ActivatorSpeed,
+    ActivatorState, ActivatorType, Chord, Echo, Echoes, Gate, GateInputs, InactivatedObjects, Note,
+    NoteLength, Percussion, Portal, Pulse, PulseRings, Ring, Waiting, Wall, Zone,
 };
+use crate::core::modifier::ModifierPlugin;
+use crate::core::motion::{
+    SavedMotion, anchor_moving_objects, draw_motion_paths, load_motion, move_objects,
+    release_moving_objects, save_motion, sort_moving_rings,
+};
+use crate::core::object::{ObjectAppExt, ObjectField, ObjectType, SavedEntities, cycle};
+use crate::core::portal::{PortalPlugin, portal_exit, portal_ring_size};
+use crate::core::prediction::{ObjectKind, Prediction, PredictionPlugin, Wave};
+use crate::core::pulse::{PulsePlugin, emit_pulses, execute_pulse_rings};
+use crate::core::shape::{
+    Sector, ShapePlugin, direction, execute_sweeps, size_to_reach, sorted_by_shape, sweep_angle,
+    sweep_speed,
+};
+use crate::core::wall::{
+    WallPlugin, echo_distances, is_occluded, reached_echoes, sorted_echoes, wall_segments,
+};
+use crate::core::zone::{ZoneArea, ZonePlugin, travel_distance, wavefront_distance, zone_areas};
+use crate::music::audio::BackgroundCycleEvent;
 use crate::music::model::Scale;
-use crate::state::GameState;
+use crate::state::{ExecutionState, GameState};
+use crate::visual::color::ColorPalette;
+use bevy::platform::collections::HashSet;
 use bevy::prelude::*;
-use bevy_svg::prelude::{Svg, Svg2d};
+use bevy_svg::prelude::{Origin, Svg, Svg2d};
+use serde::{Deserialize, Serialize};
 use std::cmp::Ordering;
+use std::f32::consts::TAU;
+use std::time::Duration;
 
 pub struct CoreGamePlugin;
 
 impl Plugin for CoreGamePlugin {
     fn build(&self, app: &mut App) {
-        app.init_resource::<CoreAssets>()
+        app.add_plugins(PredictionPlugin)
+            .init_resource::<CoreAssets>()
+            .init_resource::<LoopState>()
+            .init_resource::<ReachedNotes>()
+            .init_resource::<ExecutionClock>()
             .add_event::<NotePlayedEvent>()
-            .add_event::<ActivatorEnabledEvent>()
             .add_event::<ActivatorDisabledEvent>()
-            .add_event::<ObjectActivatedEvent>()
+            .add_event::<ObjectHitEvent>()
             .add_event::<AllPlayedEvent>()
+            .add_event::<ScrubEvent>()
             .add_observer(activate_activator)
+            .register_object(
+                ObjectType::<Note>::new()
+                    .item("Note", |assets| assets.note_icon.clone(), spawn_note)
+                    .item(
+                        "Percussion",
+                        |assets| assets.drum_icon.clone(),
+                        spawn_percussion,
+                    )
+                    .draw(draw_unreached_notes)
+                    .field(DRUM)
+                    .field(LENGTH)
+                    .save(save_note, load_note)
+                    .on_hit(|_| ObjectKind::Note, play_note),
+            )
+            .register_object(
+                ObjectType::<Activator>::new()
+                    .item("Activator", activator_icon, spawn_activator)
+                    .item("Delay", activator_icon, spawn_delay_activator)
+                    .draw(draw_delay_markers)
+                    .field(COLOR)
+                    .field(SPEED)
+                    .field(MAX_RADIUS)
+                    .field(LIFETIME)
+                    .field(DELAY)
+                    .save(save_activator, load_activator)
+                    .on_hit(activator_kind, enable_hit_activator),
+            )
+            // every other mechanic registers its objects in its own plugin
+            .add_plugins((
+                ChordPlugin,
+                PulsePlugin,
+                ShapePlugin,
+                GatePlugin,
+                PortalPlugin,
+                WallPlugin,
+                ZonePlugin,
+                ModifierPlugin,
+            ))
             .add_systems(
                 Update,
                 (
+                    tick_execution_clock,
+                    move_objects,
+                    scrub_execution,
+                    start_delayed_activators,
+                    emit_pulses,
+                    sort_moving_rings,
                     execute_activator_and_check_collisions,
+                    execute_sweeps,
+                    execute_pulse_rings,
                     draw_activator_sizes,
-                    collect_activation_events,
-                    handle_object_activated,
+                    handle_object_hit,
+                    remember_played_notes,
                     check_all_played,
                     handle_all_played,
+                    restart_loop,
                 )
                     .run_if(in_state(GameState::Execute))
                     .chain(),
             )
-            .add_systems(Update, disable_activator)
-            .add_systems(OnEnter(GameState::Execute), enter_execution)
-            .add_systems(OnExit(GameState::Execute), exit_execution);
+            .add_systems(Update, (disable_activator, draw_motion_paths))
+            .add_systems(
+                OnEnter(GameState::Execute),
+                (enter_execution, anchor_moving_objects),
+            )
+            .add_systems(
+                OnExit(GameState::Execute),
+                (exit_execution, release_moving_objects),
+            );
     }
 }
 
@@ -42,6 +126,144 @@ impl Plugin for CoreGamePlugin {
 pub struct LevelConfig {
     pub grow_factor: f32,
     pub scale: Box<dyn Scale>,
+    /// Tempo in beats per minute, which times pulses, delays, motions and note lengths.
+    pub bpm: f32,
+    /// Seconds a note with a length fades out after it. A short release lets the notes sound
+    /// staccato, a long one lets them overlap.
+    pub release: f32,
+    pub looping: Looping,
+    /// Asset path of the level script, see [`crate::level::script`].
+    pub script: Option<String>,
+    /// How many objects of an item the player can place, by the name of the item. The other items
+    /// can be placed as often as the player likes.
+    pub item_limits: Vec<(&'static str, u32)>,
+}
+
+impl LevelConfig {
+    /// Length of a beat in seconds.
+    pub fn beat_length(&self) -> f32 {
+        60.0 / self.bpm
+    }
+
+    /// Length of a bar in seconds. A bar has four beats.
+    pub fn bar_length(&self) -> f32 {
+        self.beat_length() * 4.0
+    }
+
+    /// Distance the ring of an activator with the given speed grows per second.
+    pub fn growth(&self, speed: &ActivatorSpeed) -> f32 {
+        self.grow_factor * **speed
+    }
+}
+
+/// Repeat the execution like a looper pedal instead of returning to the build mode. One iteration
+/// of the loop always lasts whole bars of the level.
+#[derive(Debug, Clone, Copy)]
+pub struct Looping {
+    pub enabled: bool,
+    /// Start the next iteration with the next cycle of the background strings instead.
+    pub sync_to_background: bool,
+}
+
+/// Available playback rates of the execution.
+const PLAYBACK_RATES: [f32; 5] = [0.25, 0.5, 1.0, 2.0, 4.0];
+/// The execution time a single step advances while paused.
+const STEP: Duration = Duration::from_millis(100);
+
+/// Time of the current execution or loop iteration. It runs with the playback rate and stands still
+/// while paused, so growing the activators, triggering notes and drawing are always consistent.
+#[derive(Resource, Debug)]
+pub struct ExecutionClock {
+    rate: f32,
+    delta: Duration,
+    elapsed: Duration,
+    step_requested: bool,
+}
+
+impl Default for ExecutionClock {
+    fn default() -> Self {
+        ExecutionClock {
+            rate: 1.0,
+            delta: Duration::ZERO,
+            elapsed: Duration::ZERO,
+            step_requested: false,
+        }
+    }
+}
+
+impl ExecutionClock {
+    pub fn delta(&self) -> Duration {
+        self.delta
+    }
+
+    pub fn elapsed(&self) -> Duration {
+        self.elapsed
+    }
+
+    pub fn rate(&self) -> f32 {
+        self.rate
+    }
+
+    pub fn faster(&mut self) {
+        if let Some(rate) = PLAYBACK_RATES.iter().find(|it| **it > self.rate) {
+            self.rate = *rate;
+        }
+    }
+
+    pub fn slower(&mut self) {
+        if let Some(rate) = PLAYBACK_RATES.iter().rev().find(|it| **it < self.rate) {
+            self.rate = *rate;
+        }
+    }
+
+    /// Advance the execution by one step in the next frame. Only has an effect while paused.
+    pub fn step(&mut self) {
+        self.step_requested = true;
+    }
+
+    pub(crate) fn tick(&mut self, delta: Duration, state: &ExecutionState) {
+        self.delta = match state {
+            ExecutionState::Running => delta.mul_f32(self.rate),
+            ExecutionState::Paused if self.step_requested => STEP,
+            ExecutionState::Paused => Duration::ZERO,
+        };
+        self.step_requested = false;
+        self.elapsed += self.delta;
+    }
+}
+
+/// State of the loop mode between two iterations.
+#[derive(Resource, Default, Debug)]
+struct LoopState {
+    /// set after all notes of the current iteration are played
+    next: Option<NextIteration>,
+}
+
+#[derive(Debug)]
+enum NextIteration {
+    After(Duration),
+    BackgroundCycle,
+}
+
+/// The notes played in the current execution or loop iteration. Notes which are out of reach of
+/// every activator are reported when it ends.
+#[derive(Resource, Default, Debug)]
+struct ReachedNotes {
+    played: HashSet<Entity>,
+    /// notes which were not played in the last execution
+    unreached: Vec<Entity>,
+}
+
+impl ReachedNotes {
+    fn report(&mut self, notes: &Query<Entity, With<Note>>) {
+        self.unreached = notes
+            .iter()
+            .filter(|it| !self.played.contains(it))
+            .collect();
+        if !self.unreached.is_empty() {
+            info!("{} notes were not reached", self.unreached.len());
+        }
+    }
 }
 
 #[derive(Resource)]
@@ -49,6 +271,11 @@ pub struct CoreAssets {
     pub note_icon: Handle<Svg>,
     pub activator_icon_pause: Handle<Svg>,
     pub activator_icon_play: Handle<Svg>,
+    pub wall_icon: Handle<Svg>,
+    pub mirror_icon: Handle<Svg>,
+    pub zone_icon: Handle<Svg>,
+    pub modifier_icon: Handle<Svg>,
+    pub drum_icon: Handle<Svg>,
 }
 
 impl FromWorld for CoreAssets {
@@ -57,6 +284,28 @@ impl FromWorld for CoreAssets {
             note_icon: world.load_asset("icons/music-solid.svg"),
             activator_icon_pause: world.load_asset("icons/circle-pause-regular.svg"),
             activator_icon_play: world.load_asset("icons/circle-play-regular.svg"),
+            wall_icon: world.load_asset("icons/wall.svg"),
+            mirror_icon: world.load_asset("icons/mirror.svg"),
+            zone_icon: world.load_asset("icons/zone.svg"),
+            modifier_icon: world.load_asset("icons/modifier.svg"),
+            drum_icon: world.load_asset("icons/drum.svg"),
+        }
+    }
+}
+
+#[cfg(test)]
+impl CoreAssets {
+    /// Handles without files, for tests which run without an asset server.
+    pub fn empty() -> Self {
+        CoreAssets {
+            note_icon: Handle::default(),
+            activator_icon_pause: Handle::default(),
+            activator_icon_play: Handle::default(),
+            wall_icon: Handle::default(),
+            mirror_icon: Handle::default(),
+            zone_icon: Handle::default(),
+            modifier_icon: Handle::default(),
+            drum_icon: Handle::default(),
         }
     }
 }
@@ -76,51 +325,44 @@ pub struct ActivatorEnabledEvent {
 #[derive(Event, Debug, Deref)]
 struct ActivatorDisabledEvent(Entity);
 
-#[derive(Event, Debug)]
-struct ObjectActivatedEvent {
-    source: Option<Entity>,
-    object: Entity,
-}
-
 #[derive(Event, Debug)]
 struct AllPlayedEvent;
 
-/// Collects all events which represent an "activation" and map them to [`ObjectActivatedEvent`]s to
-/// handle similarly.
-fn collect_activation_events(
-    mut note_played: EventReader<NotePlayedEvent>,
-    mut activator_enabled: EventReader<ActivatorEnabledEvent>,
-    mut object_activated: EventWriter<ObjectActivatedEvent>,
-) {
-    note_played.read().for_each(|ev| {
-        object_activated.write(ObjectActivatedEvent {
-            source: Some(ev.source),
-            object: ev.note,
-        });
-    });
-    activator_enabled.read().for_each(|ev| {
-        object_activated.write(ObjectActivatedEvent {
-            source: ev.source,
-            object: ev.target,
-        });
-    });
-}
+/// Jump to the given time in seconds of the current execution or loop iteration.
+#[derive(Event, Debug)]
+pub struct ScrubEvent(pub f32);
 
 /// Set the model data up for one execution. We keep some data in memory to simplify calculations.
 fn enter_execution(
     activators: Query<(Entity, &ActivatorType)>,
-    mut enabled_activators: EventWriter<ActivatorEnabledEvent>,
+    mut loop_state: ResMut<LoopState>,
+    mut reached: ResMut<ReachedNotes>,
+    mut clock: ResMut<ExecutionClock>,
+    mut gates: Query<&mut GateInputs, With<Gate>>,
     mut commands: Commands,
 ) {
-    for (entity, activator) in &activators {
+    *loop_state = LoopState::default();
+    *reached = ReachedNotes::default();
+    // the playback rate is kept for the next execution
+    clock.elapsed = Duration::ZERO;
+    fire_main_activators(&activators, &mut gates, &mut commands);
+}
+
+fn fire_main_activators(
+    activators: &Query<(Entity, &ActivatorType)>,
+    gates: &mut Query<&mut GateInputs, With<Gate>>,
+    commands: &mut Commands,
+) {
+    // logic gates start without inputs
+    for mut inputs in gates {
+        *inputs = GateInputs::default();
+    }
+    for (entity, activator) in activators {
         if activator == &ActivatorType::Main {
-            // TODO duplication
-            let event = ActivatorEnabledEvent {
+            commands.trigger(ActivatorEnabledEvent {
                 source: None,
                 target: entity,
-            };
-            enabled_activators.write(event);
-            commands.trigger(event);
+            });
         }
     }
 }
@@ -128,119 +370,501 @@ fn enter_execution(
 /// Clear the game state after an execution.
 fn exit_execution(
     activators: Query<Entity, With<Activator>>,
+    waiting: Query<Entity, With<Waiting>>,
+    mut gates: Query<&mut GateInputs, With<Gate>>,
     mut events: EventWriter<ActivatorDisabledEvent>,
+    mut commands: Commands,
 ) {
     for entity in activators {
         events.write(ActivatorDisabledEvent(entity));
     }
+    for mut inputs in &mut gates {
+        *inputs = GateInputs::default();
+    }
+    for entity in &waiting {
+        commands.entity(entity).remove::<Waiting>();
+    }
+}
+
+fn tick_execution_clock(
+    time: Res<Time>,
+    state: Res<State<ExecutionState>>,
+    mut clock: ResMut<ExecutionClock>,
+) {
+    clock.tick(time.delta(), state.get());
+}
+
+/// Restore the state of every activator at the scrubbed time from the [`Prediction`]. Notes are
+/// not played while scrubbing.
+fn scrub_execution(
+    mut events: EventReader<ScrubEvent>,
+    prediction: Res<Prediction>,
+    config: Res<LevelConfig>,
+    activators: Query<(
+        Entity,
+        &ActivatorSpeed,
+        &ActivatorShape,
+        Option<&Pulse>,
+        Option<&Gate>,
+    )>,
+    objects: Query<(Entity, &Transform), With<Hittable>>,
+    walls: Query<(&Wall, &Transform)>,
+    zones: Query<(&Zone, &Transform)>,
+    assets: Res<CoreAssets>,
+    mut clock: ResMut<ExecutionClock>,
+    mut loop_state: ResMut<LoopState>,
+    mut commands: Commands,
+) {
+    let Some(ScrubEvent(time)) = events.read().last() else {
+        return;
+    };
+    let time = time.max(0.0);
+
+    *loop_state = LoopState::default();
+    clock.elapsed = Duration::from_secs_f32(time);
+    let walls = wall_segments(&walls);
+    let zones = zone_areas(&zones);
+
+    for (activator, speed, shape, pulse, gate) in &activators {
+        let Ok((_, activator_position)) = objects.get(activator) else {
+            continue;
+        };
+        if let Some(gate) = gate {
+            commands.entity(activator).try_insert(inputs_at(
+                gate,
+                activator,
+                &prediction.events,
+                time,
+            ));
+        }
+        let center = activator_position.translation.xy();
+        let sector = Sector::new(shape, activator_position);
+        let sweep = match shape {
+            ActivatorShape::Sweep { beats } => Some(sweep_speed(*beats, speed, &config)),
+            _ => None,
+        };
+        // a ring is compared by travel distance and a sweep by the angle it has turned
+        let key = |position: Vec2| match sweep {
+            Some(_) => sweep_angle(direction(activator_position), position - center),
+            None => travel_distance(center, position, &zones),
+        };
+        // every object a ring has not reached yet is still to be hit
+        let ring_at = |wave: Wave| {
+            let size =
+                wave.size + (time - wave.start) * sweep.unwrap_or_else(|| config.growth(speed));
+            let mut remaining: Vec<(Entity, f32)> = objects
+                .iter()
+                .filter(|(entity, _)| *entity != activator)
+                .map(|(entity, position)| (entity, key(position.translation.xy())))
+                .filter(|(_, distance)| *distance >= size)
+                .collect();
+            remaining.sort_by(|(_, d1), (_, d2)| d1.total_cmp(d2));
+            let mut echoes: Vec<Echo> = objects
+                .iter()
+                .filter(|(entity, _)| *entity != activator && sweep.is_none())
+                .flat_map(|(target, position)| {
+                    echo_distances(center, position.translation.xy(), &sector, &walls, &zones)
+                        .into_iter()
+                        .map(move |distance| Echo { target, distance })
+                })
+                .filter(|echo| echo.distance >= size)
+                .collect();
+            echoes.sort_by(|e1, e2| e1.distance.total_cmp(&e2.distance));
+            Ring {
+                size,
+                inactivated: remaining.into_iter().map(|(entity, _)| entity).collect(),
+                echoes,
+            }
+        };
+
+        let waves = prediction
+            .waves
+            .get(&activator)
+            .map(Vec::as_slice)
+            .unwrap_or_default();
+        if let Some(wave) = waves
+            .iter()
+            .find(|wave| wave.hit <= time && time < wave.start)
+        {
+            // a delay activator was hit, but its ring has not started yet
+            commands
+                .entity(activator)
+                .remove::<(InactivatedObjects, Echoes, PulseRings)>()
+                .try_insert(ActivatorState::Disabled)
+                .try_insert(ActivatorSize::zero())
+                .try_insert(Svg2d(assets.activator_icon_play.clone()))
+                .try_insert(Waiting {
+                    remaining: wave.start - time,
+                });
+            continue;
+        }
+        let current = waves.iter().rposition(|wave| wave.start <= time);
+        let next_pulse = current
+            .and_then(|index| waves.get(index + 1))
+            .filter(|next| current.is_some_and(|index| next.pulse == waves[index].pulse + 1));
+        let current = current
+            .map(|index| waves[index])
+            .filter(|wave| time < wave.end || next_pulse.is_some());
+        let Some(current) = current else {
+            commands
+                .entity(activator)
+                .remove::<(InactivatedObjects, Echoes, PulseRings, Waiting)>()
+                .try_insert(ActivatorState::Disabled)
+                .try_insert(ActivatorSize::zero())
+                .try_insert(Svg2d(assets.activator_icon_play.clone()));
+            continue;
+        };
+
+        let mut ring = ring_at(current);
+        if time >= current.end {
+            // the ring has hit everything and waits for the next pulse
+            ring.inactivated.clear();
+            ring.echoes.clear();
+        }
+        let mut activator = commands.entity(activator);
+        activator
+            .remove::<Waiting>()
+            .try_insert(ActivatorState::Enabled)
+            .try_insert(ActivatorSize::new(ring.size))
+            .try_insert(Svg2d(assets.activator_icon_pause.clone()))
+            .try_insert(InactivatedObjects(ring.inactivated))
+            .try_insert(Echoes(ring.echoes));
+
+        if let Some(pulse) = pulse {
+            let rings = prediction
+                .waves_at(activator.id(), time)
+                .into_iter()
+                .filter(|wave| wave.pulse < current.pulse)
+                .map(ring_at)
+                .collect();
+            activator.try_insert(PulseRings {
+                rings,
+                remaining: pulse.count.map(|it| it.saturating_sub(current.pulse + 1)),
+                until_next: next_pulse.map(|it| it.start - time).unwrap_or_else(|| {
+                    current.start + pulse.interval * config.beat_length() - time
+                }),
+            });
+        }
+    }
+}
+
+/// Start the ring of every delay activator whose delay is over.
+fn start_delayed_activators(
+    mut waiting: Query<(Entity, &mut Waiting)>,
+    clock: Res<ExecutionClock>,
+    mut commands: Commands,
+) {
+    for (entity, mut waiting) in &mut waiting {
+        waiting.remaining -= clock.delta().as_secs_f32();
+        if waiting.remaining > 0.0 {
+            continue;
+        }
+
+        // without a source the activator is enabled right away
+        commands.trigger(ActivatorEnabledEvent {
+            source: None,
+            target: entity,
+        });
+    }
 }
 
 /// The core game logic: increment activator and check for collisions
 fn execute_activator_and_check_collisions(
-    activators: Query<(Entity, &mut ActivatorSize, &ActivatorState, &Transform)>,
-    unplayed_objects: Query<&InactivatedObjects>,
-    notes: Query<&Note>,
+    activators: Query<(
+        Entity,
+        &mut ActivatorSize,
+        &ActivatorState,
+        &ActivatorLimit,
+        &ActivatorSpeed,
+        &ActivatorShape,
+        &Transform,
+    )>,
+    mut unplayed_objects: Query<&mut InactivatedObjects>,
+    mut echoes: Query<&mut Echoes>,
     positions: Query<&Transform>,
+    walls: Query<(&Wall, &Transform)>,
+    zones: Query<(&Zone, &Transform)>,
     config: Res<LevelConfig>,
-    time: Res<Time>,
-    mut play_note_events: EventWriter<NotePlayedEvent>,
-    mut enable_activator_events: EventWriter<ActivatorEnabledEvent>,
+    clock: Res<ExecutionClock>,
+    mut hits: EventWriter<ObjectHitEvent>,
     mut commands: Commands,
 ) {
-    for (activator, mut size, activator_state, activator_position) in activators {
-        // grow enabled activator size
-        if !activator_state.is_active() {
+    let walls = wall_segments(&walls);
+    let zones = zone_areas(&zones);
+    for (activator, mut size, activator_state, limit, speed, shape, activator_position) in
+        activators
+    {
+        // grow enabled activator size, sweeps turn instead
+        if !activator_state.is_active() || matches!(shape, ActivatorShape::Sweep { .. }) {
             continue;
         }
-        size.increment(time.delta().as_secs_f32() * config.grow_factor);
+        let growth = config.growth(speed);
+        let max_size = limit.max_size(growth * config.beat_length());
+        size.increment(clock.delta().as_secs_f32() * growth);
+        if let Some(max_size) = max_size {
+            size.limit(max_size);
+        }
 
-        let Ok(unplayed_objects_of_activator) = unplayed_objects.get(activator) else {
+        let Ok(mut unplayed_objects_of_activator) = unplayed_objects.get_mut(activator) else {
             // only test when unplayed objects are present
             // should not happen, since an activator is disabled in this condition
             continue;
         };
 
         // check collisions
+        let center = activator_position.translation.xy();
+        let sector = Sector::new(shape, activator_position);
+        let mut shadowed = vec![];
         for other in &unplayed_objects_of_activator.0 {
             let Ok(position) = positions.get(*other) else {
                 continue;
             };
 
-            if position
-                .translation
-                .xy()
-                .distance(activator_position.translation.xy())
-                < **size
-            {
-                // we can implement more types here, only activator cannot be matched with a query
-                match notes.get(*other) {
-                    // unplayed is a note
-                    Ok(_) => {
-                        play_note_events.write(NotePlayedEvent {
-                            source: activator,
-                            note: *other,
-                        });
-                    }
-                    _ => {
-                        // unplayed is another activator
-                        //TODO duplication
-                        let event = ActivatorEnabledEvent {
-                            source: Some(activator),
-                            target: *other,
-                        };
-                        commands.trigger(event);
-                        enable_activator_events.write(event);
-                    }
+            if travel_distance(center, position.translation.xy(), &zones) < **size {
+                if is_occluded(center, position.translation.xy(), &walls)
+                    || !sector.covers(position.translation.xy() - center)
+                {
+                    // the object is in the shadow of a wall or outside of the cone and is never
+                    // hit by this ring
+                    shadowed.push(*other);
+                    continue;
                 }
+                hit_object(activator, *other, &mut hits, &mut commands);
             } else {
                 // since the objects are sorted relative to the activator we can stop at the first
                 // one which is too far away
                 break;
             }
         }
+        unplayed_objects_of_activator
+            .0
+            .retain(|it| !shadowed.contains(it));
+
+        let mut echoes = echoes.get_mut(activator).ok();
+        for other in echoes
+            .as_mut()
+            .map(|it| reached_echoes(&mut it.0, **size))
+            .unwrap_or_default()
+        {
+            hit_object(activator, other, &mut hits, &mut commands);
+        }
+
+        if max_size.is_some_and(|max_size| **size >= max_size) {
+            // the ring ends at its limit and never reaches the remaining objects
+            unplayed_objects_of_activator.0.clear();
+            if let Some(echoes) = &mut echoes {
+                echoes.0.clear();
+            }
+        }
     }
 }
 
 /// Visualize the size of each activator.
 fn draw_activator_sizes(
     mut gizmos: Gizmos,
-    activators: Query<(&ActivatorState, &ActivatorSize, &ActivatorColor, &Transform)>,
+    config: Res<LevelConfig>,
+    activators: Query<(
+        &ActivatorState,
+        &ActivatorSize,
+        &ActivatorColor,
+        &ActivatorLimit,
+        &ActivatorSpeed,
+        &ActivatorShape,
+        &Transform,
+        Option<&PulseRings>,
+    )>,
+    zones: Query<(&Zone, &Transform)>,
 ) {
-    for (state, size, color, transform) in &activators {
-        if !state.is_active() {
+    let zones = zone_areas(&zones);
+    for (state, size, color, limit, speed, shape, transform, pulse) in &activators {
+        // the line of a sweep is drawn with its marker
+        if !state.is_active() || matches!(shape, ActivatorShape::Sweep { .. }) {
+            continue;
+        }
+
+        let center = transform.translation.xy();
+        let sector = Sector::new(shape, transform);
+        let color = Color::from(color);
+        draw_ring(&mut gizmos, center, **size, &sector, speed, &zones, color);
+        if let Some(max_size) = limit.max_size(config.growth(speed) * config.beat_length()) {
+            // the ring ends here
+            draw_ring(
+                &mut gizmos,
+                center,
+                max_size,
+                &sector,
+                &ActivatorSpeed::default(),
+                &zones,
+                color.with_alpha(0.3),
+            );
+        }
+        for ring in pulse.iter().flat_map(|it| &it.rings) {
+            draw_ring(
+                &mut gizmos,
+                center,
+                ring.size,
+                &sector,
+                speed,
+                &zones,
+                color,
+            );
+        }
+    }
+}
+
+/// Number of dashes of a slow ring.
+const RING_DASHES: u32 = 24;
+
+/// Draw a ring within its sector in the style of its speed. Faster rings are doubled, slower rings
+/// are dashed.
+fn draw_ring(
+    gizmos: &mut Gizmos,
+    center: Vec2,
+    size: f32,
+    sector: &Sector,
+    speed: &ActivatorSpeed,
+    zones: &[ZoneArea],
+    color: Color,
+) {
+    if !zones.is_empty() {
+        draw_wavefront(gizmos, center, size, sector, speed, zones, color);
+        return;
+    }
+
+    // arcs start at the y axis
+    let start = Vec2::Y.angle_to(sector.direction) - sector.width / 2.0;
+    let position = |angle: f32| Isometry2d::new(center, Rot2::radians(angle));
+
+    if **speed < 1.0 {
+        let dashes = (RING_DASHES as f32 * sector.width / TAU).ceil().max(1.0);
+        let step = sector.width / dashes;
+        for dash in 0..dashes as u32 {
+            gizmos
+                .arc_2d(
+                    position(start + dash as f32 * step),
+                    step / 2.0,
+                    size,
+                    color,
+                )
+                .resolution(4);
+        }
+        return;
+    }
+
+    gizmos
+        .arc_2d(position(start), sector.width, size, color)
+        .resolution(64);
+    if **speed > 1.0 && size > 4.0 {
+        gizmos
+            .arc_2d(position(start), sector.width, size - 4.0, color)
+            .resolution(64);
+    }
+}
+
+/// Number of points of a wavefront around a full circle.
+const WAVEFRONT_POINTS: u32 = 64;
+
+/// Draw a ring which may pass zones in the style of [`draw_ring`]. It is no circle anymore, so its
+/// wavefront is drawn through the points it reached in every direction.
+fn draw_wavefront(
+    gizmos: &mut Gizmos,
+    center: Vec2,
+    size: f32,
+    sector: &Sector,
+    speed: &ActivatorSpeed,
+    zones: &[ZoneArea],
+    color: Color,
+) {
+    let start = sector.direction.to_angle() - sector.width / 2.0;
+    let steps = (WAVEFRONT_POINTS as f32 * sector.width / TAU)
+        .ceil()
+        .max(1.0) as u32;
+    let wavefront = |size: f32| -> Vec<Vec2> {
+        (0..=steps)
+            .map(|step| {
+                let direction = Vec2::from_angle(start + sector.width * step as f32 / steps as f32);
+                center + direction * wavefront_distance(center, direction, size, zones)
+            })
+            .collect()
+    };
+
+    if **speed < 1.0 {
+        for dash in wavefront(size).windows(2).step_by(2) {
+            gizmos.line_2d(dash[0], dash[1], color);
+        }
+        return;
+    }
+    gizmos.linestrip_2d(wavefront(size), color);
+    if **speed > 1.0 && size > 4.0 {
+        gizmos.linestrip_2d(wavefront(size - 4.0), color);
+    }
+}
+
+/// Mark delay activators with a square, which is doubled while they wait.
+fn draw_delay_markers(
+    mut gizmos: Gizmos,
+    activators: Query<(&ActivatorType, &ActivatorColor, &Transform, Has<Waiting>)>,
+) {
+    for (activator, color, transform, waiting) in &activators {
+        if !matches!(activator, ActivatorType::Delay { .. }) {
             continue;
         }
 
         let position = Isometry2d::from_translation(transform.translation.xy());
-        gizmos.circle_2d(position, **size, color).resolution(64);
+        gizmos.rect_2d(position, Vec2::splat(32.0), color);
+        if waiting {
+            gizmos.rect_2d(position, Vec2::splat(26.0), color);
+        }
     }
 }
 
 /// If an activator hits an object it is removed from the list of unplayed objects for the activator
-fn handle_object_activated(
-    mut object_activated_event: EventReader<ObjectActivatedEvent>,
-    mut all_inactive: Query<&mut InactivatedObjects>,
+fn handle_object_hit(
+    mut hits: EventReader<ObjectHitEvent>,
+    mut all_inactive: Query<(&mut InactivatedObjects, &ActivatorSize, &ActivatorShape)>,
+    positions: Query<&Transform>,
+    zones: Query<(&Zone, &Transform)>,
 ) {
-    for event in object_activated_event.read() {
-        if let Some(activator) = event.source {
-            // source is only None if it is the MainActivator on enter_execution...
-
-            if let Ok(mut inactive) = all_inactive.get_mut(activator) {
-                inactive.0.retain(|it| it != &event.object);
-            }
+    let zones = zone_areas(&zones);
+    for event in hits.read() {
+        let Ok((mut inactive, size, shape)) = all_inactive.get_mut(event.source) else {
+            continue;
+        };
+        // an older ring of a pulse activator hits objects the current ring has not reached yet
+        if let (Ok(center), Ok(object)) = (positions.get(event.source), positions.get(event.object))
+            && size_to_reach(shape, center, object.translation.xy(), &zones) >= **size
+        {
+            continue;
         }
+        inactive.0.retain(|it| it != &event.object);
     }
 }
 
-/// After all notes are played the execution is done.
+/// After all activators are done the execution is done.
 fn check_all_played(
-    mut notes: Query<(Entity, &InactivatedObjects)>,
+    activators: Query<(
+        Entity,
+        &ActivatorState,
+        Option<&InactivatedObjects>,
+        Option<&Echoes>,
+        Option<&PulseRings>,
+    )>,
+    waiting: Query<(), With<Waiting>>,
     mut events: EventWriter<ActivatorDisabledEvent>,
     mut all_played_events: EventWriter<AllPlayedEvent>,
 ) {
-    let mut all_done = true;
-    for (entity, notes) in &mut notes {
-        if notes.0.is_empty() {
+    // the execution ends when no activator is active anymore, a waiting delay activator has not
+    // played its ring yet
+    let mut all_done = waiting.is_empty();
+    for (entity, state, notes, echoes, pulse) in &activators {
+        if !state.is_active() {
+            continue;
+        }
+        // a pulse activator is done after its last ring
+        if notes.is_none_or(|it| it.0.is_empty())
+            && echoes.is_none_or(|it| it.0.is_empty())
+            && pulse.is_none_or(PulseRings::is_done)
+        {
             events.write(ActivatorDisabledEvent(entity));
         } else {
             all_done = false;
@@ -254,19 +878,114 @@ fn check_all_played(
 
 fn handle_all_played(
     mut events: EventReader<AllPlayedEvent>,
+    config: Res<LevelConfig>,
+    clock: Res<ExecutionClock>,
+    notes: Query<Entity, With<Note>>,
+    mut reached: ResMut<ReachedNotes>,
+    mut loop_state: ResMut<LoopState>,
     mut next_state: ResMut<NextState<GameState>>,
 ) {
-    for _ in events.read() {
+    if events.read().count() == 0 {
+        return;
+    }
+
+    if !config.looping.enabled {
+        reached.report(&notes);
         next_state.set(GameState::Build);
+        return;
+    }
+
+    if loop_state.next.is_none() {
+        reached.report(&notes);
+        loop_state.next = Some(if config.looping.sync_to_background {
+            NextIteration::BackgroundCycle
+        } else {
+            NextIteration::After(until_next_bar(clock.elapsed, config.bar_length()))
+        });
+    }
+}
+
+fn remember_played_notes(
+    mut note_played: EventReader<NotePlayedEvent>,
+    mut reached: ResMut<ReachedNotes>,
+) {
+    for event in note_played.read() {
+        reached.played.insert(event.note);
+    }
+}
+
+/// Mark the notes which were not reached in the last execution with a cross.
+fn draw_unreached_notes(
+    mut gizmos: Gizmos,
+    reached: Res<ReachedNotes>,
+    positions: Query<&Transform, With<Note>>,
+) {
+    for note in &reached.unreached {
+        let Ok(transform) = positions.get(*note) else {
+            continue;
+        };
+        let center = transform.translation.xy();
+        let color = Color::srgb(1.0, 0.2, 0.2);
+        gizmos.line_2d(
+            center - Vec2::splat(12.0),
+            center + Vec2::splat(12.0),
+            color,
+        );
+        gizmos.line_2d(
+            center + Vec2::new(-12.0, 12.0),
+            center + Vec2::new(12.0, -12.0),
+            color,
+        );
+    }
+}
+
+/// Time from `elapsed` until the end of the bar, so a loop iteration lasts whole bars.
+fn until_next_bar(elapsed: Duration, bar_length: f32) -> Duration {
+    let elapsed = elapsed.as_secs_f32();
+    let bars = (elapsed / bar_length).ceil().max(1.0);
+    Duration::from_secs_f32((bars * bar_length - elapsed).max(0.0))
+}
+
+/// In loop mode the main activators fire again after the last note was played.
+fn restart_loop(
+    mut clock: ResMut<ExecutionClock>,
+    mut reached: ResMut<ReachedNotes>,
+    mut background: EventReader<BackgroundCycleEvent>,
+    mut loop_state: ResMut<LoopState>,
+    activators: Query<(Entity, &ActivatorType)>,
+    mut gates: Query<&mut GateInputs, With<Gate>>,
+    mut commands: Commands,
+) {
+    let background_cycle = background.read().count() > 0;
+
+    let restart = match &mut loop_state.next {
+        None => false,
+        Some(NextIteration::After(remaining)) => {
+            *remaining = remaining.saturating_sub(clock.delta());
+            remaining.is_zero()
+        }
+        Some(NextIteration::BackgroundCycle) => background_cycle,
+    };
+
+    if restart {
+        *loop_state = LoopState::default();
+        reached.played.clear();
+        clock.elapsed = Duration::ZERO;
+        fire_main_activators(&activators, &mut gates, &mut commands);
     }
 }
 
 fn activate_activator(
     cause: Trigger<ActivatorEnabledEvent>,
-    activators: Query<Entity, With<Activator>>,
-    notes: Query<Entity, With<Note>>,
+    types: Query<&ActivatorType>,
+    shapes: Query<&ActivatorShape>,
+    portals: Query<&Portal>,
+    objects: Query<Entity, With<Hittable>>,
     positions: Query<&Transform>,
+    walls: Query<(&Wall, &Transform)>,
+    zones: Query<(&Zone, &Transform)>,
     assets: Res<CoreAssets>,
+    config: Res<LevelConfig>,
     mut commands: Commands,
     state: Res<State<GameState>>,
 ) {
@@ -276,45 +995,414 @@ fn activate_activator(
         return;
     }
 
-    // collect all objects in a list template
-    let mut inactive: Vec<Entity> = Vec::new();
-    for object in &notes {
-        inactive.push(object);
-    }
-    for object in &activators {
-        inactive.push(object);
-    }
-
-    let activator = cause.target;
+    let zones = zone_areas(&zones);
+    // a ring which reaches a portal continues from the linked one
+    let (activator, size) = match (portals.get(cause.target), cause.source) {
+        (Ok(portal), Some(source)) => {
+            let Some(link) = portal_exit(portal, source) else {
+                return;
+            };
+            let size = match (positions.get(source), positions.get(cause.target)) {
+                (Ok(from), Ok(to)) => {
+                    portal_ring_size(portal, from.translation.xy(), to.translation.xy(), &zones)
+                }
+                _ => 0.0,
+            };
+            (link, size)
+        }
+        _ => (cause.target, 0.0),
+    };
     let Ok(mut target) = commands.get_entity(activator) else {
         return;
     };
+
+    // a hit delay activator stops its current ring and starts the next one later
+    if let Ok(ActivatorType::Delay { beats }) = types.get(activator)
+        && cause.source.is_some()
+    {
+        target
+            .remove::<(InactivatedObjects, Echoes)>()
+            .try_insert(ActivatorState::Disabled)
+            .try_insert(ActivatorSize::zero())
+            .try_insert(Svg2d(assets.activator_icon_play.clone()))
+            .try_insert(Waiting {
+                remaining: beats * config.beat_length(),
+            });
+        return;
+    }
+
+    let shape = shapes.get(activator).copied().unwrap_or_default();
+    // a sweep always starts at its direction
+    let size = match shape {
+        ActivatorShape::Sweep { .. } => 0.0,
+        _ => size,
+    };
+    let mut result = sorted_by_shape(activator, &shape, &objects, &positions, &zones);
+    let mut echoes = sorted_echoes(
+        activator,
+        &shape,
+        &objects,
+        &positions,
+        &wall_segments(&walls),
+        &zones,
+    );
+    if size > 0.0
+        && let Ok(center) = positions.get(activator)
+    {
+        // the objects inside a kept ring are not hit again
+        let center = center.translation.xy();
+        result.retain(|it| {
+            positions
+                .get(*it)
+                .is_ok_and(|it| travel_distance(center, it.translation.xy(), &zones) >= size)
+        });
+        echoes.retain(|it| it.distance >= size);
+    }
+
+    // enable the activator
+    target
+        .remove::<Waiting>()
+        .try_insert(ActivatorState::Enabled)
+        .try_insert(ActivatorSize::new(size))
+        .try_insert(Svg2d(assets.activator_icon_pause.clone()))
+        .try_insert(InactivatedObjects(result))
+        .try_insert(Echoes(echoes));
+}
+
+/// Show the icon at the position of the object, scaled to the size of the other objects.
+fn insert_icon(entity: &mut EntityWorldMut, icon: impl Fn(&CoreAssets) -> Handle<Svg>, scale: f32) {
+    let icon = icon(entity.world().resource::<CoreAssets>());
+    entity.insert((Svg2d(icon), Origin::Center));
+    if let Some(mut transform) = entity.get_mut::<Transform>() {
+        transform.scale = Vec3::splat(scale);
+    }
+}
+
+pub(super) fn spawn_note(entity: &mut EntityWorldMut) {
+    insert_icon(entity, |assets| assets.note_icon.clone(), 0.025);
+    entity.insert(Note);
+}
+
+fn spawn_percussion(entity: &mut EntityWorldMut) {
+    insert_icon(entity, |assets| assets.drum_icon.clone(), 0.025);
+    entity.insert(Percussion::default());
+}
+
+/// Drum of a percussion object.
+const DRUM: ObjectField = ObjectField {
+    name: "Drum",
+    steps: None,
+    value: |object| object.get::<Percussion>().map(|it| format!("{it:?}")),
+    edit: |object, step| {
+        if let Some(mut percussion) = object.get_mut::<Percussion>() {
+            let drums = [
+                Percussion::Kick,
+                Percussion::Snare,
+                Percussion::Hat,
+                Percussion::Clap,
+            ];
+            if let Some(next) = cycle(&drums, Some(*percussion), step) {
+                *percussion = next;
+            }
+        }
+    },
+};
+
+/// Beats a note sounds or its whole sample.
+const LENGTH: ObjectField = ObjectField {
+    name: "Length",
+    steps: Some(&[-1.0, -0.25, 0.25, 1.0]),
+    value: |object| match object.get::<NoteLength>() {
+        Some(length) => Some(format!("{} beats", length.0)),
+        None => Some("full".to_string()),
+    },
+    edit: |object, step| match step_limit(object.get::<NoteLength>().map(|it| it.0), step) {
+        Some(length) => {
+            object.insert(NoteLength(length));
+        }
+        None => {
+            object.remove::<NoteLength>();
+        }
+    },
+};
+
+/// The variations of a note, its pitch follows from its position.
+#[derive(Serialize, Deserialize)]
+struct SavedNote {
+    chord: Option<Chord>,
+    percussion: Option<Percussion>,
+    length: Option<NoteLength>,
+    motion: Option<SavedMotion>,
+}
+
+fn save_note(object: &EntityRef, entities: &SavedEntities) -> SavedNote {
+    SavedNote {
+        chord: object.get::<Chord>().copied(),
+        percussion: object.get::<Percussion>().copied(),
+        length: object.get::<NoteLength>().copied(),
+        motion: save_motion(object, entities),
+    }
+}
+
+fn load_note(note: SavedNote, entity: &mut EntityWorldMut, entities: &SavedEntities) {
+    if let Some(chord) = note.chord {
+        entity.insert(chord);
+    }
+    if let Some(percussion) = note.percussion {
+        entity.insert(percussion);
+    }
+    if let Some(length) = note.length {
+        entity.insert(length);
+    }
+    load_motion(note.motion, entity, entities);
+}
+
+pub(super) fn activator_kind(object: &EntityRef) -> ObjectKind {
+    ObjectKind::Activator {
+        main: object.get::<ActivatorType>() == Some(&ActivatorType::Main),
+    }
+}
+
+pub(super) fn activator_icon(assets: &CoreAssets) -> Handle<Svg> {
+    assets.activator_icon_play.clone()
+}
+
+const COLOR: ObjectField = ObjectField {
+    name: "Color",
+    steps: None,
+    value: |object| {
+        object
+            .get::<ActivatorColor>()
+            .map(|it| format!("{:?}", it.0))
+    },
+    edit: |object, step| {
+        if let Some(mut color) = object.get_mut::<ActivatorColor>()
+            && let Some(next) = cycle(&ColorPalette::enumerate(), Some(color.0), step)
+        {
+            color.0 = next;
+        }
+    },
+};
+
+/// Available speed multipliers of a ring or zone, which play subdivisions of the beat.
+pub(super) const SPEEDS: [f32; 8] = [0.25, 0.5, 0.75, 1.0, 1.5, 2.0, 3.0, 4.0];
+
+/// Multiplier of the speed the ring grows with.
+const SPEED: ObjectField = ObjectField {
+    name: "Speed",
+    steps: None,
+    value: |object| {
+        object
+            .get::<ActivatorSpeed>()
+            .map(|it| format!("x{}", it.0))
+    },
+    edit: |object, step| {
+        if let Some(mut speed) = object.get_mut::<ActivatorSpeed>()
+            && let Some(next) = cycle(&SPEEDS, Some(speed.0), step)
+        {
+            speed.0 = next;
+        }
+    },
+};
+
+/// Maximum size of the ring or unlimited.
+const MAX_RADIUS: ObjectField = ObjectField {
+    name: "Max Radius",
+    steps: Some(&[-50.0, -10.0, 10.0, 50.0]),
+    value: |object| match object.get::<ActivatorLimit>()?.radius {
+        Some(radius) => Some(format!("{radius}")),
+        None => Some("unlimited".to_string()),
+    },
+    edit: |object, step| {
+        if let Some(mut limit) = object.get_mut::<ActivatorLimit>() {
+            limit.radius = step_limit(limit.radius, step);
+        }
+    },
+};
+
+/// Maximum lifetime of the ring in beats or unlimited.
+const LIFETIME: ObjectField = ObjectField {
+    name: "Lifetime",
+    steps: Some(&[-1.0, -0.25, 0.25, 1.0]),
+    value: |object| match object.get::<ActivatorLimit>()?.lifetime {
+        Some(lifetime) => Some(format!("{lifetime} beats")),
+        None => Some("unlimited".to_string()),
+    },
+    edit: |object, step| {
+        if let Some(mut limit) = object.get_mut::<ActivatorLimit>() {
+            limit.lifetime = step_limit(limit.lifetime, step);
+        }
+    },
+};
+
+/// Beats a delay activator waits after it was hit.
+const DELAY: ObjectField = ObjectField {
+    name: "Delay",
+    steps: Some(&[-1.0, -0.25, 0.25, 1.0]),
+    value: |object| match object.get::<ActivatorType>()? {
+        ActivatorType::Delay { beats } => Some(format!("{beats} beats")),
+        _ => None,
+    },
+    edit: |object, step| {
+        if let Some(mut activator) = object.get_mut::<ActivatorType>()
+            && let ActivatorType::Delay { beats } = activator.as_mut()
+        {
+            *beats = (*beats + step).max(0.0);
+        }
+    },
+};
+
+/// Change a limit of a ring or the length of a note. Unlimited is right below zero.
+fn step_limit(limit: Option<f32>, step: f32) -> Option<f32> {
+    match limit {
+        None if step < 0.0 => None,
+        None => Some(step),
+        Some(limit) => Some(limit + step).filter(|it| *it > 0.0),
+    }
+}
+
+/// Placed activators are passive and get a color by their position.
+pub(super) fn spawn_activator(entity: &mut EntityWorldMut) {
+    insert_icon(entity, activator_icon, 0.05);
+    let position = entity
+        .get::<Transform>()
+        .map(|it| it.translation.xy())
+        .unwrap_or_default();
+    entity.insert((
+        ActivatorType::Passive,
+        ActivatorColor(ColorPalette::get_random(position)),
+    ));
+}
+
+fn spawn_delay_activator(entity: &mut EntityWorldMut) {
+    spawn_activator(entity);
+    entity.insert(ActivatorType::Delay { beats: 1.0 });
+}
+
+/// The properties of an activator.
+#[derive(Serialize, Deserialize)]
+struct SavedActivator {
+    kind: ActivatorType,
+    color: ActivatorColor,
+    shape: ActivatorShape,
+    speed: ActivatorSpeed,
+    limit: ActivatorLimit,
+    pulse: Option<Pulse>,
+    gate: Option<Gate>,
+    keep_size: Option<bool>,
+    /// index of the linked portal in the saved level
+    link: Option<usize>,
+    motion: Option<SavedMotion>,
+}
+
+fn save_activator(object: &EntityRef, entities: &SavedEntities) -> SavedActivator {
+    SavedActivator {
+        kind: object.get().copied().unwrap_or_default(),
+        color: object.get().copied().unwrap_or_default(),
+        shape: object.get().copied().unwrap_or_default(),
+        speed: object.get().copied().unwrap_or_default(),
+        limit: object.get().copied().unwrap_or_default(),
+        pulse: object.get().copied(),
+        gate: object.get().copied(),
+        keep_size: object.get::<Portal>().map(|it| it.keep_size),
+        link: object
+            .get::<Portal>()
+            .and_then(|it| it.link)
+            .and_then(|it| entities.index(it)),
+        motion: save_motion(object, entities),
+    }
+}
+
+fn load_activator(
+    activator: SavedActivator,
+    entity: &mut EntityWorldMut,
+    entities: &SavedEntities,
+) {
+    entity.insert((
+        activator.kind,
+        activator.color,
+        activator.shape,
+        activator.speed,
+        activator.limit,
+    ));
+    if let Some(pulse) = activator.pulse {
+        entity.insert(pulse);
+    }
+    if let Some(gate) = activator.gate {
+        entity.insert(gate);
+    }
+    // the portals were linked in the order they were spawned, the saved pairs replace that
+    if let Some(keep_size) = activator.keep_size
+        && let Some(mut portal) = entity.get_mut::<Portal>()
+    {
+        *portal = Portal {
+            link: activator.link.and_then(|it| entities.entity(it)),
+            keep_size,
+        };
+    }
+    load_motion(activator.motion, entity, entities);
+}
+
+/// A hit note is played. Chords and percussion are notes as well, the music decides what they
+/// sound like.
+fn play_note(hit: Trigger<WaveHitEvent>, mut play_note_events: EventWriter<NotePlayedEvent>) {
+    play_note_events.write(NotePlayedEvent {
+        source: hit.source,
+        note: hit.target(),
+    });
+}
+
+/// A hit activator is enabled, unless it is a logic gate which waits for more hits.
+fn enable_hit_activator(
+    hit: Trigger<WaveHitEvent>,
+    mut gates: Query<(&Gate, &mut GateInputs)>,
+    mut commands: Commands,
+) {
+    let target = hit.target();
+    let enabled = gates
+        .get_mut(target)
+        .map(|(gate, mut inputs)| inputs.hit(gate, hit.source))
+        .unwrap_or(true);
+    if enabled {
+        commands.trigger(ActivatorEnabledEvent {
+            source: Some(hit.source),
+            target,
+        });
+    }
+}
+
+/// All other objects as the unplayed objects list for the activator. This list is sorted by
+/// travel distance from the activator.
+pub(super) fn sorted_objects(
+    activator: Entity,
+    objects: &Query<Entity, With<Hittable>>,
+    positions: &Query<&Transform>,
+    zones: &[ZoneArea],
+) -> Vec<Entity> {
     let activator_position = positions
         .get(activator)
         .expect("sort unplayed objects for activator: activator must have a position");
 
-    // add all other objects to the unplayed objects list for this activator.
-    // this list is sorted by distance to the activator.
-    let mut result: Vec<Entity> = inactive.clone();
-    result.retain(|it| it != &activator);
+    let mut result: Vec<Entity> = objects.iter().filter(|it| it != &activator).collect();
     result.sort_by(|e1, e2| {
-        distance_for_sort(activator_position.translation.xy(), e1, e2, &positions)
+        distance_for_sort(
+            activator_position.translation.xy(),
+            e1,
+            e2,
+            positions,
+            zones,
+        )
     });
-
-    // enable the activator
-    target
-        .insert(ActivatorState::Enabled)
-        .insert(ActivatorSize::zero())
-        .insert(Svg2d(assets.activator_icon_pause.clone()))
-        .insert(InactivatedObjects(result));
+    result
 }
 
-/// Compare the two objects by its distance to an activator.
+/// Compare the two objects by its travel distance from an activator.
 fn distance_for_sort(
     activator_position: Vec2,
     e1: &Entity,
     e2: &Entity,
     positions: &Query<&Transform>,
+    zones: &[ZoneArea],
 ) -> Ordering {
     let pos1 = positions
         .get(*e1)
@@ -327,8 +1415,11 @@ fn distance_for_sort(
         .translation
         .xy();
 
-    pos1.distance(activator_position)
-        .total_cmp(&pos2.distance(activator_position))
+    travel_distance(activator_position, pos1, zones).total_cmp(&travel_distance(
+        activator_position,
+        pos2,
+        zones,
+    ))
 }
 
 fn disable_activator(
@@ -337,12 +1428,440 @@ fn disable_activator(
     mut commands: Commands,
 ) {
     for event in events.read() {
-        commands
-            .get_entity(event.0)
-            .expect("activator should exist")
-            .remove::<InactivatedObjects>()
-            .insert(ActivatorState::Disabled)
-            .insert(ActivatorSize::zero())
-            .insert(Svg2d(assets.activator_icon_play.clone()));
+        let Ok(mut activator) = commands.get_entity(event.0) else {
+            // the activator was deleted during the execution
+            continue;
+        };
+        activator
+            .remove::<(InactivatedObjects, Echoes, PulseRings)>()
+            .try_insert(ActivatorState::Disabled)
+            .try_insert(ActivatorSize::zero())
+            .try_insert(Svg2d(assets.activator_icon_play.clone()));
+    }
+}
+
+/// An object placed during the execution is added to every active activator whose wave has not
+/// passed it yet, so it is still hit.
+pub(super) fn track_added_object<T: Component>(
+    trigger: Trigger<OnAdd, T>,
+    mut activators: Query<(
+        Entity,
+        &ActivatorSize,
+        &ActivatorShape,
+        &mut InactivatedObjects,
+    )>,
+    mut pulses: Query<(Entity, &ActivatorShape, &mut PulseRings)>,
+    positions: Query<&Transform>,
+    zones: Query<(&Zone, &Transform)>,
+) {
+    let object = trigger.target();
+    let Ok(object_position) = positions.get(object) else {
+        return;
+    };
+    let object_position = object_position.translation.xy();
+    let zones = zone_areas(&zones);
+
+    for (activator, size, shape, mut inactive) in &mut activators {
+        if activator == object {
+            continue;
+        }
+        track_object(
+            &mut inactive.0,
+            **size,
+            shape,
+            activator,
+            object,
+            object_position,
+            &positions,
+            &zones,
+        );
+    }
+    for (activator, shape, mut pulse) in &mut pulses {
+        if activator == object {
+            continue;
+        }
+        for ring in &mut pulse.rings {
+            track_object(
+                &mut ring.inactivated,
+                ring.size,
+                shape,
+                activator,
+                object,
+                object_position,
+                &positions,
+                &zones,
+            );
+        }
+    }
+}
+
+/// Add the object to the unplayed objects of a wave, unless the wave already passed it.
+fn track_object(
+    inactive: &mut Vec<Entity>,
+    size: f32,
+    shape: &ActivatorShape,
+    activator: Entity,
+    object: Entity,
+    object_position: Vec2,
+    positions: &Query<&Transform>,
+    zones: &[ZoneArea],
+) {
+    if inactive.contains(&object) {
+        return;
+    }
+    let Ok(activator_transform) = positions.get(activator) else {
+        return;
+    };
+    let reached_at = size_to_reach(shape, activator_transform, object_position, zones);
+    if reached_at < size {
+        // the wave already passed the object
+        return;
+    }
+
+    // keep the list sorted by travel distance or angle from the activator
+    let index = inactive.partition_point(|it| {
+        positions
+            .get(*it)
+            .map(|it| {
+                size_to_reach(shape, activator_transform, it.translation.xy(), zones) <= reached_at
+            })
+            .unwrap_or(true)
+    });
+    inactive.insert(index, object);
+}
+
+/// A deleted object can no longer be hit, so it is removed from all activators.
+pub(super) fn forget_removed_object<T: Component>(
+    trigger: Trigger<OnRemove, T>,
+    mut activators: Query<&mut InactivatedObjects>,
+    mut echoes: Query<&mut Echoes>,
+    mut pulses: Query<&mut PulseRings>,
+) {
+    let object = trigger.target();
+    for mut inactive in &mut activators {
+        inactive.0.retain(|it| it != &object);
+    }
+    for mut echoes in &mut echoes {
+        echoes.0.retain(|it| it.target != object);
+    }
+    for mut pulse in &mut pulses {
+        for ring in &mut pulse.rings {
+            ring.inactivated.retain(|it| it != &object);
+            ring.echoes.retain(|it| it.target != object);
+        }
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use crate::core::model::Motion;
+    use crate::core::object::{ObjectItems, load_objects, save_objects, spawn_item};
+    use crate::music::model::NaturalMinorScale;
+    use crate::state::GameStatePlugin;
+    use bevy::gizmos::GizmoPlugin;
+    use bevy::state::app::StatesPlugin;
+    use bevy::time::TimeUpdateStrategy;
+
+    /// Time of one frame of an [`execution_app`].
+    const FRAME: Duration = Duration::from_millis(10);
+    /// An execution of a test is stopped after this many frames.
+    const MAX_FRAMES: usize = 3000;
+
+    /// The notes played in the current execution with the seconds they were played at.
+    #[derive(Resource, Default)]
+    pub(crate) struct PlayedNotes(pub(crate) Vec<(f32, Entity)>);
+
+    fn record_played_notes(
+        mut events: EventReader<NotePlayedEvent>,
+        clock: Res<ExecutionClock>,
+        mut played: ResMut<PlayedNotes>,
+    ) {
+        for event in events.read() {
+            played.0.push((clock.elapsed.as_secs_f32(), event.note));
+        }
+    }
+
+    /// An app which runs the core game in the build mode without rendering and audio, frames take
+    /// a fixed time. Rings grow 100 units per second.
+    pub(crate) fn execution_app() -> App {
+        let mut app = App::new();
+        app.add_plugins((MinimalPlugins, AssetPlugin::default(), StatesPlugin))
+            // the gizmos load their shaders even without rendering
+            .init_asset::<Shader>()
+            .add_plugins((GizmoPlugin, GameStatePlugin))
+            .insert_resource(TimeUpdateStrategy::ManualDuration(FRAME))
+            .insert_resource(CoreAssets::empty())
+            .insert_resource(LevelConfig {
+                grow_factor: 100.0,
+                scale: Box::new(NaturalMinorScale::new(crate::music::model::Note::A)),
+                bpm: 60.0,
+                release: 0.1,
+                looping: Looping {
+                    enabled: false,
+                    sync_to_background: false,
+                },
+                script: None,
+                item_limits: Vec::new(),
+            })
+            .init_resource::<PlayedNotes>()
+            .add_event::<BackgroundCycleEvent>()
+            .add_plugins(CoreGamePlugin)
+            .add_systems(PostUpdate, record_played_notes);
+        // pass the setup states
+        while app
+            .world()
+            .get_resource::<State<GameState>>()
+            .map(|it| *it.get())
+            != Some(GameState::Build)
+        {
+            app.update();
+        }
+        app
+    }
+
+    /// Run one execution until it returns to the build mode and return the played notes.
+    /// `during` is called before every frame with the elapsed seconds of the execution.
+    pub(crate) fn execute(
+        app: &mut App,
+        mut during: impl FnMut(&mut World, f32),
+    ) -> Vec<(f32, Entity)> {
+        // the prediction of the objects is updated in the build mode
+        app.update();
+        app.world_mut().resource_mut::<PlayedNotes>().0.clear();
+        app.world_mut()
+            .resource_mut::<NextState<GameState>>()
+            .set(GameState::Execute);
+        app.update();
+        for _ in 0..MAX_FRAMES {
+            let elapsed = app.world().resource::<ExecutionClock>().elapsed();
+            during(app.world_mut(), elapsed.as_secs_f32());
+            app.update();
+            if *app.world().resource::<State<GameState>>().get() == GameState::Build {
+                return app
+                    .world_mut()
+                    .resource_mut::<PlayedNotes>()
+                    .0
+                    .drain(..)
+                    .collect();
+            }
+        }
+        panic!("the execution ends");
+    }
+
+    /// Place an object by the name of its item.
+    pub(crate) fn place(world: &mut World, item: &str, x: f32, y: f32) -> Entity {
+        let item = *world.resource::<ObjectItems>().get(item).unwrap();
+        spawn_item(world, &item, Vec2::new(x, y))
+    }
+
+    /// The main activator of the level.
+    pub(crate) fn spawn_main(world: &mut World, x: f32, y: f32) -> Entity {
+        world
+            .spawn((
+                ActivatorType::Main,
+                Transform::from_translation(Vec3::new(x, y, 0.0)),
+            ))
+            .id()
+    }
+
+    #[test]
+    fn test_execution_clock() {
+        let mut clock = ExecutionClock::default();
+        let frame = Duration::from_millis(250);
+
+        clock.tick(frame, &ExecutionState::Running);
+        assert_eq!(frame, clock.delta());
+
+        clock.faster();
+        clock.tick(frame, &ExecutionState::Running);
+        assert_eq!(frame * 2, clock.delta());
+
+        clock.tick(frame, &ExecutionState::Paused);
+        assert_eq!(Duration::ZERO, clock.delta());
+
+        clock.step();
+        clock.tick(frame, &ExecutionState::Paused);
+        assert_eq!(STEP, clock.delta());
+        clock.tick(frame, &ExecutionState::Paused);
+        assert_eq!(Duration::ZERO, clock.delta());
+
+        assert_eq!(frame * 3 + STEP, clock.elapsed);
+    }
+
+    #[test]
+    fn test_playback_rate_is_limited() {
+        let mut clock = ExecutionClock::default();
+        for _ in 0..10 {
+            clock.faster();
+        }
+        assert_eq!(4.0, clock.rate());
+        for _ in 0..10 {
+            clock.slower();
+        }
+        assert_eq!(0.25, clock.rate());
+    }
+
+    #[test]
+    fn test_step_limit() {
+        assert_eq!(Some(10.0), step_limit(None, 10.0));
+        assert_eq!(None, step_limit(None, -10.0));
+        assert_eq!(Some(60.0), step_limit(Some(50.0), 10.0));
+        assert_eq!(None, step_limit(Some(10.0), -10.0));
+    }
+
+    #[test]
+    fn test_until_next_bar() {
+        let bar = 2.0;
+        let assert_secs = |expected: f32, actual: Duration| {
+            assert!((expected - actual.as_secs_f32()).abs() < 0.001);
+        };
+
+        assert_secs(2.0, until_next_bar(Duration::ZERO, bar));
+        assert_secs(1.5, until_next_bar(Duration::from_secs_f32(0.5), bar));
+        assert_secs(0.0, until_next_bar(Duration::from_secs_f32(2.0), bar));
+        assert_secs(0.5, until_next_bar(Duration::from_secs_f32(5.5), bar));
+    }
+
+    /// An app which saves and loads notes and activators.
+    fn save_app() -> App {
+        let mut app = App::new();
+        app.insert_resource(CoreAssets::empty())
+            .register_object(
+                ObjectType::<Note>::new()
+                    .item("Note", |assets| assets.note_icon.clone(), spawn_note)
+                    .save(save_note, load_note),
+            )
+            .register_object(
+                ObjectType::<Activator>::new()
+                    .item("Activator", activator_icon, spawn_activator)
+                    .save(save_activator, load_activator),
+            )
+            .add_plugins(PortalPlugin);
+        app
+    }
+
+    #[test]
+    fn test_saved_portals_keep_their_partners() {
+        let mut app = save_app();
+        let world = app.world_mut();
+        let item = *world.resource::<ObjectItems>().get("Portal").unwrap();
+        let place = |world: &mut World, x: f32| spawn_item(world, &item, Vec2::new(x, 0.0));
+
+        // A and B, C and D are linked, then B is replaced by E
+        let a = place(world, 0.0);
+        let b = place(world, 1.0);
+        let c = place(world, 2.0);
+        let d = place(world, 3.0);
+        world.despawn(b);
+        let e = place(world, 4.0);
+        let link = |world: &World, portal| world.get::<Portal>(portal).unwrap().link;
+        assert_eq!(Some(e), link(world, a));
+
+        let content = save_objects(
+            world,
+            [(e, "Portal"), (d, "Portal"), (c, "Portal"), (a, "Portal")],
+        )
+        .unwrap();
+        for portal in [a, c, d, e] {
+            world.despawn(portal);
+        }
+        let loaded: Vec<Entity> = load_objects(world, &content)
+            .unwrap()
+            .into_iter()
+            .map(|(entity, _)| entity)
+            .collect();
+
+        let [e, d, c, a] = loaded[..] else {
+            panic!("all portals are loaded");
+        };
+        assert_eq!(Some(e), link(world, a));
+        assert_eq!(Some(a), link(world, e));
+        assert_eq!(Some(d), link(world, c));
+        assert_eq!(Some(c), link(world, d));
+    }
+
+    #[test]
+    fn test_saved_objects_keep_their_motion() {
+        let mut app = save_app();
+        let world = app.world_mut();
+        let main = world.spawn((Activator, ActivatorType::Main)).id();
+        let items = world.resource::<ObjectItems>();
+        let (note, activator) = (
+            *items.get("Note").unwrap(),
+            *items.get("Activator").unwrap(),
+        );
+
+        let center = spawn_item(world, &activator, Vec2::new(100.0, 0.0));
+        let orbiting = spawn_item(world, &note, Vec2::new(0.0, 50.0));
+        let around_main = spawn_item(world, &activator, Vec2::new(0.0, 80.0));
+        let path = Motion::Path {
+            offset: Vec2::new(30.0, 40.0),
+            beats: 2.0,
+        };
+        world
+            .entity_mut(orbiting)
+            .insert(Motion::Orbit { center, beats: 4.0 });
+        world.entity_mut(around_main).insert(Motion::Orbit {
+            center: main,
+            beats: 3.0,
+        });
+        world.entity_mut(center).insert(path);
+
+        let placed = [
+            (orbiting, "Note"),
+            (around_main, "Activator"),
+            (center, "Activator"),
+        ];
+        let content = save_objects(world, placed).unwrap();
+        for (entity, _) in placed {
+            world.despawn(entity);
+        }
+        let loaded: Vec<Entity> = load_objects(world, &content)
+            .unwrap()
+            .into_iter()
+            .map(|(entity, _)| entity)
+            .collect();
+
+        let [orbiting, around_main, center] = loaded[..] else {
+            panic!("all objects are loaded");
+        };
+        let motion = |entity| world.get::<Motion>(entity).copied();
+        assert_eq!(Some(Motion::Orbit { center, beats: 4.0 }), motion(orbiting));
+        // the main activator is not saved, it belongs to the level
+        assert_eq!(
+            Some(Motion::Orbit {
+                center: main,
+                beats: 3.0
+            }),
+            motion(around_main)
+        );
+        assert_eq!(Some(path), motion(center));
+    }
+
+    #[test]
+    fn test_objects_can_be_changed_during_an_execution() {
+        let mut app = execution_app();
+        let world = app.world_mut();
+        spawn_main(world, 0.0, 0.0);
+        let near = place(world, "Note", 100.0, 0.0);
+        let removed = place(world, "Note", 150.0, 0.0);
+        let mut added = None;
+        let mut passed = None;
+
+        let played = execute(&mut app, |world, elapsed| {
+            if elapsed >= 0.5 && added.is_none() {
+                // the ring of the main activator is 50 units large and already knows the removed
+                // note
+                world.despawn(removed);
+                added = Some(place(world, "Note", 200.0, 0.0));
+                passed = Some(place(world, "Note", 20.0, 0.0));
+            }
+        });
+
+        let played: Vec<Entity> = played.into_iter().map(|(_, note)| note).collect();
+        // the wave already passed the note placed close to the activator
+        assert_eq!(vec![near, added.unwrap()], played);
+        assert!(passed.is_some());
     }
 }
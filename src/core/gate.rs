@@ -0,0 +1,144 @@
+//! # Gate
+//!
+//! Every other activator is enabled by the first ring which hits it. A logic gate collects the hits
+//! instead and is only enabled by some of them: an AND gate once rings of enough different
+//! activators have hit it, a counter by every k-th hit. The hits in between still count as passed
+//! for the ring, so it does not hit the gate twice.
+
+use crate::core::game::{activator_icon, spawn_activator};
+use crate::core::model::{ActivatorColor, Gate, GateInputs};
+use crate::core::object::{ObjectAppExt, ObjectField, ObjectType};
+use crate::core::prediction::PredictedEvent;
+use bevy::prelude::*;
+use std::f32::consts::{FRAC_PI_2, TAU};
+
+pub(super) struct GatePlugin;
+
+impl Plugin for GatePlugin {
+    fn build(&self, app: &mut App) {
+        // gates are saved and hit as activators
+        app.register_object(
+            ObjectType::<Gate>::new()
+                .item("Gate", activator_icon, spawn_gate)
+                .draw(draw_gate_markers)
+                .field(KIND)
+                .field(INPUTS),
+        );
+    }
+}
+
+/// The inputs of the gate at the given time, replayed from the predicted hits.
+pub(super) fn inputs_at(
+    gate: &Gate,
+    activator: Entity,
+    events: &[PredictedEvent],
+    time: f32,
+) -> GateInputs {
+    let mut inputs = GateInputs::default();
+    for event in events
+        .iter()
+        .take_while(|it| it.time <= time)
+        .filter(|it| it.target == activator)
+    {
+        inputs.hit(gate, event.source);
+    }
+    inputs
+}
+
+/// AND gate or counter, the number of inputs is kept.
+const KIND: ObjectField = ObjectField {
+    name: "Gate",
+    steps: None,
+    value: |object| match object.get::<Gate>()? {
+        Gate::And { .. } => Some("AND".to_string()),
+        Gate::Counter { .. } => Some("Counter".to_string()),
+    },
+    edit: |object, _| {
+        if let Some(mut gate) = object.get_mut::<Gate>() {
+            *gate = match *gate {
+                Gate::And { sources } => Gate::Counter { every: sources },
+                Gate::Counter { every } => Gate::And { sources: every },
+            };
+        }
+    },
+};
+
+/// Sources an AND gate needs or hits a counter needs.
+const INPUTS: ObjectField = ObjectField {
+    name: "Inputs",
+    steps: Some(&[-1.0, 1.0]),
+    value: |object| match object.get::<Gate>()? {
+        Gate::And { sources } => Some(format!("{sources} sources")),
+        Gate::Counter { every } => Some(format!("every {every}")),
+    },
+    edit: |object, step| {
+        if let Some(mut gate) = object.get_mut::<Gate>() {
+            let (Gate::And { sources: inputs } | Gate::Counter { every: inputs }) = gate.as_mut();
+            *inputs = (*inputs as f32 + step).max(1.0) as u32;
+        }
+    },
+};
+
+fn spawn_gate(entity: &mut EntityWorldMut) {
+    spawn_activator(entity);
+    entity.insert(Gate::default());
+}
+
+/// Show a dot for every input a gate needs, the collected ones are doubled. The dots of an AND gate
+/// surround it, the ones of a counter are lined up below it.
+fn draw_gate_markers(
+    gates: Query<(&Gate, &GateInputs, &ActivatorColor, &Transform)>,
+    mut gizmos: Gizmos,
+) {
+    for (gate, inputs, color, transform) in &gates {
+        let center = transform.translation.xy();
+        let (needed, collected) = match gate {
+            Gate::And { sources } => (*sources, inputs.sources.len() as u32),
+            Gate::Counter { every } => (*every, inputs.hits),
+        };
+        for index in 0..needed {
+            let offset = match gate {
+                Gate::And { .. } => {
+                    Vec2::from_angle(FRAC_PI_2 + TAU * index as f32 / needed as f32) * 24.0
+                }
+                Gate::Counter { .. } => {
+                    Vec2::new((index as f32 - (needed - 1) as f32 / 2.0) * 8.0, -24.0)
+                }
+            };
+            let position = Isometry2d::from_translation(center + offset);
+            gizmos.circle_2d(position, 3.0, color);
+            if index < collected {
+                gizmos.circle_2d(position, 1.5, color);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_and_gate_needs_different_sources() {
+        let gate = Gate::And { sources: 2 };
+        let mut inputs = GateInputs::default();
+
+        assert!(!inputs.hit(&gate, Entity::from_raw(1)));
+        assert!(!inputs.hit(&gate, Entity::from_raw(1)));
+        assert!(inputs.hit(&gate, Entity::from_raw(2)));
+        // starts over
+        assert_eq!(GateInputs::default(), inputs);
+        assert!(!inputs.hit(&gate, Entity::from_raw(2)));
+    }
+
+    #[test]
+    fn test_counter_is_enabled_by_every_kth_hit() {
+        let gate = Gate::Counter { every: 3 };
+        let mut inputs = GateInputs::default();
+
+        let enabled: Vec<bool> = (0..6)
+            .map(|_| inputs.hit(&gate, Entity::from_raw(1)))
+            .collect();
+        assert_eq!(vec![false, false, true, false, false, true], enabled);
+    }
+}
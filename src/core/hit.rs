@@ -0,0 +1,155 @@
+//! # Hit
+//!
+//! Everything a wave can hit is [`Hittable`]. An object type becomes hittable with the hit hook of
+//! its [`crate::core::object::ObjectType`], which declares its hit behavior. The waves collect
+//! all hittable objects, and when one of them reaches an object, a [`WaveHitEvent`] is triggered
+//! on it for the registered types it has. Only the behaviors of these types observe the hit, so new
+//! mechanics can be added in their own plugin without touching the execution loop. The prediction
+//! includes every hittable object as well, so the hits of a new type show up on the timeline.
+//!
+//! Notes are played and activators start their wave, both are registered by the core plugin.
+
+use crate::core::game::{forget_removed_object, track_added_object};
+use crate::core::prediction::ObjectKind;
+use bevy::ecs::component::ComponentId;
+use bevy::ecs::system::IntoObserverSystem;
+use bevy::prelude::*;
+
+/// Marks an object which waves can hit. Every registered type requires it.
+#[derive(Component, Default, Debug)]
+pub struct Hittable;
+
+/// Triggered on an object when a wave of the source activator reaches it. The observer of a
+/// registered type only sees the hits of objects with the type.
+#[derive(Event, Debug, Copy, Clone)]
+pub struct WaveHitEvent {
+    pub source: Entity,
+}
+
+/// Written for every hit, so the wave does not hit the object again.
+#[derive(Event, Debug)]
+pub(super) struct ObjectHitEvent {
+    pub(super) source: Entity,
+    pub(super) object: Entity,
+}
+
+/// The components of all registered types with the kind the prediction sees their objects as.
+#[derive(Resource, Default, Debug)]
+pub(super) struct HittableTypes(Vec<(ComponentId, fn(&EntityRef) -> ObjectKind)>);
+
+impl HittableTypes {
+    /// The kind of the first registered type the object has.
+    pub(super) fn kind(&self, object: &EntityRef) -> ObjectKind {
+        self.0
+            .iter()
+            .find(|(component, _)| object.contains_id(*component))
+            .map(|(_, kind)| kind(object))
+            .unwrap_or(ObjectKind::Object)
+    }
+}
+
+pub(super) trait HittableAppExt {
+    /// Let the waves hit every object with the component and run the observer on each hit.
+    fn register_hittable<T: Component, M>(
+        &mut self,
+        kind: fn(&EntityRef) -> ObjectKind,
+        on_hit: impl IntoObserverSystem<WaveHitEvent, (), M>,
+    ) -> &mut Self;
+}
+
+impl HittableAppExt for App {
+    fn register_hittable<T: Component, M>(
+        &mut self,
+        kind: fn(&EntityRef) -> ObjectKind,
+        on_hit: impl IntoObserverSystem<WaveHitEvent, (), M>,
+    ) -> &mut Self {
+        let world = self.world_mut();
+        let component = world.register_component::<T>();
+        world
+            .get_resource_or_init::<HittableTypes>()
+            .0
+            .push((component, kind));
+        world.spawn(Observer::new(on_hit).with_component(component));
+
+        self.register_required_components::<T, Hittable>()
+            .add_observer(track_added_object::<T>)
+            .add_observer(forget_removed_object::<T>)
+    }
+}
+
+/// The wave of the activator reached the other object.
+pub(super) fn hit_object(
+    activator: Entity,
+    other: Entity,
+    hits: &mut EventWriter<ObjectHitEvent>,
+    commands: &mut Commands,
+) {
+    hits.write(ObjectHitEvent {
+        source: activator,
+        object: other,
+    });
+    commands.queue(move |world: &mut World| {
+        let Ok(object) = world.get_entity(other) else {
+            return;
+        };
+        let types: Vec<ComponentId> = world
+            .resource::<HittableTypes>()
+            .0
+            .iter()
+            .map(|(component, _)| *component)
+            .filter(|it| object.contains_id(*it))
+            .collect();
+        world.trigger_targets(WaveHitEvent { source: activator }, (other, types));
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bevy::ecs::system::RunSystemOnce;
+
+    #[derive(Component, Default)]
+    struct Bell;
+
+    #[derive(Component, Default)]
+    struct Gong;
+
+    #[derive(Resource, Default)]
+    struct Rung(Vec<(&'static str, Entity)>);
+
+    #[test]
+    fn test_hit_runs_only_the_behavior_of_its_type() {
+        let mut app = App::new();
+        app.init_resource::<Rung>()
+            .add_event::<ObjectHitEvent>()
+            .register_hittable::<Bell, _>(
+                |_| ObjectKind::Object,
+                |hit: Trigger<WaveHitEvent>, mut rung: ResMut<Rung>| {
+                    rung.0.push(("bell", hit.target()));
+                },
+            )
+            .register_hittable::<Gong, _>(
+                |_| ObjectKind::Note,
+                |hit: Trigger<WaveHitEvent>, mut rung: ResMut<Rung>| {
+                    rung.0.push(("gong", hit.target()));
+                },
+            );
+        let world = app.world_mut();
+        let activator = world.spawn_empty().id();
+        let bell = world.spawn(Bell).id();
+        let gong = world.spawn(Gong).id();
+        assert!(world.entity(bell).contains::<Hittable>());
+        let types = world.resource::<HittableTypes>();
+        assert_eq!(ObjectKind::Object, types.kind(&world.entity(bell)));
+        assert_eq!(ObjectKind::Note, types.kind(&world.entity(gong)));
+
+        world
+            .run_system_once(
+                move |mut hits: EventWriter<ObjectHitEvent>, mut commands: Commands| {
+                    hit_object(activator, bell, &mut hits, &mut commands);
+                },
+            )
+            .unwrap();
+        assert_eq!(vec![("bell", bell)], world.resource::<Rung>().0);
+    }
+}
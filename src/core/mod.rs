@@ -1,2 +1,14 @@
+pub mod chord;
 pub mod game;
+mod gate;
+pub mod hit;
 pub mod model;
+pub mod modifier;
+mod motion;
+pub mod object;
+mod portal;
+pub mod prediction;
+mod pulse;
+mod shape;
+mod wall;
+mod zone;
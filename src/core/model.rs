@@ -1,13 +1,22 @@
 use crate::visual::color::ColorPalette;
 use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
 
-#[derive(Component)]
+#[derive(Component, Default)]
 pub struct Note;
 
 #[derive(Component, Default, Debug)]
-#[require(ActivatorSize, ActivatorColor)]
+#[require(
+    ActivatorSize,
+    ActivatorColor,
+    ActivatorLimit,
+    ActivatorSpeed,
+    ActivatorShape
+)]
 pub struct Activator;
 
+/// The size of the wave of an activator: the radius of a ring or the angle in radians a sweep has
+/// turned.
 #[derive(Component, Default, PartialEq, Debug, Deref)]
 pub struct ActivatorSize(f32);
 
@@ -16,9 +25,62 @@ impl ActivatorSize {
         ActivatorSize(0.0)
     }
 
+    pub fn new(value: f32) -> Self {
+        ActivatorSize(value)
+    }
+
     pub fn increment(&mut self, value: f32) {
         self.0 += value;
     }
+
+    /// Keep the size below the maximum.
+    pub fn limit(&mut self, max: f32) {
+        self.0 = self.0.min(max);
+    }
+}
+
+/// The shape of the wave of an activator. The direction of a cone or sweep is the rotation of the
+/// activator.
+#[derive(Component, Default, PartialEq, Debug, Copy, Clone, Serialize, Deserialize)]
+pub enum ActivatorShape {
+    /// a ring in every direction
+    #[default]
+    Circle,
+    /// a ring which only covers the given degrees around the direction
+    Cone { width: f32 },
+    /// a line which turns once around the activator in the given beats, starting at the direction
+    Sweep { beats: f32 },
+}
+
+/// Multiplier of the speed the ring of an activator grows with.
+#[derive(Component, PartialEq, Debug, Copy, Clone, Deref, Serialize, Deserialize)]
+pub struct ActivatorSpeed(pub f32);
+
+impl Default for ActivatorSpeed {
+    fn default() -> Self {
+        ActivatorSpeed(1.0)
+    }
+}
+
+/// Limits of the ring of an activator. A ring which reaches its limit disables itself, even if it
+/// has not hit every object.
+#[derive(Component, Default, PartialEq, Debug, Copy, Clone, Serialize, Deserialize)]
+pub struct ActivatorLimit {
+    /// maximum size of the ring
+    pub radius: Option<f32>,
+    /// maximum lifetime of the ring in beats
+    pub lifetime: Option<f32>,
+}
+
+impl ActivatorLimit {
+    /// The size at which the ring ends for the given growth per beat, `None` if unlimited.
+    pub fn max_size(&self, growth_per_beat: f32) -> Option<f32> {
+        let lifetime = self.lifetime.map(|it| it * growth_per_beat);
+        match (self.radius, lifetime) {
+            (Some(radius), Some(lifetime)) => Some(radius.min(lifetime)),
+            (radius, lifetime) => radius.or(lifetime),
+        }
+    }
 }
 
 #[derive(Component, Default, PartialEq, Debug)]
@@ -35,15 +97,26 @@ impl ActivatorState {
     }
 }
 
-#[derive(Component, Default, PartialEq, Debug)]
+#[derive(Component, Default, PartialEq, Debug, Copy, Clone, Serialize, Deserialize)]
 #[require(Activator, ActivatorState, ActivatorSize)]
 pub enum ActivatorType {
     Main,
     #[default]
     Passive,
+    /// starts its ring the given number of beats after it was hit
+    Delay {
+        beats: f32,
+    },
 }
 
-#[derive(Component, Default, PartialEq, Debug, Copy, Clone)]
+/// A hit delay activator waiting for its ring to start.
+#[derive(Component, PartialEq, Debug)]
+pub struct Waiting {
+    /// seconds until the ring starts
+    pub remaining: f32,
+}
+
+#[derive(Component, Default, PartialEq, Debug, Copy, Clone, Serialize, Deserialize)]
 pub struct ActivatorColor(pub ColorPalette);
 
 impl From<&ActivatorColor> for Color {
@@ -54,3 +127,214 @@ impl From<&ActivatorColor> for Color {
 
 #[derive(Component, Deref)]
 pub struct InactivatedObjects(pub Vec<Entity>);
+
+/// The echoes of the ring of an activator which have not reached their object yet, sorted by
+/// distance.
+#[derive(Component, Deref, Default, Debug)]
+pub struct Echoes(pub Vec<Echo>);
+
+/// The ring of an activator reflected by a wall reaches the object once the ring has grown to the
+/// distance of the detour.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Echo {
+    pub target: Entity,
+    pub distance: f32,
+}
+
+/// A wall segment along the x axis of its transform. Every wall blocks rings, a reflective wall
+/// also reflects them.
+#[derive(Component, Default, PartialEq, Eq, Debug, Copy, Clone, Serialize, Deserialize)]
+pub enum Wall {
+    #[default]
+    Blocking,
+    Reflective,
+}
+
+/// A region in which rings grow slower or faster. Its size is the scale of its transform.
+#[derive(Component, PartialEq, Debug, Copy, Clone, Serialize, Deserialize)]
+pub struct Zone {
+    pub shape: ZoneShape,
+    /// multiplier of the speed of rings inside the zone
+    pub speed: f32,
+}
+
+impl Default for Zone {
+    fn default() -> Self {
+        Zone {
+            shape: ZoneShape::default(),
+            speed: 0.5,
+        }
+    }
+}
+
+#[derive(Default, PartialEq, Eq, Debug, Copy, Clone, Serialize, Deserialize)]
+pub enum ZoneShape {
+    #[default]
+    Rectangle,
+    Circle,
+}
+
+/// An activator which emits a new ring every `interval` beats after it was enabled.
+#[derive(Component, PartialEq, Debug, Copy, Clone, Serialize, Deserialize)]
+#[require(Activator)]
+pub struct Pulse {
+    /// beats between two rings
+    pub interval: f32,
+    /// number of rings including the first one, `None` to pulse forever
+    pub count: Option<u32>,
+}
+
+impl Default for Pulse {
+    fn default() -> Self {
+        Pulse {
+            interval: 1.0,
+            count: Some(4),
+        }
+    }
+}
+
+/// An activator which is only enabled by some hits of other rings. The hits which do not enable
+/// it are collected in [`GateInputs`].
+#[derive(Component, PartialEq, Debug, Copy, Clone, Serialize, Deserialize)]
+#[require(Activator, GateInputs)]
+pub enum Gate {
+    /// enabled once the rings of the given number of different activators have hit it
+    And { sources: u32 },
+    /// enabled by every hit with the given number, e.g. every third hit
+    Counter { every: u32 },
+}
+
+impl Default for Gate {
+    fn default() -> Self {
+        Gate::And { sources: 2 }
+    }
+}
+
+/// The hits a logic gate has collected since it was enabled last.
+#[derive(Component, Default, PartialEq, Debug, Clone)]
+pub struct GateInputs {
+    /// the different activators which have hit the gate
+    pub sources: Vec<Entity>,
+    pub hits: u32,
+}
+
+impl GateInputs {
+    /// Collect the hit by the ring of the source and return whether it enables the gate. The
+    /// inputs start over once the gate is enabled.
+    pub fn hit(&mut self, gate: &Gate, source: Entity) -> bool {
+        if !self.sources.contains(&source) {
+            self.sources.push(source);
+        }
+        self.hits += 1;
+        let enabled = match gate {
+            Gate::And { sources } => self.sources.len() >= *sources as usize,
+            Gate::Counter { every } => self.hits >= *every,
+        };
+        if enabled {
+            *self = GateInputs::default();
+        }
+        enabled
+    }
+}
+
+/// One end of a pair of portals. A ring which reaches the portal continues from the linked one.
+#[derive(Component, Default, PartialEq, Debug, Copy, Clone)]
+#[require(Activator)]
+pub struct Portal {
+    /// the other end of the pair
+    pub link: Option<Entity>,
+    /// the ring of the linked portal starts with the size the ring had here instead of zero
+    pub keep_size: bool,
+}
+
+/// An object which moves during the execution. It starts at its position of the build mode, which
+/// is kept as its [`Anchor`].
+#[derive(Component, PartialEq, Debug, Copy, Clone)]
+pub enum Motion {
+    /// circles counterclockwise around the activator once in the given beats
+    Orbit { center: Entity, beats: f32 },
+    /// moves to the offset and back once in the given beats
+    Path { offset: Vec2, beats: f32 },
+}
+
+/// The position of a moving object at the start of the execution.
+#[derive(Component, PartialEq, Debug, Copy, Clone, Deref)]
+pub struct Anchor(pub Vec2);
+
+/// The rings a pulse activator emitted before its current one and the pulses still to come. The
+/// current ring is the [`ActivatorSize`] and [`InactivatedObjects`] of the activator.
+#[derive(Component, Debug, Default)]
+pub struct PulseRings {
+    pub rings: Vec<Ring>,
+    /// rings still to emit, `None` if the activator pulses forever
+    pub remaining: Option<u32>,
+    /// seconds until the next ring
+    pub until_next: f32,
+}
+
+impl PulseRings {
+    /// No ring is growing and no ring is left to emit.
+    pub fn is_done(&self) -> bool {
+        self.rings.is_empty() && self.remaining == Some(0)
+    }
+}
+
+/// An older ring of a pulse activator with the objects it has not hit yet, sorted by distance.
+#[derive(Debug, Default)]
+pub struct Ring {
+    pub size: f32,
+    pub inactivated: Vec<Entity>,
+    pub echoes: Vec<Echo>,
+}
+
+/// Alters the pitch of the notes around it. Its radius is scaled with its transform.
+#[derive(Component, Default, PartialEq, Eq, Debug, Copy, Clone, Serialize, Deserialize)]
+pub enum Modifier {
+    /// a semitone higher, which may leave the scale
+    #[default]
+    Sharp,
+    /// a semitone lower, which may leave the scale
+    Flat,
+    OctaveUp,
+    OctaveDown,
+}
+
+/// A note which plays a chord of stacked thirds on its scale degree instead of a single note.
+#[derive(Component, Default, PartialEq, Eq, Debug, Copy, Clone, Serialize, Deserialize)]
+#[require(Note)]
+pub struct Chord {
+    pub kind: ChordKind,
+    /// lowest tones which are played an octave higher
+    pub inversion: u8,
+}
+
+#[derive(Default, PartialEq, Eq, Debug, Copy, Clone, Serialize, Deserialize)]
+pub enum ChordKind {
+    #[default]
+    Triad,
+    Seventh,
+}
+
+impl ChordKind {
+    pub fn tones(&self) -> u8 {
+        match self {
+            ChordKind::Triad => 3,
+            ChordKind::Seventh => 4,
+        }
+    }
+}
+
+/// The beats a note sounds before it fades out. A note without a length plays its whole sample.
+#[derive(Component, PartialEq, Debug, Copy, Clone, Deref, Serialize, Deserialize)]
+pub struct NoteLength(pub f32);
+
+/// A note without a pitch, which plays the sample of its drum at any angle.
+#[derive(Component, Default, PartialEq, Eq, Debug, Copy, Clone, Serialize, Deserialize)]
+#[require(Note)]
+pub enum Percussion {
+    #[default]
+    Kick,
+    Snare,
+    Hat,
+    Clap,
+}
@@ -0,0 +1,192 @@
+//! # Modifier
+//!
+//! Modifiers alter the pitch of every note inside their radius after its scale degree is
+//! calculated. Sharps and flats shift the note by a semitone, so it may leave the scale, and octave
+//! modifiers play it an octave higher or lower. The modifiers around a note add up.
+
+use crate::core::model::Modifier;
+use crate::core::object::{ObjectAppExt, ObjectField, ObjectType, cycle};
+use crate::music::model::Pitch;
+use bevy::prelude::*;
+
+pub(super) struct ModifierPlugin;
+
+impl Plugin for ModifierPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_object(
+            ObjectType::<Modifier>::new()
+                .item(
+                    "Modifier",
+                    |assets| assets.modifier_icon.clone(),
+                    spawn_modifier,
+                )
+                .draw(draw_modifiers)
+                .field(KIND)
+                .save_component(),
+        );
+    }
+}
+
+/// Radius of a modifier with a scale of one in logical pixels.
+const MODIFIER_RADIUS: f32 = 40.0;
+
+/// The area of a modifier in world coordinates.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct ModifierArea {
+    pub modifier: Modifier,
+    pub center: Vec2,
+    pub radius: f32,
+}
+
+impl ModifierArea {
+    pub fn new(modifier: &Modifier, transform: &Transform) -> Self {
+        ModifierArea {
+            modifier: *modifier,
+            center: transform.translation.xy(),
+            radius: transform.scale.x * MODIFIER_RADIUS,
+        }
+    }
+}
+
+pub fn modifier_areas(modifiers: &Query<(&Modifier, &Transform)>) -> Vec<ModifierArea> {
+    modifiers
+        .iter()
+        .map(|(modifier, transform)| ModifierArea::new(modifier, transform))
+        .collect()
+}
+
+/// The pitch a note at the given position plays instead of the given one.
+pub fn modify_pitch(mut pitch: Pitch, position: Vec2, modifiers: &[ModifierArea]) -> Pitch {
+    for area in modifiers
+        .iter()
+        .filter(|it| it.center.distance(position) < it.radius)
+    {
+        match area.modifier {
+            Modifier::Sharp => pitch = pitch.transpose(1),
+            Modifier::Flat => pitch = pitch.transpose(-1),
+            Modifier::OctaveUp => pitch.octave += 1,
+            Modifier::OctaveDown => pitch.octave -= 1,
+        }
+    }
+    pitch
+}
+
+/// The kinds a modifier cycles through in the inspector.
+const MODIFIERS: [Modifier; 4] = [
+    Modifier::Sharp,
+    Modifier::Flat,
+    Modifier::OctaveUp,
+    Modifier::OctaveDown,
+];
+
+/// How the modifier alters the notes around it.
+const KIND: ObjectField = ObjectField {
+    name: "Modifier",
+    steps: None,
+    value: |object| object.get::<Modifier>().map(|it| format!("{it:?}")),
+    edit: |object, step| {
+        if let Some(mut modifier) = object.get_mut::<Modifier>()
+            && let Some(next) = cycle(&MODIFIERS, Some(*modifier), step)
+        {
+            *modifier = next;
+        }
+    },
+};
+
+/// Modifiers are drawn as circles, their radius is the scale.
+fn spawn_modifier(entity: &mut EntityWorldMut) {
+    entity.insert(Modifier::default());
+}
+
+/// Show the radius of every modifier with an arrow inside, a double arrow for octaves.
+fn draw_modifiers(modifiers: Query<(&Modifier, &Transform)>, mut gizmos: Gizmos) {
+    let color = Color::srgba(0.8, 0.6, 1.0, 0.6);
+    for (modifier, transform) in &modifiers {
+        let area = ModifierArea::new(modifier, transform);
+        gizmos
+            .circle_2d(
+                Isometry2d::from_translation(area.center),
+                area.radius,
+                color,
+            )
+            .resolution(32);
+
+        let (up, arrows) = match modifier {
+            Modifier::Sharp => (true, 1),
+            Modifier::Flat => (false, 1),
+            Modifier::OctaveUp => (true, 2),
+            Modifier::OctaveDown => (false, 2),
+        };
+        let tip = if up { Vec2::Y } else { Vec2::NEG_Y } * 6.0;
+        for arrow in 0..arrows {
+            let center = area.center + tip * (arrow as f32 - (arrows - 1) as f32 / 2.0) * 1.5;
+            gizmos.line_2d(center - tip + Vec2::X * 6.0, center + tip, color);
+            gizmos.line_2d(center - tip - Vec2::X * 6.0, center + tip, color);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::music::model::Note;
+
+    fn area(modifier: Modifier, x: f32) -> ModifierArea {
+        ModifierArea {
+            modifier,
+            center: Vec2::new(x, 0.0),
+            radius: MODIFIER_RADIUS,
+        }
+    }
+
+    const A: Pitch = Pitch {
+        note: Note::A,
+        octave: 0,
+    };
+
+    #[test]
+    fn test_kind_is_cycled() {
+        let mut world = World::new();
+        let modifier = world.spawn(Modifier::Sharp).id();
+
+        let kinds: Vec<Modifier> = (0..4)
+            .map(|_| {
+                (KIND.edit)(&mut world.entity_mut(modifier), 1.0);
+                *world.get::<Modifier>(modifier).unwrap()
+            })
+            .collect();
+        assert_eq!(
+            vec![
+                Modifier::Flat,
+                Modifier::OctaveUp,
+                Modifier::OctaveDown,
+                Modifier::Sharp
+            ],
+            kinds
+        );
+    }
+
+    #[test]
+    fn test_modifiers_around_the_note_add_up() {
+        let modifiers = [
+            area(Modifier::Flat, 0.0),
+            area(Modifier::OctaveUp, 30.0),
+            area(Modifier::Sharp, 100.0),
+        ];
+        // the flat shifts A down to the G# of octave -1, the octave modifier lifts it back to 0
+        assert_eq!(
+            Pitch {
+                note: Note::Gs,
+                octave: 0
+            },
+            modify_pitch(A, Vec2::new(10.0, 0.0), &modifiers)
+        );
+        assert_eq!(
+            Pitch {
+                note: Note::As,
+                octave: 0
+            },
+            modify_pitch(A, Vec2::new(100.0, 0.0), &modifiers)
+        );
+    }
+}
@@ -0,0 +1,295 @@
+//! # Motion
+//!
+//! Moving objects orbit an activator or move back and forth along a path while the rings expand.
+//! Their position only depends on the time since the start of the execution, so a loop iteration or
+//! a scrubbed time always shows the same board. The position of the build mode is kept as the
+//! [`Anchor`] and restored after the execution.
+//!
+//! The unplayed objects of a ring are sorted by distance or angle once they are collected. While
+//! objects move, the lists are sorted again every frame, so the collision checks can still stop at
+//! the first object which is out of reach.
+
+use crate::core::game::{ExecutionClock, LevelConfig, ScrubEvent};
+use crate::core::model::{
+    ActivatorShape, ActivatorType, Anchor, InactivatedObjects, Motion, PulseRings, Zone,
+};
+use crate::core::object::SavedEntities;
+use crate::core::shape::{direction, sweep_angle};
+use crate::core::zone::{travel_distance, zone_areas};
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::f32::consts::TAU;
+
+/// The motion of an object in world coordinates and seconds.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum MotionPath {
+    Orbit { center: Vec2, period: f32 },
+    Path { offset: Vec2, period: f32 },
+}
+
+impl MotionPath {
+    /// The path of the motion around the start position of the orbited object, `None` if it is
+    /// missing.
+    pub fn new(motion: &Motion, center: Option<Vec2>, beat_length: f32) -> Option<Self> {
+        match motion {
+            Motion::Orbit { beats, .. } => Some(MotionPath::Orbit {
+                center: center?,
+                period: beats * beat_length,
+            }),
+            Motion::Path { offset, beats } => Some(MotionPath::Path {
+                offset: *offset,
+                period: beats * beat_length,
+            }),
+        }
+    }
+
+    /// The position of an object which started at the anchor at the given seconds since the start
+    /// of the execution.
+    pub fn position(&self, anchor: Vec2, time: f32) -> Vec2 {
+        match self {
+            MotionPath::Orbit { center, period } => {
+                center + Vec2::from_angle(TAU * time / period).rotate(anchor - center)
+            }
+            MotionPath::Path { offset, period } => {
+                // there in the first half of the period and back in the second one
+                let phase = (time / period).fract();
+                anchor + offset * (1.0 - (2.0 * phase - 1.0).abs())
+            }
+        }
+    }
+
+    /// The speed of an object which started at the anchor, in world units per second.
+    pub fn max_speed(&self, anchor: Vec2) -> f32 {
+        match self {
+            MotionPath::Orbit { center, period } => TAU * anchor.distance(*center) / period,
+            // there and back once per period
+            MotionPath::Path { offset, period } => 2.0 * offset.length() / period,
+        }
+    }
+}
+
+/// The motion of a saved object. An orbit refers to its center by its index in the saved level.
+/// Centers which are not saved belong to the level, which is its main activator.
+#[derive(Serialize, Deserialize, Debug, Copy, Clone, PartialEq)]
+pub(super) enum SavedMotion {
+    Orbit { center: Option<usize>, beats: f32 },
+    Path { offset: Vec2, beats: f32 },
+}
+
+pub(super) fn save_motion(object: &EntityRef, entities: &SavedEntities) -> Option<SavedMotion> {
+    match *object.get::<Motion>()? {
+        Motion::Orbit { center, beats } => Some(SavedMotion::Orbit {
+            center: entities.index(center),
+            beats,
+        }),
+        Motion::Path { offset, beats } => Some(SavedMotion::Path { offset, beats }),
+    }
+}
+
+pub(super) fn load_motion(
+    motion: Option<SavedMotion>,
+    entity: &mut EntityWorldMut,
+    entities: &SavedEntities,
+) {
+    let motion = match motion {
+        Some(SavedMotion::Orbit { center, beats }) => {
+            let center = match center {
+                Some(index) => entities.entity(index),
+                None => entity.world_scope(|world| {
+                    let mut activators = world.query::<(Entity, &ActivatorType)>();
+                    activators
+                        .iter(world)
+                        .find(|(_, kind)| **kind == ActivatorType::Main)
+                        .map(|(entity, _)| entity)
+                }),
+            };
+            let Some(center) = center else {
+                warn!("the center of a saved orbit is missing");
+                return;
+            };
+            Motion::Orbit { center, beats }
+        }
+        Some(SavedMotion::Path { offset, beats }) => Motion::Path { offset, beats },
+        None => return,
+    };
+    entity.insert(motion);
+}
+
+/// The start position of an object, which is its current one outside of the execution.
+pub(super) fn start_position(
+    entity: Entity,
+    anchors: &Query<&Anchor>,
+    transforms: &Query<&Transform>,
+) -> Option<Vec2> {
+    anchors
+        .get(entity)
+        .map(|it| **it)
+        .or_else(|_| transforms.get(entity).map(|it| it.translation.xy()))
+        .ok()
+}
+
+pub(super) fn anchor_moving_objects(
+    moving: Query<(Entity, &Transform), With<Motion>>,
+    mut commands: Commands,
+) {
+    for (entity, transform) in &moving {
+        commands
+            .entity(entity)
+            .try_insert(Anchor(transform.translation.xy()));
+    }
+}
+
+/// Return every moving object to its position of the build mode.
+pub(super) fn release_moving_objects(
+    mut moving: Query<(Entity, &Anchor, &mut Transform)>,
+    mut commands: Commands,
+) {
+    for (entity, anchor, mut transform) in &mut moving {
+        transform.translation = anchor.extend(transform.translation.z);
+        commands.entity(entity).remove::<Anchor>();
+    }
+}
+
+/// Move every object to its position at the current or scrubbed time.
+pub(super) fn move_objects(
+    mut scrubs: EventReader<ScrubEvent>,
+    moving: Query<(Entity, &Motion, &Anchor)>,
+    anchors: Query<&Anchor>,
+    mut transforms: Query<&mut Transform>,
+    config: Res<LevelConfig>,
+    clock: Res<ExecutionClock>,
+) {
+    let time = scrubs
+        .read()
+        .last()
+        .map(|it| it.0.max(0.0))
+        .unwrap_or_else(|| clock.elapsed().as_secs_f32());
+
+    let positions: Vec<(Entity, Vec2)> = moving
+        .iter()
+        .filter_map(|(entity, motion, anchor)| {
+            let center = match motion {
+                Motion::Orbit { center, .. } => {
+                    start_position(*center, &anchors, &transforms.as_readonly())
+                }
+                Motion::Path { .. } => None,
+            };
+            let path = MotionPath::new(motion, center, config.beat_length())?;
+            Some((entity, path.position(**anchor, time)))
+        })
+        .collect();
+    for (entity, position) in positions {
+        if let Ok(mut transform) = transforms.get_mut(entity) {
+            transform.translation = position.extend(transform.translation.z);
+        }
+    }
+}
+
+/// Sort the unplayed objects of every ring again, since moving objects change their distance and
+/// angle.
+pub(super) fn sort_moving_rings(
+    moving: Query<(), With<Motion>>,
+    mut activators: Query<(
+        Entity,
+        &ActivatorShape,
+        Option<&mut InactivatedObjects>,
+        Option<&mut PulseRings>,
+    )>,
+    positions: Query<&Transform>,
+    zones: Query<(&Zone, &Transform)>,
+) {
+    if moving.is_empty() {
+        return;
+    }
+    let zones = zone_areas(&zones);
+
+    for (activator, shape, inactive, pulse) in &mut activators {
+        let Ok(transform) = positions.get(activator) else {
+            continue;
+        };
+        let center = transform.translation.xy();
+        let direction = direction(transform);
+        let key = |object: &Entity| {
+            positions
+                .get(*object)
+                .map(|it| {
+                    let position = it.translation.xy();
+                    match shape {
+                        ActivatorShape::Sweep { .. } => sweep_angle(direction, position - center),
+                        _ => travel_distance(center, position, &zones),
+                    }
+                })
+                // despawned objects are dropped at the front
+                .unwrap_or(-1.0)
+        };
+        let sort = |objects: &mut Vec<Entity>| objects.sort_by(|a, b| key(a).total_cmp(&key(b)));
+
+        if let Some(mut inactive) = inactive {
+            sort(&mut inactive.0);
+        }
+        if let Some(mut pulse) = pulse {
+            for ring in &mut pulse.rings {
+                sort(&mut ring.inactivated);
+            }
+        }
+    }
+}
+
+/// Show the orbit or path of every moving object.
+pub(super) fn draw_motion_paths(
+    moving: Query<(&Motion, &Transform, Option<&Anchor>)>,
+    transforms: Query<&Transform>,
+    mut gizmos: Gizmos,
+) {
+    let color = Color::srgba(1.0, 1.0, 1.0, 0.2);
+    for (motion, transform, anchor) in &moving {
+        let position = transform.translation.xy();
+        match motion {
+            Motion::Orbit { center, .. } => {
+                let Ok(center) = transforms.get(*center) else {
+                    continue;
+                };
+                let center = center.translation.xy();
+                gizmos
+                    .circle_2d(
+                        Isometry2d::from_translation(center),
+                        center.distance(position),
+                        color,
+                    )
+                    .resolution(64);
+            }
+            Motion::Path { offset, .. } => {
+                let start = anchor.map(|it| **it).unwrap_or(position);
+                gizmos.line_2d(start, start + offset, color);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_motion_positions() {
+        let orbit = MotionPath::Orbit {
+            center: Vec2::ZERO,
+            period: 4.0,
+        };
+        assert!(
+            orbit
+                .position(Vec2::new(100.0, 0.0), 1.0)
+                .distance(Vec2::new(0.0, 100.0))
+                < 1e-3
+        );
+
+        let path = MotionPath::Path {
+            offset: Vec2::new(100.0, 0.0),
+            period: 2.0,
+        };
+        assert_eq!(Vec2::ZERO, path.position(Vec2::ZERO, 0.0));
+        assert_eq!(Vec2::new(50.0, 0.0), path.position(Vec2::ZERO, 0.5));
+        assert_eq!(Vec2::new(100.0, 0.0), path.position(Vec2::ZERO, 1.0));
+        assert_eq!(Vec2::new(50.0, 0.0), path.position(Vec2::ZERO, 3.5));
+    }
+}
@@ -0,0 +1,452 @@
+//! # Object
+//!
+//! Every type of object on the board is registered as an [`ObjectType`] with
+//! [`ObjectAppExt::register_object`]. The type declares its hooks: the toolbar items which spawn
+//! it, the systems which draw it, the fields the inspector edits, how it is saved and what happens
+//! when a wave hits it. A new mechanic registers its type in its own plugin, and the toolbar, the
+//! inspector, the saved levels, the execution and the prediction pick it up without changes to
+//! them.
+//!
+//! Notes and activators are registered by the core plugin, the other mechanics of the core in
+//! their own plugins. A chord or a pulse is a note or an activator as well, so its type only adds
+//! the item and the drawing, the note or activator type saves it and lets the waves hit it.
+
+use crate::core::game::CoreAssets;
+use crate::core::hit::{HittableAppExt, WaveHitEvent};
+use crate::core::prediction::ObjectKind;
+use bevy::ecs::component::ComponentId;
+use bevy::ecs::system::{IntoObserverSystem, ScheduleSystem};
+use bevy::prelude::*;
+use bevy_svg::prelude::Svg;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::marker::PhantomData;
+
+/// An item which places an object of its type, usually from the toolbar.
+#[derive(Debug, Clone, Copy)]
+pub struct ObjectItem {
+    pub name: &'static str,
+    /// The icon of its toolbar slot, items without one are only spawned by levels and scripts.
+    pub icon: Option<fn(&CoreAssets) -> Handle<Svg>>,
+    /// Inserts the components of the object, its transform is at the position already.
+    pub spawn: fn(&mut EntityWorldMut),
+}
+
+/// The items of all registered types in the order of their registration.
+#[derive(Resource, Default, Debug)]
+pub struct ObjectItems(Vec<ObjectItem>);
+
+impl ObjectItems {
+    pub fn get(&self, name: &str) -> Option<&ObjectItem> {
+        self.0.iter().find(|it| it.name == name)
+    }
+
+    /// The items with an icon in the order of the toolbar.
+    pub fn toolbar(&self) -> impl Iterator<Item = &ObjectItem> {
+        self.0.iter().filter(|it| it.icon.is_some())
+    }
+}
+
+/// A property of an object which the inspector shows and changes with its buttons.
+#[derive(Debug, Clone, Copy)]
+pub struct ObjectField {
+    pub name: &'static str,
+    /// The steps of the buttons, `None` for a field which cycles through its values.
+    pub steps: Option<&'static [f32]>,
+    /// The shown value, `None` if the object does not have the property.
+    pub value: fn(&EntityRef) -> Option<String>,
+    /// Changes the property by the step of a button, a cycled field steps by -1 or 1.
+    pub edit: fn(&mut EntityWorldMut, f32),
+}
+
+/// The fields of all registered types in the order of their registration.
+#[derive(Resource, Default, Debug)]
+pub struct ObjectFields(Vec<(ComponentId, ObjectField)>);
+
+impl ObjectFields {
+    pub fn get(&self, index: usize) -> Option<&ObjectField> {
+        self.0.get(index).map(|(_, field)| field)
+    }
+
+    /// The fields the object has with their index.
+    pub fn of<'a>(
+        &'a self,
+        object: &'a EntityRef,
+    ) -> impl Iterator<Item = (usize, &'a ObjectField)> + 'a {
+        self.0
+            .iter()
+            .enumerate()
+            .filter(|(_, (component, field))| {
+                object.contains_id(*component) && (field.value)(object).is_some()
+            })
+            .map(|(index, (_, field))| (index, field))
+    }
+}
+
+/// Select the next or previous element of the list, depending on the sign of the step.
+pub fn cycle<T: PartialEq + Copy>(list: &[T], current: Option<T>, step: f32) -> Option<T> {
+    let Some(index) = current.and_then(|current| list.iter().position(|it| *it == current)) else {
+        return if step < 0.0 {
+            list.last()
+        } else {
+            list.first()
+        }
+        .copied();
+    };
+    let next = if step < 0.0 {
+        (index + list.len() - 1) % list.len()
+    } else {
+        (index + 1) % list.len()
+    };
+    Some(list[next])
+}
+
+type SaveFn = Box<dyn Fn(&EntityRef, &SavedEntities) -> Result<String> + Send + Sync>;
+type LoadFn = Box<dyn Fn(&str, &mut EntityWorldMut, &SavedEntities) -> Result + Send + Sync>;
+
+/// The objects of a saved level in their order. Objects refer to each other by their index, as
+/// the entities are different once the level is loaded.
+#[derive(Debug, Default)]
+pub struct SavedEntities(Vec<Option<Entity>>);
+
+impl SavedEntities {
+    /// Index of the object in the level, `None` if it is not saved.
+    pub fn index(&self, entity: Entity) -> Option<usize> {
+        self.0.iter().position(|it| *it == Some(entity))
+    }
+
+    /// The object at the index, `None` if it was skipped while loading.
+    pub fn entity(&self, index: usize) -> Option<Entity> {
+        self.0.get(index).copied().flatten()
+    }
+}
+
+/// Saves the objects of a type and restores them once their item has spawned them again.
+struct SaveHook {
+    component: ComponentId,
+    save: SaveFn,
+    load: LoadFn,
+}
+
+#[derive(Resource, Default)]
+struct SaveHooks(Vec<SaveHook>);
+
+/// The hooks of the objects with the component `T`, all of them are optional.
+pub struct ObjectType<T: Component> {
+    items: Vec<ObjectItem>,
+    fields: Vec<ObjectField>,
+    save: Option<(SaveFn, LoadFn)>,
+    /// systems and observers added to the app on registration
+    hooks: Vec<Box<dyn FnOnce(&mut App)>>,
+    _type: PhantomData<T>,
+}
+
+impl<T: Component> Default for ObjectType<T> {
+    fn default() -> Self {
+        ObjectType {
+            items: Vec::new(),
+            fields: Vec::new(),
+            save: None,
+            hooks: Vec::new(),
+            _type: PhantomData,
+        }
+    }
+}
+
+impl<T: Component> ObjectType<T> {
+    pub fn new() -> Self {
+        ObjectType::default()
+    }
+
+    /// Add an item to the toolbar which spawns an object of the type.
+    pub fn item(
+        mut self,
+        name: &'static str,
+        icon: fn(&CoreAssets) -> Handle<Svg>,
+        spawn: fn(&mut EntityWorldMut),
+    ) -> Self {
+        self.items.push(ObjectItem {
+            name,
+            icon: Some(icon),
+            spawn,
+        });
+        self
+    }
+
+    /// Add an item which is not in the toolbar, e.g. for objects only a level script places.
+    /// Saved levels spawn the objects with it like with every other item.
+    pub fn hidden_item(mut self, name: &'static str, spawn: fn(&mut EntityWorldMut)) -> Self {
+        self.items.push(ObjectItem {
+            name,
+            icon: None,
+            spawn,
+        });
+        self
+    }
+
+    /// Draw the objects with the systems, in the build mode and during the execution.
+    pub fn draw<M>(
+        mut self,
+        systems: impl IntoScheduleConfigs<ScheduleSystem, M> + 'static,
+    ) -> Self {
+        self.hooks.push(Box::new(move |app: &mut App| {
+            app.add_systems(Update, systems);
+        }));
+        self
+    }
+
+    /// Let the inspector edit the field of the objects, after the fields registered before.
+    pub fn field(mut self, field: ObjectField) -> Self {
+        self.fields.push(field);
+        self
+    }
+
+    /// Save an object as the data the first function returns, which the second one restores. The
+    /// second function runs once every object of the level is spawned, so it can link the object
+    /// with others through [`SavedEntities`].
+    pub fn save<S: Serialize + DeserializeOwned + 'static>(
+        mut self,
+        save: fn(&EntityRef, &SavedEntities) -> S,
+        load: fn(S, &mut EntityWorldMut, &SavedEntities),
+    ) -> Self {
+        self.save = Some((
+            Box::new(move |object, entities| Ok(ron::to_string(&save(object, entities))?)),
+            Box::new(move |data, object, entities| {
+                load(ron::from_str(data)?, object, entities);
+                Ok(())
+            }),
+        ));
+        self
+    }
+
+    /// Save the objects as their component.
+    pub fn save_component(self) -> Self
+    where
+        T: Clone + Serialize + DeserializeOwned,
+    {
+        self.save(
+            |object, _| object.get::<T>().cloned(),
+            |component, object, _| {
+                if let Some(component) = component {
+                    object.insert(component);
+                }
+            },
+        )
+    }
+
+    /// Let the waves hit the objects and run the observer on each hit. The prediction sees an
+    /// object as the kind the function returns.
+    pub fn on_hit<M>(
+        mut self,
+        kind: fn(&EntityRef) -> ObjectKind,
+        on_hit: impl IntoObserverSystem<WaveHitEvent, (), M> + 'static,
+    ) -> Self {
+        self.hooks.push(Box::new(move |app: &mut App| {
+            app.register_hittable::<T, _>(kind, on_hit);
+        }));
+        self
+    }
+}
+
+pub trait ObjectAppExt {
+    /// Add the items of the type to the toolbar and install its hooks.
+    fn register_object<T: Component>(&mut self, object: ObjectType<T>) -> &mut Self;
+}
+
+impl ObjectAppExt for App {
+    fn register_object<T: Component>(&mut self, object: ObjectType<T>) -> &mut Self {
+        self.init_resource::<ObjectItems>()
+            .init_resource::<ObjectFields>()
+            .init_resource::<SaveHooks>();
+        let world = self.world_mut();
+        let component = world.register_component::<T>();
+        world.resource_mut::<ObjectItems>().0.extend(object.items);
+        world
+            .resource_mut::<ObjectFields>()
+            .0
+            .extend(object.fields.into_iter().map(|it| (component, it)));
+        if let Some((save, load)) = object.save {
+            world.resource_mut::<SaveHooks>().0.push(SaveHook {
+                component,
+                save,
+                load,
+            });
+        }
+
+        for hook in object.hooks {
+            hook(self);
+        }
+        self
+    }
+}
+
+/// Spawn an object of the item at the position.
+pub fn spawn_item(world: &mut World, item: &ObjectItem, position: Vec2) -> Entity {
+    let mut entity = world.spawn((
+        Name::new(item.name),
+        Transform::from_translation(position.extend(0.0)),
+    ));
+    (item.spawn)(&mut entity);
+    entity.id()
+}
+
+/// An object of a saved level.
+#[derive(Serialize, Deserialize, Debug)]
+struct SavedObject {
+    item: String,
+    transform: Transform,
+    /// what the save hook of its type returned
+    data: Option<String>,
+}
+
+/// Write the objects into a level in RON, each with the name of the item which placed it.
+pub fn save_objects(
+    world: &World,
+    objects: impl IntoIterator<Item = (Entity, &'static str)>,
+) -> Result<String> {
+    let hooks = world.resource::<SaveHooks>();
+    let objects: Vec<(Entity, &'static str)> = objects.into_iter().collect();
+    let entities = SavedEntities(objects.iter().map(|(entity, _)| Some(*entity)).collect());
+    let saved = objects
+        .into_iter()
+        .map(|(entity, item)| {
+            let object = world.entity(entity);
+            let data = hooks
+                .0
+                .iter()
+                .find(|it| object.contains_id(it.component))
+                .map(|it| (it.save)(&object, &entities))
+                .transpose()?;
+            Ok(SavedObject {
+                item: item.to_string(),
+                transform: object.get::<Transform>().copied().unwrap_or_default(),
+                data,
+            })
+        })
+        .collect::<Result<Vec<_>>>()?;
+    Ok(ron::ser::to_string_pretty(
+        &saved,
+        ron::ser::PrettyConfig::default(),
+    )?)
+}
+
+/// Spawn the objects of a saved level with their items and return them with the item names.
+/// Objects of items which are not registered anymore are skipped.
+pub fn load_objects(world: &mut World, content: &str) -> Result<Vec<(Entity, &'static str)>> {
+    let saved: Vec<SavedObject> = ron::from_str(content)?;
+    let mut entities = SavedEntities::default();
+    let mut loaded = Vec::new();
+    for object in &saved {
+        let Some(item) = world.resource::<ObjectItems>().get(&object.item).copied() else {
+            warn!("saved level contains the unknown item {}", object.item);
+            entities.0.push(None);
+            continue;
+        };
+        let entity = spawn_item(world, &item, object.transform.translation.xy());
+        world.entity_mut(entity).insert(object.transform);
+        entities.0.push(Some(entity));
+        loaded.push((entity, item.name));
+    }
+
+    // the data is restored once all objects exist, so links between them can be restored as well
+    for (index, object) in saved.into_iter().enumerate() {
+        let (Some(entity), Some(data)) = (entities.entity(index), object.data) else {
+            continue;
+        };
+        world.resource_scope(|world, hooks: Mut<SaveHooks>| {
+            let mut object = world.entity_mut(entity);
+            match hooks.0.iter().find(|it| object.contains_id(it.component)) {
+                Some(hook) => (hook.load)(&data, &mut object, &entities),
+                None => Ok(()),
+            }
+        })?;
+    }
+    Ok(loaded)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::hit::Hittable;
+
+    /// A type as a plugin outside of the core would register it.
+    #[derive(Component, Default, PartialEq, Debug, Serialize, Deserialize)]
+    struct Bell {
+        rung: u32,
+    }
+
+    fn app() -> App {
+        let mut app = App::new();
+        app.register_object(
+            ObjectType::<Bell>::new()
+                .item(
+                    "Bell",
+                    |assets| assets.note_icon.clone(),
+                    |entity| {
+                        entity.insert(Bell::default());
+                    },
+                )
+                .save(
+                    |object, _| object.get::<Bell>().map(|it| it.rung),
+                    |rung, object, _| {
+                        object.insert(Bell {
+                            rung: rung.unwrap_or_default(),
+                        });
+                    },
+                )
+                .on_hit(|_| ObjectKind::Object, |_: Trigger<WaveHitEvent>| {}),
+        );
+        app
+    }
+
+    #[test]
+    fn test_cycle() {
+        let list = [1, 2, 3];
+        assert_eq!(Some(2), cycle(&list, Some(1), 1.0));
+        assert_eq!(Some(1), cycle(&list, Some(3), 1.0));
+        assert_eq!(Some(3), cycle(&list, Some(1), -1.0));
+        assert_eq!(Some(1), cycle(&list, None, 1.0));
+        assert_eq!(Some(3), cycle(&list, None, -1.0));
+        assert_eq!(None, cycle(&[] as &[i32], None, 1.0));
+    }
+
+    #[test]
+    fn test_registered_item_spawns_a_hittable_object() {
+        let mut app = app();
+        let world = app.world_mut();
+        let item = *world.resource::<ObjectItems>().get("Bell").unwrap();
+
+        let bell = spawn_item(world, &item, Vec2::new(10.0, 20.0));
+        let bell = world.entity(bell);
+        assert!(bell.contains::<Hittable>());
+        assert_eq!(Some(&Bell::default()), bell.get::<Bell>());
+        assert_eq!(
+            Vec2::new(10.0, 20.0),
+            bell.get::<Transform>().unwrap().translation.xy()
+        );
+    }
+
+    #[test]
+    fn test_saved_objects_are_restored() {
+        let mut app = app();
+        let world = app.world_mut();
+        let item = *world.resource::<ObjectItems>().get("Bell").unwrap();
+        let bell = spawn_item(world, &item, Vec2::new(10.0, 20.0));
+        world.entity_mut(bell).insert(Bell { rung: 3 });
+
+        let content = save_objects(world, [(bell, "Bell")]).unwrap();
+        world.entity_mut(bell).despawn();
+        let loaded = load_objects(world, &content).unwrap();
+
+        assert_eq!(1, loaded.len());
+        let (restored, item) = loaded[0];
+        assert_eq!("Bell", item);
+        assert_eq!(
+            Some(&Bell { rung: 3 }),
+            world.entity(restored).get::<Bell>()
+        );
+        // unknown items are skipped
+        let content = content.replace("Bell", "Gong");
+        assert!(load_objects(world, &content).unwrap().is_empty());
+    }
+}
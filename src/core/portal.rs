@@ -0,0 +1,113 @@
+//! # Portal
+//!
+//! Portals come in linked pairs. A ring which reaches one portal is not passed on to it, instead
+//! the linked portal starts a new ring. The new ring either starts from zero or keeps the size the
+//! ring had when it reached the portal, which is the travel distance between the activator and the
+//! portal. Objects inside the kept size are not hit again.
+//!
+//! The ring of a portal reaches its own partner as well, but does not enter it.
+
+use crate::core::game::{activator_icon, spawn_activator};
+use crate::core::model::{ActivatorColor, Portal};
+use crate::core::object::{ObjectAppExt, ObjectField, ObjectType};
+use crate::core::zone::{ZoneArea, travel_distance};
+use bevy::prelude::*;
+
+pub(super) struct PortalPlugin;
+
+impl Plugin for PortalPlugin {
+    fn build(&self, app: &mut App) {
+        // portals are saved and hit as activators
+        app.register_object(
+            ObjectType::<Portal>::new()
+                .item("Portal", activator_icon, spawn_portal)
+                .draw(draw_portals)
+                .field(KEEP_SIZE),
+        );
+    }
+}
+
+/// The portal a ring of the source continues from after it reached the given portal, `None` for an
+/// unlinked portal or a ring of its partner.
+pub fn portal_exit(portal: &Portal, source: Entity) -> Option<Entity> {
+    portal.link.filter(|it| *it != source)
+}
+
+/// The size the new ring of the linked portal starts with.
+pub fn portal_ring_size(portal: &Portal, source: Vec2, entry: Vec2, zones: &[ZoneArea]) -> f32 {
+    if portal.keep_size {
+        travel_distance(source, entry, zones)
+    } else {
+        0.0
+    }
+}
+
+/// Whether the ring of the linked portal keeps its size.
+const KEEP_SIZE: ObjectField = ObjectField {
+    name: "Exit Ring",
+    steps: None,
+    value: |object| {
+        let portal = object.get::<Portal>()?;
+        Some(if portal.keep_size { "kept" } else { "reset" }.to_string())
+    },
+    edit: |object, _| {
+        if let Some(mut portal) = object.get_mut::<Portal>() {
+            portal.keep_size = !portal.keep_size;
+        }
+    },
+};
+
+/// A new portal is linked with the oldest one without a partner.
+fn spawn_portal(entity: &mut EntityWorldMut) {
+    spawn_activator(entity);
+    let id = entity.id();
+    let partner = entity.world_scope(|world| {
+        let mut portals = world.query::<(Entity, &Portal)>();
+        let partner = portals
+            .iter(world)
+            .filter(|(_, portal)| {
+                portal
+                    .link
+                    .is_none_or(|it| world.get::<Portal>(it).is_none())
+            })
+            .map(|(entity, _)| entity)
+            .min();
+        if let Some(partner) = partner
+            && let Some(mut portal) = world.get_mut::<Portal>(partner)
+        {
+            portal.link = Some(id);
+        }
+        partner
+    });
+    entity.insert(Portal {
+        link: partner,
+        keep_size: false,
+    });
+}
+
+/// Mark portals with an oval and connect linked pairs with a faint line.
+fn draw_portals(
+    portals: Query<(Entity, &Portal, &ActivatorColor, &Transform)>,
+    positions: Query<&Transform>,
+    mut gizmos: Gizmos,
+) {
+    for (entity, portal, color, transform) in &portals {
+        let center = transform.translation.xy();
+        gizmos.ellipse_2d(
+            Isometry2d::from_translation(center),
+            Vec2::new(14.0, 22.0),
+            color,
+        );
+
+        // every pair is connected only once
+        if let Some(link) = portal.link.filter(|it| *it > entity)
+            && let Ok(other) = positions.get(link)
+        {
+            gizmos.line_2d(
+                center,
+                other.translation.xy(),
+                Color::srgba(1.0, 1.0, 1.0, 0.15),
+            );
+        }
+    }
+}
@@ -0,0 +1,1314 @@
+//! # Prediction
+//!
+//! A deterministic model of the execution. Since every activator wave grows with a constant speed,
+//! the time at which it reaches an object is known in advance. The prediction replays the rules of
+//! [`crate::core::game`] on a snapshot of the board: a wave hits every other object once and an
+//! activator which is hit starts a new wave, replacing its current one.
+//!
+//! Chain reactions can go on forever, so the prediction stops at a time horizon.
+
+use crate::core::game::LevelConfig;
+use crate::core::hit::{Hittable, HittableTypes};
+use crate::core::model::{
+    ActivatorLimit, ActivatorShape, ActivatorSpeed, ActivatorType, Anchor, Gate, GateInputs,
+    Motion, Portal, Pulse, Wall, Zone,
+};
+use crate::core::motion::{MotionPath, start_position};
+use crate::core::portal::{portal_exit, portal_ring_size};
+use crate::core::shape::{Sector, direction, sweep_angle, sweep_speed};
+use crate::core::wall::{WallSegment, echo_distances, is_occluded, wall_segments};
+use crate::core::zone::{ZoneArea, travel_distance, zone_areas};
+use crate::state::MinimalGameState;
+use bevy::platform::collections::HashMap;
+use bevy::prelude::*;
+use std::cmp::{Ordering, Reverse};
+use std::collections::BinaryHeap;
+use std::f32::consts::TAU;
+use std::iter::once;
+
+pub(super) struct PredictionPlugin;
+
+impl Plugin for PredictionPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Prediction>().add_systems(
+            PostUpdate,
+            update_prediction.run_if(in_state(MinimalGameState::Running)),
+        );
+    }
+}
+
+/// Predictions end at this time in seconds, even if the chain reaction goes on.
+const HORIZON: f32 = 60.0;
+/// Upper limit of predicted events to keep dense chain reactions cheap.
+const MAX_EVENTS: usize = 2000;
+/// Shortest seconds between two checks of a wave and a moving object. Otherwise a step lasts as
+/// long as the wave cannot reach the object, even if it approaches at full speed.
+const MOTION_STEP: f32 = 1.0 / 60.0;
+/// Longest seconds between two checks, zones may let the wave catch up faster than expected.
+const MAX_MOTION_STEP: f32 = 0.25;
+/// Seconds up to which the time a wave passes a moving object is bisected.
+const MOTION_PRECISION: f32 = 1.0 / 240.0;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ObjectKind {
+    Note,
+    Activator {
+        main: bool,
+    },
+    /// any other registered type, which reacts to a hit without starting a wave
+    Object,
+}
+
+/// An object on the board as seen by the prediction.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SimObject {
+    pub entity: Entity,
+    /// the position at the start of the execution
+    pub position: Vec2,
+    pub kind: ObjectKind,
+    pub pulse: Option<PulseSpec>,
+    /// seconds between a hit and the start of the ring
+    pub delay: f32,
+    /// the maximum size of the ring or the length of a sweep, `None` if unlimited
+    pub reach: Option<f32>,
+    /// multiplier of the grow factor for the ring
+    pub speed: f32,
+    pub wave: WaveShape,
+    pub gate: Option<Gate>,
+    pub portal: Option<Portal>,
+    pub motion: Option<MotionPath>,
+}
+
+/// The shape of the wave of an activator.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum WaveShape {
+    Ring {
+        sector: Sector,
+    },
+    /// a line which turns once in `turn` seconds, starting at the direction, and ends after
+    /// `duration` seconds
+    Sweep {
+        direction: Vec2,
+        turn: f32,
+        duration: f32,
+    },
+}
+
+/// The rings of a pulse activator with the interval in seconds.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PulseSpec {
+    pub interval: f32,
+    pub count: Option<u32>,
+}
+
+/// A wave of an activator reaches an object.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PredictedEvent {
+    /// seconds since the start of the execution
+    pub time: f32,
+    pub source: Entity,
+    pub target: Entity,
+    pub kind: ObjectKind,
+    /// the position of the target relative to the source at the time of the hit
+    pub offset: Vec2,
+    /// the position of the target at the time of the hit
+    pub position: Vec2,
+}
+
+/// The time span in which a wave of an activator grows.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Wave {
+    /// the time the activator was hit, before the start if it is delayed
+    pub hit: f32,
+    pub start: f32,
+    /// the wave ends when it has reached every object or when the activator is hit again
+    pub end: f32,
+    /// index of the ring of a pulse activator since it was enabled, 0 for the first one
+    pub pulse: u32,
+    /// size of the ring at its start, above zero for a ring continued by a portal
+    pub size: f32,
+}
+
+#[derive(Resource, Debug, Default, Clone)]
+pub struct Prediction {
+    /// all events sorted by time
+    pub events: Vec<PredictedEvent>,
+    /// all waves per activator sorted by time
+    pub waves: HashMap<Entity, Vec<Wave>>,
+    /// the time of the last wave end or the horizon for endless chain reactions
+    pub duration: f32,
+}
+
+impl Prediction {
+    /// All waves of the activator which are growing at the given time, the newest one last.
+    pub fn waves_at(&self, activator: Entity, time: f32) -> Vec<Wave> {
+        self.waves
+            .get(&activator)
+            .into_iter()
+            .flatten()
+            .filter(|wave| wave.start <= time && time < wave.end)
+            .copied()
+            .collect()
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord)]
+enum Action {
+    /// a wave of the owner reaches the target
+    Hit { target: usize },
+    /// the owner starts its ring after a delay
+    Start { hit: OrderedTime },
+    /// the owner emits its next ring
+    Pulse { index: u32 },
+}
+
+/// A time which can be compared in the event queue.
+#[derive(Debug, Clone, Copy)]
+struct OrderedTime(f32);
+
+impl PartialEq for OrderedTime {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for OrderedTime {}
+
+impl PartialOrd for OrderedTime {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for OrderedTime {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.0.total_cmp(&other.0)
+    }
+}
+
+/// An action of an object in the event queue, ordered by time.
+#[derive(Debug, PartialEq)]
+struct Scheduled {
+    time: f32,
+    owner: usize,
+    /// the activation of the owner which scheduled the action
+    generation: u32,
+    action: Action,
+}
+
+impl Eq for Scheduled {}
+
+impl PartialOrd for Scheduled {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Scheduled {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.time
+            .total_cmp(&other.time)
+            .then(self.owner.cmp(&other.owner))
+            .then(self.action.cmp(&other.action))
+    }
+}
+
+struct Simulation<'a> {
+    objects: &'a [SimObject],
+    walls: &'a [WallSegment],
+    zones: &'a [ZoneArea],
+    grow_factor: f32,
+    queue: BinaryHeap<Reverse<Scheduled>>,
+    /// increased whenever an activator is enabled again, which cancels all its scheduled actions
+    generations: Vec<u32>,
+    /// the hits the logic gates have collected
+    inputs: Vec<GateInputs>,
+    waves: Vec<Vec<Wave>>,
+}
+
+impl Simulation<'_> {
+    fn schedule(&mut self, time: f32, owner: usize, action: Action) {
+        self.queue.push(Reverse(Scheduled {
+            time,
+            owner,
+            generation: self.generations[owner],
+            action,
+        }));
+    }
+
+    /// Enable the activator, which replaces all of its waves. A delay activator which is hit starts
+    /// its ring later, the ring of a portal may start with a size.
+    fn activate(&mut self, index: usize, time: f32, hit: bool, size: f32) {
+        self.generations[index] += 1;
+        for wave in &mut self.waves[index] {
+            wave.end = wave.end.min(time);
+        }
+
+        let delay = self.objects[index].delay;
+        if hit && delay > 0.0 {
+            self.schedule(
+                time + delay,
+                index,
+                Action::Start {
+                    hit: OrderedTime(time),
+                },
+            );
+        } else {
+            self.start_wave(index, time, time, 0, size);
+        }
+    }
+
+    fn position_at(&self, index: usize, time: f32) -> Vec2 {
+        let object = self.objects[index];
+        object
+            .motion
+            .map(|it| it.position(object.position, time))
+            .unwrap_or(object.position)
+    }
+
+    /// The seconds after its start at which the wave of the activator passes the target and
+    /// whether it hits the target there. Walls and cones let a ring pass without a hit.
+    fn passes(&self, index: usize, target: usize, time: f32) -> Vec<(f32, bool)> {
+        let object = self.objects[index];
+        let center = self.position_at(index, time);
+        let position = self.position_at(target, time);
+        let occluded = is_occluded(center, position, self.walls);
+        match object.wave {
+            WaveShape::Ring { sector } => {
+                let growth = self.grow_factor * object.speed;
+                let direct = (
+                    travel_distance(center, position, self.zones),
+                    !occluded && sector.covers(position - center),
+                );
+                let echoes = echo_distances(center, position, &sector, self.walls, self.zones)
+                    .into_iter()
+                    .map(|it| (it, true));
+                once(direct)
+                    .chain(echoes)
+                    .map(|(distance, hits)| (distance / growth, hits))
+                    .collect()
+            }
+            WaveShape::Sweep {
+                direction, turn, ..
+            } => {
+                let reached = object.reach.is_none_or(|it| center.distance(position) < it);
+                vec![(
+                    sweep_angle(direction, position - center) / TAU * turn,
+                    reached && !occluded,
+                )]
+            }
+        }
+    }
+
+    /// The seconds after its start at which the wave of the activator passes the target if one of
+    /// them moves, `None` if it does not within the limit or the horizon. The distance and angle
+    /// change over time, so the wave is checked in steps which skip the time the target cannot be
+    /// reached in, and the pass is bisected between the last two checks.
+    fn moving_pass(
+        &self,
+        index: usize,
+        target: usize,
+        time: f32,
+        grown: f32,
+        limit: Option<f32>,
+    ) -> Option<(f32, bool)> {
+        let object = self.objects[index];
+        let last = limit.unwrap_or(HORIZON).min(HORIZON - time).max(0.0);
+        let positions = |delay: f32| {
+            (
+                self.position_at(index, time + delay),
+                self.position_at(target, time + delay),
+            )
+        };
+        // the activator and the target approach each other at most this fast
+        let approach = self.max_speed(index) + self.max_speed(target);
+        // how far the wave is still away from the target, below zero once it passed, and how
+        // fast this may shrink at most
+        let remaining = |delay: f32| -> (f32, f32) {
+            let (center, position) = positions(delay);
+            match object.wave {
+                WaveShape::Ring { .. } => {
+                    let growth = self.grow_factor * object.speed;
+                    let size = (grown + delay) * growth;
+                    (
+                        travel_distance(center, position, self.zones) - size,
+                        growth + approach,
+                    )
+                }
+                WaveShape::Sweep {
+                    direction, turn, ..
+                } => {
+                    let turning = TAU / turn;
+                    let distance = center.distance(position).max(f32::EPSILON);
+                    (
+                        sweep_angle(direction, position - center) - delay * turning,
+                        turning + approach / distance,
+                    )
+                }
+            }
+        };
+
+        let mut before = 0.0;
+        let mut delay = 0.0;
+        loop {
+            let (distance, rate) = remaining(delay);
+            if distance < 0.0 {
+                break;
+            }
+            if delay >= last {
+                return None;
+            }
+            before = delay;
+            delay = (delay + (distance / rate).clamp(MOTION_STEP, MAX_MOTION_STEP)).min(last);
+        }
+        if delay > 0.0 {
+            while delay - before > MOTION_PRECISION {
+                let middle = (before + delay) / 2.0;
+                if remaining(middle).0 < 0.0 {
+                    delay = middle;
+                } else {
+                    before = middle;
+                }
+            }
+        }
+
+        let (center, position) = positions(delay);
+        let occluded = is_occluded(center, position, self.walls);
+        let hits = match object.wave {
+            // an object inside the size the wave started with is not hit
+            WaveShape::Ring { sector } => {
+                delay > 0.0 && !occluded && sector.covers(position - center)
+            }
+            WaveShape::Sweep { .. } => {
+                !occluded && object.reach.is_none_or(|it| center.distance(position) < it)
+            }
+        };
+        Some((delay, hits))
+    }
+
+    /// The fastest the object moves, zero if it does not.
+    fn max_speed(&self, index: usize) -> f32 {
+        let object = self.objects[index];
+        object
+            .motion
+            .map(|it| it.max_speed(object.position))
+            .unwrap_or_default()
+    }
+
+    fn start_wave(&mut self, index: usize, hit: f32, time: f32, pulse: u32, size: f32) {
+        let object = self.objects[index];
+        // seconds the wave would have grown to its size, a sweep always starts at its direction
+        let (size, grown) = match object.wave {
+            WaveShape::Ring { .. } => (size, size / (self.grow_factor * object.speed)),
+            WaveShape::Sweep { .. } => (0.0, 0.0),
+        };
+        // seconds until the wave ends at its limit
+        let limit = match object.wave {
+            WaveShape::Ring { .. } => object
+                .reach
+                .map(|it| (it / (self.grow_factor * object.speed) - grown).max(0.0)),
+            WaveShape::Sweep { duration, .. } => Some(duration),
+        };
+        let mut end = time;
+        for target in 0..self.objects.len() {
+            if target == index {
+                continue;
+            }
+            let mut passes: Vec<(f32, bool)> = self
+                .passes(index, target, time)
+                .into_iter()
+                .map(|(delay, hits)| (delay - grown, hits))
+                .collect();
+            if object.motion.is_some() || self.objects[target].motion.is_some() {
+                // the echoes are taken from the positions at the start like in the execution
+                passes[0] = self
+                    .moving_pass(index, target, time, grown, limit)
+                    .unwrap_or((limit.unwrap_or(HORIZON), false));
+            }
+            for (delay, hits) in passes {
+                if delay < 0.0 {
+                    // inside the size the wave started with
+                    continue;
+                }
+                if let Some(limit) = limit
+                    && delay >= limit
+                {
+                    // the wave ends before it reaches the object
+                    end = end.max(time + limit);
+                    continue;
+                }
+                end = end.max(time + delay);
+                if hits {
+                    self.schedule(time + delay, index, Action::Hit { target });
+                }
+            }
+        }
+        self.waves[index].push(Wave {
+            hit,
+            start: time,
+            end,
+            pulse,
+            size,
+        });
+
+        if let Some(spec) = self.objects[index].pulse
+            && spec.count.is_none_or(|count| pulse + 1 < count)
+        {
+            self.schedule(
+                time + spec.interval,
+                index,
+                Action::Pulse { index: pulse + 1 },
+            );
+        }
+    }
+}
+
+/// Predict the execution for the objects, walls and zones with the given growth of the activators
+/// per second.
+pub fn predict(
+    objects: &[SimObject],
+    walls: &[WallSegment],
+    zones: &[ZoneArea],
+    grow_factor: f32,
+) -> Prediction {
+    let mut simulation = Simulation {
+        objects,
+        walls,
+        zones,
+        grow_factor,
+        queue: BinaryHeap::new(),
+        generations: vec![0; objects.len()],
+        inputs: vec![GateInputs::default(); objects.len()],
+        waves: vec![vec![]; objects.len()],
+    };
+    let mut events = vec![];
+
+    for (index, object) in objects.iter().enumerate() {
+        if object.kind == (ObjectKind::Activator { main: true }) {
+            simulation.activate(index, 0.0, false, 0.0);
+        }
+    }
+
+    let mut complete = true;
+    while let Some(Reverse(scheduled)) = simulation.queue.pop() {
+        if scheduled.generation != simulation.generations[scheduled.owner] {
+            // the owner was hit again and started over
+            continue;
+        }
+        if scheduled.time > HORIZON || events.len() >= MAX_EVENTS {
+            complete = false;
+            break;
+        }
+
+        match scheduled.action {
+            Action::Hit { target } => {
+                let object = objects[target];
+                let source = objects[scheduled.owner].entity;
+                let source_position = simulation.position_at(scheduled.owner, scheduled.time);
+                let target_position = simulation.position_at(target, scheduled.time);
+                events.push(PredictedEvent {
+                    time: scheduled.time,
+                    source,
+                    target: object.entity,
+                    kind: object.kind,
+                    offset: target_position - source_position,
+                    position: target_position,
+                });
+                if let ObjectKind::Activator { .. } = object.kind
+                    && object
+                        .gate
+                        .is_none_or(|gate| simulation.inputs[target].hit(&gate, source))
+                {
+                    match object.portal {
+                        Some(portal) => {
+                            let exit = portal_exit(&portal, source)
+                                .and_then(|link| objects.iter().position(|it| it.entity == link));
+                            if let Some(exit) = exit {
+                                let size = portal_ring_size(
+                                    &portal,
+                                    source_position,
+                                    target_position,
+                                    zones,
+                                );
+                                simulation.activate(exit, scheduled.time, true, size);
+                            }
+                        }
+                        None => simulation.activate(target, scheduled.time, true, 0.0),
+                    }
+                }
+            }
+            Action::Start { hit } => {
+                simulation.start_wave(scheduled.owner, hit.0, scheduled.time, 0, 0.0);
+            }
+            Action::Pulse { index } => {
+                simulation.start_wave(scheduled.owner, scheduled.time, scheduled.time, index, 0.0);
+            }
+        }
+    }
+
+    let waves: HashMap<Entity, Vec<Wave>> = simulation
+        .waves
+        .into_iter()
+        .enumerate()
+        .filter(|(_, waves)| !waves.is_empty())
+        .map(|(index, waves)| (objects[index].entity, waves))
+        .collect();
+    let duration = if complete {
+        waves
+            .values()
+            .flatten()
+            .map(|wave| wave.end)
+            .fold(0.0, f32::max)
+    } else {
+        events.last().map(|it| it.time).unwrap_or(HORIZON)
+    };
+
+    Prediction {
+        events,
+        waves,
+        duration,
+    }
+}
+
+/// Recalculate the prediction whenever an object is placed, moved, changed or removed. Objects
+/// moving during the execution are predicted from their start position. Every hittable object is
+/// part of it, including the types registered outside of the core.
+fn update_prediction(
+    moved: Query<
+        (),
+        (
+            Or<(With<Hittable>, With<Wall>, With<Zone>)>,
+            Changed<Transform>,
+            Without<Anchor>,
+        ),
+    >,
+    changed: Query<
+        (),
+        (
+            Or<(With<Hittable>, With<Wall>, With<Zone>)>,
+            Or<(
+                Changed<Pulse>,
+                Changed<ActivatorType>,
+                Changed<ActivatorLimit>,
+                Changed<ActivatorSpeed>,
+                Changed<ActivatorShape>,
+                Changed<Gate>,
+                Changed<Portal>,
+                Changed<Motion>,
+                Changed<Wall>,
+                Changed<Zone>,
+            )>,
+        ),
+    >,
+    mut removed_objects: RemovedComponents<Hittable>,
+    mut removed_walls: RemovedComponents<Wall>,
+    mut removed_zones: RemovedComponents<Zone>,
+    objects: Query<EntityRef, With<Hittable>>,
+    types: Res<HittableTypes>,
+    activators: Query<(
+        &Transform,
+        &ActivatorType,
+        &ActivatorLimit,
+        &ActivatorSpeed,
+        &ActivatorShape,
+        Option<&Pulse>,
+        Option<&Gate>,
+        Option<&Portal>,
+    )>,
+    motions: Query<&Motion>,
+    anchors: Query<&Anchor>,
+    transforms: Query<&Transform>,
+    walls: Query<(&Wall, &Transform)>,
+    zones: Query<(&Zone, &Transform)>,
+    config: Option<Res<LevelConfig>>,
+    mut prediction: ResMut<Prediction>,
+) {
+    let removed = removed_objects.read().count()
+        + removed_walls.read().count()
+        + removed_zones.read().count()
+        > 0;
+    let Some(config) = config else {
+        return;
+    };
+    if moved.is_empty() && changed.is_empty() && !removed && !config.is_changed() {
+        return;
+    }
+
+    let position =
+        |entity: Entity| start_position(entity, &anchors, &transforms).unwrap_or_default();
+    let motion = |entity: Entity| {
+        let motion = motions.get(entity).ok()?;
+        let center = match motion {
+            Motion::Orbit { center, .. } => start_position(*center, &anchors, &transforms),
+            Motion::Path { .. } => None,
+        };
+        MotionPath::new(motion, center, config.beat_length())
+    };
+    let mut objects: Vec<SimObject> = objects
+        .iter()
+        .map(|object| {
+            let entity = object.id();
+            let simulated = SimObject {
+                entity,
+                position: position(entity),
+                kind: types.kind(&object),
+                pulse: None,
+                delay: 0.0,
+                reach: None,
+                speed: 1.0,
+                wave: WaveShape::Ring {
+                    sector: Sector::FULL,
+                },
+                gate: None,
+                portal: None,
+                motion: motion(entity),
+            };
+            // the objects which start a wave when they are hit
+            let Ok((transform, activator, limit, speed, shape, pulse, gate, portal)) =
+                activators.get(entity)
+            else {
+                return simulated;
+            };
+            SimObject {
+                pulse: pulse.map(|pulse| PulseSpec {
+                    interval: pulse.interval * config.beat_length(),
+                    count: pulse.count,
+                }),
+                delay: match activator {
+                    ActivatorType::Delay { beats } => beats * config.beat_length(),
+                    _ => 0.0,
+                },
+                reach: match shape {
+                    ActivatorShape::Sweep { .. } => limit.radius,
+                    _ => limit.max_size(config.growth(speed) * config.beat_length()),
+                },
+                speed: **speed,
+                wave: match shape {
+                    ActivatorShape::Sweep { beats } => {
+                        let turn = TAU / sweep_speed(*beats, speed, &config);
+                        WaveShape::Sweep {
+                            direction: direction(transform),
+                            turn,
+                            duration: limit
+                                .lifetime
+                                .map(|it| (it * config.beat_length()).min(turn))
+                                .unwrap_or(turn),
+                        }
+                    }
+                    _ => WaveShape::Ring {
+                        sector: Sector::new(shape, transform),
+                    },
+                },
+                gate: gate.copied(),
+                portal: portal.copied(),
+                ..simulated
+            }
+        })
+        .collect();
+    // queries have no stable order, but the prediction has to be deterministic
+    objects.sort_by_key(|it| it.entity);
+
+    *prediction = predict(
+        &objects,
+        &wall_segments(&walls),
+        &zone_areas(&zones),
+        config.grow_factor,
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::game::tests::{execute, execution_app, place, spawn_main};
+    use std::f32::consts::FRAC_PI_2;
+
+    fn object(index: u32, x: f32, y: f32, kind: ObjectKind) -> SimObject {
+        SimObject {
+            entity: Entity::from_raw(index),
+            position: Vec2::new(x, y),
+            kind,
+            pulse: None,
+            delay: 0.0,
+            reach: None,
+            speed: 1.0,
+            wave: WaveShape::Ring {
+                sector: Sector::FULL,
+            },
+            gate: None,
+            portal: None,
+            motion: None,
+        }
+    }
+
+    const MAIN: ObjectKind = ObjectKind::Activator { main: true };
+    const PASSIVE: ObjectKind = ObjectKind::Activator { main: false };
+
+    #[test]
+    fn test_notes_are_played_by_distance() {
+        let objects = [
+            object(0, 0.0, 0.0, MAIN),
+            object(1, 200.0, 0.0, ObjectKind::Note),
+            object(2, 0.0, 100.0, ObjectKind::Note),
+        ];
+        let prediction = predict(&objects, &[], &[], 100.0);
+
+        let times: Vec<(f32, Entity)> = prediction
+            .events
+            .iter()
+            .map(|it| (it.time, it.target))
+            .collect();
+        assert_eq!(
+            vec![(1.0, Entity::from_raw(2)), (2.0, Entity::from_raw(1))],
+            times
+        );
+        assert_eq!(2.0, prediction.duration);
+        assert_eq!(
+            vec![Wave {
+                hit: 0.0,
+                start: 0.0,
+                end: 2.0,
+                pulse: 0,
+                size: 0.0
+            }],
+            prediction.waves_at(Entity::from_raw(0), 1.5)
+        );
+        assert!(prediction.waves_at(Entity::from_raw(0), 2.0).is_empty());
+    }
+
+    #[test]
+    fn test_hit_activator_starts_a_new_wave() {
+        let objects = [
+            object(0, 0.0, 0.0, MAIN),
+            object(1, 100.0, 0.0, PASSIVE),
+            object(2, 100.0, 100.0, ObjectKind::Note),
+        ];
+        let prediction = predict(&objects, &[], &[], 100.0);
+
+        // the note is reached by the main and then by the passive activator
+        let notes: Vec<Entity> = prediction
+            .events
+            .iter()
+            .filter(|it| it.kind == ObjectKind::Note)
+            .map(|it| it.source)
+            .take(2)
+            .collect();
+        assert_eq!(vec![Entity::from_raw(0), Entity::from_raw(1)], notes);
+        assert_eq!(
+            vec![Wave {
+                hit: 1.0,
+                start: 1.0,
+                end: 2.0,
+                pulse: 0,
+                size: 0.0
+            }],
+            prediction.waves_at(Entity::from_raw(1), 1.5)
+        );
+    }
+
+    #[test]
+    fn test_restarted_wave_cancels_pending_hits() {
+        // the activators hit each other again and again
+        let objects = [
+            object(0, 0.0, 0.0, MAIN),
+            object(1, 100.0, 0.0, PASSIVE),
+            object(2, -1000.0, 0.0, ObjectKind::Note),
+        ];
+        let prediction = predict(&objects, &[], &[], 100.0);
+
+        // the note is too far away to be reached before the main activator is hit again
+        assert!(
+            prediction
+                .events
+                .iter()
+                .all(|it| it.kind != ObjectKind::Note)
+        );
+        assert_eq!(
+            Some(Wave {
+                hit: 0.0,
+                start: 0.0,
+                end: 2.0,
+                pulse: 0,
+                size: 0.0
+            }),
+            prediction.waves[&Entity::from_raw(0)].first().copied()
+        );
+        assert!(prediction.duration <= HORIZON);
+    }
+
+    #[test]
+    fn test_pulse_emits_overlapping_waves() {
+        let mut pulse = object(0, 0.0, 0.0, MAIN);
+        pulse.pulse = Some(PulseSpec {
+            interval: 0.5,
+            count: Some(3),
+        });
+        let objects = [pulse, object(1, 100.0, 0.0, ObjectKind::Note)];
+        let prediction = predict(&objects, &[], &[], 100.0);
+
+        let times: Vec<f32> = prediction.events.iter().map(|it| it.time).collect();
+        assert_eq!(vec![1.0, 1.5, 2.0], times);
+        assert_eq!(2, prediction.waves_at(Entity::from_raw(0), 0.75).len());
+        assert_eq!(2.0, prediction.duration);
+    }
+
+    #[test]
+    fn test_delay_starts_ring_later() {
+        let mut delay = object(1, 100.0, 0.0, PASSIVE);
+        delay.delay = 0.5;
+        let objects = [
+            object(0, 0.0, 0.0, MAIN),
+            delay,
+            object(2, 100.0, 50.0, ObjectKind::Note),
+        ];
+        let prediction = predict(&objects, &[], &[], 100.0);
+
+        let from_delay: Vec<f32> = prediction
+            .events
+            .iter()
+            .filter(|it| it.source == Entity::from_raw(1) && it.kind == ObjectKind::Note)
+            .map(|it| it.time)
+            .take(1)
+            .collect();
+        // hit after 1s, waits 0.5s, reaches the note after another 0.5s
+        assert_eq!(vec![2.0], from_delay);
+        assert_eq!(
+            Some(Wave {
+                hit: 1.0,
+                start: 1.5,
+                end: 2.5,
+                pulse: 0,
+                size: 0.0
+            }),
+            prediction.waves[&Entity::from_raw(1)].first().copied()
+        );
+    }
+
+    #[test]
+    fn test_limited_ring_does_not_reach_far_objects() {
+        let mut main = object(0, 0.0, 0.0, MAIN);
+        main.reach = Some(150.0);
+        let objects = [
+            main,
+            object(1, 100.0, 0.0, ObjectKind::Note),
+            object(2, 200.0, 0.0, ObjectKind::Note),
+        ];
+        let prediction = predict(&objects, &[], &[], 100.0);
+
+        let targets: Vec<Entity> = prediction.events.iter().map(|it| it.target).collect();
+        assert_eq!(vec![Entity::from_raw(1)], targets);
+        // the ring ends at its limit instead of the last hit
+        assert_eq!(1.5, prediction.duration);
+    }
+
+    #[test]
+    fn test_faster_activator_plays_subdivisions() {
+        let mut passive = object(1, 100.0, 0.0, PASSIVE);
+        passive.speed = 2.0;
+        let objects = [
+            object(0, 0.0, 0.0, MAIN),
+            passive,
+            object(2, 100.0, 200.0, ObjectKind::Note),
+        ];
+        let prediction = predict(&objects, &[], &[], 100.0);
+
+        // hit after 1s, the double speed ring reaches the note after another 1s
+        let passive_note = prediction
+            .events
+            .iter()
+            .find(|it| it.source == Entity::from_raw(1) && it.kind == ObjectKind::Note)
+            .map(|it| it.time);
+        assert_eq!(Some(2.0), passive_note);
+    }
+
+    #[test]
+    fn test_walls_block_and_reflect() {
+        let objects = [
+            object(0, 0.0, 0.0, MAIN),
+            object(1, 0.0, 50.0, ObjectKind::Note),
+            object(2, 0.0, -150.0, ObjectKind::Note),
+        ];
+        let walls = [
+            WallSegment {
+                start: Vec2::new(-100.0, 100.0),
+                end: Vec2::new(100.0, 100.0),
+                kind: Wall::Reflective,
+            },
+            WallSegment {
+                start: Vec2::new(-100.0, -100.0),
+                end: Vec2::new(100.0, -100.0),
+                kind: Wall::Blocking,
+            },
+        ];
+        let prediction = predict(&objects, &walls, &[], 100.0);
+
+        // the note in front of the reflective wall is played twice, the one behind the blocking
+        // wall never
+        let times: Vec<(f32, Entity)> = prediction
+            .events
+            .iter()
+            .map(|it| (it.time, it.target))
+            .collect();
+        assert_eq!(
+            vec![(0.5, Entity::from_raw(1)), (1.5, Entity::from_raw(1))],
+            times
+        );
+    }
+
+    #[test]
+    fn test_cone_and_sweep() {
+        let mut activator = object(0, 0.0, 0.0, MAIN);
+        let objects = |activator: SimObject| {
+            [
+                activator,
+                object(1, 0.0, 100.0, ObjectKind::Note),
+                object(2, -200.0, 0.0, ObjectKind::Note),
+            ]
+        };
+        let times = |prediction: Prediction| -> Vec<(f32, Entity)> {
+            prediction
+                .events
+                .iter()
+                .map(|it| (it.time, it.target))
+                .collect()
+        };
+
+        // the cone only covers the upper note
+        activator.wave = WaveShape::Ring {
+            sector: Sector {
+                direction: Vec2::Y,
+                width: FRAC_PI_2,
+            },
+        };
+        let prediction = predict(&objects(activator), &[], &[], 100.0);
+        assert_eq!(vec![(1.0, Entity::from_raw(1))], times(prediction));
+
+        // the sweep plays by angle, not by distance
+        activator.wave = WaveShape::Sweep {
+            direction: Vec2::X,
+            turn: 4.0,
+            duration: 4.0,
+        };
+        let prediction = predict(&objects(activator), &[], &[], 100.0);
+        let played = times(prediction);
+        assert_eq!(
+            vec![Entity::from_raw(1), Entity::from_raw(2)],
+            played.iter().map(|it| it.1).collect::<Vec<_>>()
+        );
+        assert!((1.0 - played[0].0).abs() < 1e-5);
+        assert!((2.0 - played[1].0).abs() < 1e-5);
+    }
+
+    #[test]
+    fn test_and_gate_waits_for_both_sources() {
+        let limited = |object: SimObject, reach: f32| SimObject {
+            reach: Some(reach),
+            ..object
+        };
+        let objects = |gate: Gate| {
+            [
+                limited(object(0, 0.0, 0.0, MAIN), 250.0),
+                limited(object(1, 400.0, 0.0, MAIN), 250.0),
+                SimObject {
+                    gate: Some(gate),
+                    ..limited(object(2, 200.0, 0.0, PASSIVE), 100.0)
+                },
+                object(3, 200.0, 50.0, ObjectKind::Note),
+            ]
+        };
+        let played_by_gate = |prediction: Prediction| -> Vec<f32> {
+            prediction
+                .events
+                .iter()
+                .filter(|it| it.source == Entity::from_raw(2))
+                .map(|it| it.time)
+                .collect()
+        };
+
+        let prediction = predict(&objects(Gate::And { sources: 2 }), &[], &[], 100.0);
+        assert_eq!(vec![2.5], played_by_gate(prediction));
+        let prediction = predict(&objects(Gate::And { sources: 3 }), &[], &[], 100.0);
+        assert!(played_by_gate(prediction).is_empty());
+    }
+
+    #[test]
+    fn test_portal_continues_the_ring() {
+        let objects = |keep_size: bool| {
+            [
+                SimObject {
+                    reach: Some(150.0),
+                    ..object(0, 0.0, 0.0, MAIN)
+                },
+                SimObject {
+                    portal: Some(Portal {
+                        link: Some(Entity::from_raw(2)),
+                        keep_size,
+                    }),
+                    ..object(1, 100.0, 0.0, PASSIVE)
+                },
+                SimObject {
+                    reach: Some(200.0),
+                    portal: Some(Portal {
+                        link: Some(Entity::from_raw(1)),
+                        keep_size,
+                    }),
+                    ..object(2, 1000.0, 0.0, PASSIVE)
+                },
+                object(3, 1000.0, 50.0, ObjectKind::Note),
+                object(4, 1000.0, 120.0, ObjectKind::Note),
+            ]
+        };
+        let played = |prediction: Prediction| -> Vec<(f32, Entity)> {
+            prediction
+                .events
+                .iter()
+                .filter(|it| it.kind == ObjectKind::Note)
+                .map(|it| (it.time, it.target))
+                .collect()
+        };
+
+        let prediction = predict(&objects(false), &[], &[], 100.0);
+        assert_eq!(
+            vec![(1.5, Entity::from_raw(3)), (2.2, Entity::from_raw(4))],
+            played(prediction)
+        );
+        // the ring leaves the portal with a size of 100, so the closer note is skipped
+        let prediction = predict(&objects(true), &[], &[], 100.0);
+        assert_eq!(vec![(1.2, Entity::from_raw(4))], played(prediction));
+    }
+
+    #[test]
+    fn test_moving_notes_are_hit_at_their_position() {
+        let path = |x: f32| {
+            Some(MotionPath::Path {
+                offset: Vec2::new(x, 0.0),
+                period: 4.0,
+            })
+        };
+        let objects = [
+            object(0, 0.0, 0.0, MAIN),
+            // moves towards the activator and is reached early
+            SimObject {
+                motion: path(-200.0),
+                ..object(1, 300.0, 0.0, ObjectKind::Note)
+            },
+            // runs away at the speed of the ring and is only caught on the way back
+            SimObject {
+                motion: path(200.0),
+                ..object(2, 100.0, 0.0, ObjectKind::Note)
+            },
+        ];
+        let prediction = predict(&objects, &[], &[], 100.0);
+
+        let times: Vec<(f32, Entity)> = prediction
+            .events
+            .iter()
+            .map(|it| (it.time, it.target))
+            .collect();
+        assert_eq!(2, times.len());
+        assert!((times[0].0 - 1.5).abs() <= MOTION_PRECISION);
+        assert_eq!(Entity::from_raw(1), times[0].1);
+        assert!((times[1].0 - 2.5).abs() <= MOTION_PRECISION);
+        assert_eq!(Entity::from_raw(2), times[1].1);
+    }
+
+    #[test]
+    fn test_registered_types_are_predicted() {
+        use crate::core::game::Looping;
+        use crate::core::game::activator_kind;
+        use crate::core::hit::WaveHitEvent;
+        use crate::core::model::Activator;
+        use crate::core::object::{ObjectAppExt, ObjectType};
+        use crate::music::model::{NaturalMinorScale, Note};
+        use bevy::ecs::system::RunSystemOnce;
+
+        // a type of object the core does not know
+        #[derive(Component, Default)]
+        struct Bell;
+
+        let mut app = App::new();
+        app.init_resource::<Prediction>()
+            .insert_resource(LevelConfig {
+                grow_factor: 100.0,
+                scale: Box::new(NaturalMinorScale::new(Note::A)),
+                bpm: 60.0,
+                release: 0.1,
+                looping: Looping {
+                    enabled: false,
+                    sync_to_background: false,
+                },
+                script: None,
+                item_limits: Vec::new(),
+            })
+            .register_object(
+                ObjectType::<Activator>::new()
+                    .on_hit(activator_kind, |_: Trigger<WaveHitEvent>| {}),
+            )
+            .register_object(
+                ObjectType::<Bell>::new()
+                    .on_hit(|_| ObjectKind::Object, |_: Trigger<WaveHitEvent>| {}),
+            );
+        let world = app.world_mut();
+        let main = world
+            .spawn((ActivatorType::Main, Transform::default()))
+            .id();
+        let bell = world
+            .spawn((Bell, Transform::from_xyz(100.0, 0.0, 0.0)))
+            .id();
+
+        world.run_system_once(update_prediction).unwrap();
+        let events: Vec<(f32, Entity, Entity, ObjectKind)> = world
+            .resource::<Prediction>()
+            .events
+            .iter()
+            .map(|it| (it.time, it.source, it.target, it.kind))
+            .collect();
+        assert_eq!(vec![(1.0, main, bell, ObjectKind::Object)], events);
+    }
+
+    /// Live notes are played in the first frame the ring passes them, so they are at most a few
+    /// frames late in a chain reaction.
+    const LIVE_TOLERANCE: f32 = 0.05;
+
+    /// Run an execution of the app with the systems of the game and check that it plays the notes
+    /// in the order and at the times of the prediction.
+    fn assert_played_as_predicted(app: &mut App) {
+        // the prediction is updated in the build mode
+        app.update();
+        let predicted: Vec<(f32, Entity)> = app
+            .world()
+            .resource::<Prediction>()
+            .events
+            .iter()
+            .filter(|it| it.kind == ObjectKind::Note)
+            .map(|it| (it.time, it.target))
+            .collect();
+        let played = execute(app, |_, _| {});
+
+        assert_eq!(
+            predicted.iter().map(|(_, note)| *note).collect::<Vec<_>>(),
+            played.iter().map(|(_, note)| *note).collect::<Vec<_>>()
+        );
+        for ((predicted, _), (played, _)) in predicted.iter().zip(&played) {
+            assert!(
+                (predicted - played).abs() < LIVE_TOLERANCE,
+                "predicted at {predicted}, played at {played}"
+            );
+        }
+    }
+
+    fn rotate(world: &mut World, entity: Entity, angle: f32) {
+        world.get_mut::<Transform>(entity).unwrap().rotation = Quat::from_rotation_z(angle);
+    }
+
+    /// Keep the ring of an activator from hitting the other activators, otherwise they enable each
+    /// other forever.
+    fn limit(world: &mut World, activator: Entity, radius: f32) {
+        world.entity_mut(activator).insert(ActivatorLimit {
+            radius: Some(radius),
+            lifetime: None,
+        });
+    }
+
+    #[test]
+    fn test_live_chain_reaction_is_predicted() {
+        let mut app = execution_app();
+        let world = app.world_mut();
+        spawn_main(world, 0.0, 0.0);
+        place(world, "Note", 100.0, 0.0);
+        place(world, "Note", 0.0, -260.0);
+        let activator = place(world, "Activator", 0.0, 150.0);
+        limit(world, activator, 130.0);
+        place(world, "Note", 30.0, 270.0);
+        let delay = place(world, "Delay", -200.0, 0.0);
+        limit(world, delay, 140.0);
+        place(world, "Note", -200.0, 130.0);
+        let pulse = place(world, "Pulse", 350.0, 20.0);
+        limit(world, pulse, 100.0);
+        world.get_mut::<Pulse>(pulse).unwrap().count = Some(2);
+        place(world, "Note", 420.0, -40.0);
+
+        assert_played_as_predicted(&mut app);
+    }
+
+    #[test]
+    fn test_live_walls_and_zones_are_predicted() {
+        let mut app = execution_app();
+        let world = app.world_mut();
+        spawn_main(world, 0.0, 0.0);
+        let wall = place(world, "Wall", 100.0, 0.0);
+        rotate(world, wall, FRAC_PI_2);
+        place(world, "Note", 200.0, 10.0);
+        place(world, "Note", 170.0, 190.0);
+        place(world, "Mirror", 0.0, -120.0);
+        place(world, "Note", -60.0, 30.0);
+        place(world, "Zone", -200.0, 0.0);
+        place(world, "Note", -320.0, -20.0);
+
+        assert_played_as_predicted(&mut app);
+    }
+
+    #[test]
+    fn test_live_gates_and_portals_are_predicted() {
+        let mut app = execution_app();
+        let world = app.world_mut();
+        spawn_main(world, 0.0, 0.0);
+        // the gate needs the rings of the main activator and of the cone which points at it
+        let gate = place(world, "Gate", 150.0, 0.0);
+        limit(world, gate, 140.0);
+        let cone = place(world, "Cone", 0.0, 100.0);
+        rotate(world, cone, Vec2::new(150.0, -100.0).to_angle());
+        place(world, "Note", 270.0, 0.0);
+        for (x, y) in [(-200.0, 0.0), (-400.0, 300.0)] {
+            let portal = place(world, "Portal", x, y);
+            limit(world, portal, 150.0);
+        }
+        place(world, "Note", -410.0, 420.0);
+
+        assert_played_as_predicted(&mut app);
+    }
+
+    #[test]
+    fn test_live_cones_and_sweeps_are_predicted() {
+        let mut app = execution_app();
+        let world = app.world_mut();
+        spawn_main(world, 0.0, 0.0);
+        place(world, "Cone", 0.0, 100.0);
+        place(world, "Note", 160.0, 110.0);
+        place(world, "Note", -150.0, 130.0);
+        let sweep = place(world, "Sweep", 0.0, -200.0);
+        rotate(world, sweep, 0.3);
+        limit(world, sweep, 150.0);
+        place(world, "Note", 90.0, -170.0);
+        place(world, "Note", -20.0, -310.0);
+
+        assert_played_as_predicted(&mut app);
+    }
+
+    #[test]
+    fn test_live_motion_is_predicted() {
+        let mut app = execution_app();
+        // beats are shorter than seconds
+        app.world_mut().resource_mut::<LevelConfig>().bpm = 90.0;
+        let world = app.world_mut();
+        let main = spawn_main(world, 0.0, 0.0);
+        let orbiting = place(world, "Note", 0.0, 150.0);
+        world.entity_mut(orbiting).insert(Motion::Orbit {
+            center: main,
+            beats: 4.0,
+        });
+        let moving = place(world, "Note", 300.0, 0.0);
+        world.entity_mut(moving).insert(Motion::Path {
+            offset: Vec2::new(-200.0, 0.0),
+            beats: 2.0,
+        });
+        let delay = place(world, "Delay", -100.0, -100.0);
+        world.entity_mut(delay).insert(Motion::Path {
+            offset: Vec2::new(0.0, -100.0),
+            beats: 3.0,
+        });
+        limit(world, delay, 120.0);
+        place(world, "Note", -170.0, -230.0);
+
+        assert_played_as_predicted(&mut app);
+    }
+}
@@ -0,0 +1,282 @@
+//! # Pulse
+//!
+//! A pulse activator emits a new ring every few beats. The newest ring is the ring of the
+//! activator and handled like the one of every other activator. The older rings keep growing in
+//! [`PulseRings`], each with its own list of objects it has not hit yet. A pulsing sweep emits a
+//! new line the same way, its older lines keep turning.
+
+use crate::core::game::{
+    ActivatorEnabledEvent, ExecutionClock, LevelConfig, activator_icon, spawn_activator,
+};
+use crate::core::hit::{Hittable, ObjectHitEvent, hit_object};
+use crate::core::model::{
+    ActivatorColor, ActivatorLimit, ActivatorShape, ActivatorSize, ActivatorSpeed, Echoes,
+    InactivatedObjects, Pulse, PulseRings, Ring, Wall, Zone,
+};
+use crate::core::object::{ObjectAppExt, ObjectField, ObjectType};
+use crate::core::shape::{Sector, max_wave_size, size_to_reach, sorted_by_shape, wave_growth};
+use crate::core::wall::{is_occluded, reached_echoes, sorted_echoes, wall_segments};
+use crate::core::zone::zone_areas;
+use crate::state::GameState;
+use bevy::prelude::*;
+
+/// Registers the pulse activators, their rings grow in the execution of the core plugin.
+pub(super) struct PulsePlugin;
+
+impl Plugin for PulsePlugin {
+    fn build(&self, app: &mut App) {
+        // pulses are saved and hit as activators
+        app.add_observer(start_pulse).register_object(
+            ObjectType::<Pulse>::new()
+                .item("Pulse", activator_icon, spawn_pulse)
+                .draw(draw_pulse_markers)
+                .field(INTERVAL)
+                .field(COUNT),
+        );
+    }
+}
+
+/// Every time a pulse activator is enabled, it starts pulsing from the beginning.
+fn start_pulse(
+    cause: Trigger<ActivatorEnabledEvent>,
+    pulses: Query<&Pulse>,
+    config: Res<LevelConfig>,
+    state: Res<State<GameState>>,
+    mut commands: Commands,
+) {
+    if *state != GameState::Execute {
+        return;
+    }
+    let Ok(pulse) = pulses.get(cause.target) else {
+        return;
+    };
+
+    commands.entity(cause.target).try_insert(PulseRings {
+        rings: vec![],
+        // the first ring is emitted when the activator is enabled
+        remaining: pulse.count.map(|it| it.saturating_sub(1)),
+        until_next: pulse.interval * config.beat_length(),
+    });
+}
+
+/// Replace the ring of a pulse activator with a new one and keep the old one growing.
+pub(super) fn emit_pulses(
+    mut pulses: Query<(
+        Entity,
+        &Pulse,
+        &mut PulseRings,
+        &ActivatorSize,
+        &ActivatorSpeed,
+        &ActivatorShape,
+        &InactivatedObjects,
+        Option<&Echoes>,
+    )>,
+    objects: Query<Entity, With<Hittable>>,
+    positions: Query<&Transform>,
+    walls: Query<(&Wall, &Transform)>,
+    zones: Query<(&Zone, &Transform)>,
+    config: Res<LevelConfig>,
+    clock: Res<ExecutionClock>,
+    mut commands: Commands,
+) {
+    let walls = wall_segments(&walls);
+    let zones = zone_areas(&zones);
+    for (activator, pulse, mut rings, size, speed, shape, inactive, echoes) in &mut pulses {
+        if rings.remaining == Some(0) {
+            continue;
+        }
+        rings.until_next -= clock.delta().as_secs_f32();
+        if rings.until_next > 0.0 {
+            continue;
+        }
+
+        // the ring was emitted a little before this frame
+        let overshoot = -rings.until_next;
+        rings.until_next += pulse.interval * config.beat_length();
+        if let Some(remaining) = &mut rings.remaining {
+            *remaining -= 1;
+        }
+        let echoes = echoes.map(|it| it.0.clone()).unwrap_or_default();
+        if !inactive.is_empty() || !echoes.is_empty() {
+            rings.rings.push(Ring {
+                size: **size,
+                inactivated: inactive.0.clone(),
+                echoes,
+            });
+        }
+
+        commands.entity(activator).try_insert((
+            ActivatorSize::new(overshoot * wave_growth(shape, speed, &config)),
+            InactivatedObjects(sorted_by_shape(
+                activator, shape, &objects, &positions, &zones,
+            )),
+            Echoes(sorted_echoes(
+                activator, shape, &objects, &positions, &walls, &zones,
+            )),
+        ));
+    }
+}
+
+/// Grow the older rings or turn the older lines of all pulse activators and check them for
+/// collisions.
+pub(super) fn execute_pulse_rings(
+    mut pulses: Query<(
+        Entity,
+        &mut PulseRings,
+        &ActivatorLimit,
+        &ActivatorSpeed,
+        &ActivatorShape,
+    )>,
+    positions: Query<&Transform>,
+    walls: Query<(&Wall, &Transform)>,
+    zones: Query<(&Zone, &Transform)>,
+    config: Res<LevelConfig>,
+    clock: Res<ExecutionClock>,
+    mut hits: EventWriter<ObjectHitEvent>,
+    mut commands: Commands,
+) {
+    let walls = wall_segments(&walls);
+    let zones = zone_areas(&zones);
+    for (activator, mut pulse, limit, speed, shape) in &mut pulses {
+        let Ok(transform) = positions.get(activator) else {
+            continue;
+        };
+        let center = transform.translation.xy();
+        let sector = Sector::new(shape, transform);
+        let growth = wave_growth(shape, speed, &config);
+        let max_size = max_wave_size(shape, limit, speed, &config);
+
+        for ring in &mut pulse.rings {
+            ring.size += clock.delta().as_secs_f32() * growth;
+            if let Some(max_size) = max_size {
+                ring.size = ring.size.min(max_size);
+            }
+
+            // the objects are sorted by distance or angle, so all hit ones are at the front
+            let hit = ring
+                .inactivated
+                .iter()
+                .take_while(|it| {
+                    positions
+                        .get(**it)
+                        .map(|it| {
+                            size_to_reach(shape, transform, it.translation.xy(), &zones) < ring.size
+                        })
+                        // despawned objects are dropped as well
+                        .unwrap_or(true)
+                })
+                .count();
+
+            let reached: Vec<Entity> = ring
+                .inactivated
+                .drain(..hit)
+                .filter(|it| {
+                    positions
+                        .get(*it)
+                        // objects in the shadow of a wall, outside of the cone or beyond the line
+                        // of a sweep are dropped without a hit
+                        .is_ok_and(|it| {
+                            let position = it.translation.xy();
+                            let reached = match shape {
+                                ActivatorShape::Sweep { .. } => {
+                                    limit.radius.is_none_or(|it| position.distance(center) < it)
+                                }
+                                _ => true,
+                            };
+                            reached
+                                && !is_occluded(center, position, &walls)
+                                && sector.covers(position - center)
+                        })
+                })
+                .chain(reached_echoes(&mut ring.echoes, ring.size))
+                .collect();
+            for other in reached {
+                hit_object(activator, other, &mut hits, &mut commands);
+            }
+
+            if max_size.is_some_and(|max_size| ring.size >= max_size) {
+                // the ring ends at its limit
+                ring.inactivated.clear();
+                ring.echoes.clear();
+            }
+        }
+        pulse
+            .rings
+            .retain(|it| !it.inactivated.is_empty() || !it.echoes.is_empty());
+    }
+}
+
+/// Beats between two rings.
+const INTERVAL: ObjectField = ObjectField {
+    name: "Interval",
+    steps: Some(&[-1.0, -0.25, 0.25, 1.0]),
+    value: |object| {
+        object
+            .get::<Pulse>()
+            .map(|it| format!("{} beats", it.interval))
+    },
+    edit: |object, step| {
+        if let Some(mut pulse) = object.get_mut::<Pulse>() {
+            pulse.interval = (pulse.interval + step).max(0.25);
+        }
+    },
+};
+
+/// Rings of the pulse or endless.
+const COUNT: ObjectField = ObjectField {
+    name: "Rings",
+    steps: Some(&[-1.0, 1.0]),
+    value: |object| match object.get::<Pulse>()?.count {
+        Some(count) => Some(count.to_string()),
+        None => Some("endless".to_string()),
+    },
+    edit: |object, step| {
+        if let Some(mut pulse) = object.get_mut::<Pulse>() {
+            pulse.count = step_count(pulse.count, step);
+        }
+    },
+};
+
+const MAX_PULSE_COUNT: u32 = 32;
+
+/// Change the number of rings of a pulse. The counts wrap around through endless.
+fn step_count(count: Option<u32>, step: f32) -> Option<u32> {
+    match count {
+        Some(1) if step < 0.0 => None,
+        None if step < 0.0 => Some(MAX_PULSE_COUNT),
+        None => Some(1),
+        Some(count) if step < 0.0 => Some(count - 1),
+        Some(count) => Some(count + 1).filter(|it| *it <= MAX_PULSE_COUNT),
+    }
+}
+
+fn spawn_pulse(entity: &mut EntityWorldMut) {
+    spawn_activator(entity);
+    entity.insert(Pulse::default());
+}
+
+/// Mark pulse activators, since they share the icon with the other activators.
+fn draw_pulse_markers(
+    pulses: Query<(&Transform, &ActivatorColor), With<Pulse>>,
+    mut gizmos: Gizmos,
+) {
+    for (transform, color) in &pulses {
+        let position = Isometry2d::from_translation(transform.translation.xy());
+        gizmos.circle_2d(position, 16.0, color).resolution(32);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_step_count() {
+        assert_eq!(Some(3), step_count(Some(2), 1.0));
+        assert_eq!(Some(1), step_count(Some(2), -1.0));
+        assert_eq!(None, step_count(Some(1), -1.0));
+        assert_eq!(None, step_count(Some(MAX_PULSE_COUNT), 1.0));
+        assert_eq!(Some(1), step_count(None, 1.0));
+        assert_eq!(Some(MAX_PULSE_COUNT), step_count(None, -1.0));
+    }
+}
@@ -0,0 +1,397 @@
+//! # Shape
+//!
+//! Not every activator emits a full ring. A cone only covers a sector around the direction of the
+//! activator, objects outside of it are passed without a hit. A sweep is a line which turns once
+//! around the activator like a radar: it hits the objects in the order of their angle, so the angle
+//! becomes the rhythm and the distance the pitch.
+
+use crate::core::game::{
+    ExecutionClock, LevelConfig, activator_icon, sorted_objects, spawn_activator,
+};
+use crate::core::hit::{Hittable, ObjectHitEvent, hit_object};
+use crate::core::model::{
+    ActivatorColor, ActivatorLimit, ActivatorShape, ActivatorSize, ActivatorSpeed, ActivatorState,
+    InactivatedObjects, Wall,
+};
+use crate::core::object::{ObjectAppExt, ObjectField, ObjectType};
+use crate::core::wall::{is_occluded, wall_segments};
+use crate::core::zone::{ZoneArea, travel_distance};
+use bevy::prelude::*;
+use std::f32::consts::TAU;
+
+/// Registers cones and sweeps, their waves grow in the execution of the core plugin.
+pub(super) struct ShapePlugin;
+
+impl Plugin for ShapePlugin {
+    fn build(&self, app: &mut App) {
+        // cones and sweeps are saved and hit as activators
+        app.register_object(
+            ObjectType::<ActivatorShape>::new()
+                .item("Cone", activator_icon, spawn_cone)
+                .item("Sweep", activator_icon, spawn_sweep)
+                .draw(draw_shape_markers)
+                .field(ROTATION)
+                .field(WIDTH)
+                .field(TURN),
+        );
+    }
+}
+
+/// Length of the line of a sweep without a radius limit.
+const SWEEP_LENGTH: f32 = 2000.0;
+
+/// The directions a ring covers: the sector of a cone or the full circle.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Sector {
+    pub direction: Vec2,
+    /// width in radians
+    pub width: f32,
+}
+
+impl Sector {
+    pub const FULL: Sector = Sector {
+        direction: Vec2::X,
+        width: TAU,
+    };
+
+    pub fn new(shape: &ActivatorShape, transform: &Transform) -> Self {
+        match shape {
+            ActivatorShape::Cone { width } => Sector {
+                direction: direction(transform),
+                width: width.to_radians(),
+            },
+            ActivatorShape::Circle | ActivatorShape::Sweep { .. } => Sector::FULL,
+        }
+    }
+
+    /// The offset from the activator points into the sector.
+    pub fn covers(&self, offset: Vec2) -> bool {
+        self.width >= TAU || self.direction.angle_to(offset).abs() <= self.width / 2.0
+    }
+}
+
+/// The direction of a cone or sweep.
+pub fn direction(transform: &Transform) -> Vec2 {
+    (transform.rotation * Vec3::X).xy()
+}
+
+/// The angle a sweep has to turn counterclockwise from its direction to reach the offset.
+pub fn sweep_angle(direction: Vec2, offset: Vec2) -> f32 {
+    direction.angle_to(offset).rem_euclid(TAU)
+}
+
+/// Radians a sweep turns per second.
+pub(super) fn sweep_speed(beats: f32, speed: &ActivatorSpeed, config: &LevelConfig) -> f32 {
+    TAU / (beats * config.beat_length()) * **speed
+}
+
+/// The angle at which a sweep ends, a full turn or less if its lifetime is limited.
+fn max_sweep_angle(limit: &ActivatorLimit, angular_speed: f32, config: &LevelConfig) -> f32 {
+    limit
+        .lifetime
+        .map(|it| (it * config.beat_length() * angular_speed).min(TAU))
+        .unwrap_or(TAU)
+}
+
+/// The size per second the wave of an activator grows with: the radius of a ring or the angle of a
+/// sweep.
+pub(super) fn wave_growth(
+    shape: &ActivatorShape,
+    speed: &ActivatorSpeed,
+    config: &LevelConfig,
+) -> f32 {
+    match shape {
+        ActivatorShape::Sweep { beats } => sweep_speed(*beats, speed, config),
+        _ => config.growth(speed),
+    }
+}
+
+/// The size at which the wave of an activator ends, `None` if a ring is unlimited.
+pub(super) fn max_wave_size(
+    shape: &ActivatorShape,
+    limit: &ActivatorLimit,
+    speed: &ActivatorSpeed,
+    config: &LevelConfig,
+) -> Option<f32> {
+    match shape {
+        ActivatorShape::Sweep { beats } => Some(max_sweep_angle(
+            limit,
+            sweep_speed(*beats, speed, config),
+            config,
+        )),
+        _ => limit.max_size(config.growth(speed) * config.beat_length()),
+    }
+}
+
+/// The size the wave of an activator has when it reaches the position: the travel distance of a
+/// ring or the angle of a sweep.
+pub(super) fn size_to_reach(
+    shape: &ActivatorShape,
+    activator: &Transform,
+    position: Vec2,
+    zones: &[ZoneArea],
+) -> f32 {
+    let center = activator.translation.xy();
+    match shape {
+        ActivatorShape::Sweep { .. } => sweep_angle(direction(activator), position - center),
+        _ => travel_distance(center, position, zones),
+    }
+}
+
+/// All other objects as the unplayed objects list of a new wave, sorted by the size the wave has
+/// when it reaches them.
+pub(super) fn sorted_by_shape(
+    activator: Entity,
+    shape: &ActivatorShape,
+    objects: &Query<Entity, With<Hittable>>,
+    positions: &Query<&Transform>,
+    zones: &[ZoneArea],
+) -> Vec<Entity> {
+    match shape {
+        ActivatorShape::Sweep { .. } => sorted_by_angle(activator, objects, positions),
+        _ => sorted_objects(activator, objects, positions, zones),
+    }
+}
+
+/// All other objects as the unplayed objects list for a sweep. This list is sorted by the angle the
+/// sweep has to turn to reach them.
+fn sorted_by_angle(
+    activator: Entity,
+    objects: &Query<Entity, With<Hittable>>,
+    positions: &Query<&Transform>,
+) -> Vec<Entity> {
+    let Ok(transform) = positions.get(activator) else {
+        return vec![];
+    };
+    let center = transform.translation.xy();
+    let direction = direction(transform);
+
+    let mut result: Vec<(Entity, f32)> = objects
+        .iter()
+        .filter(|it| it != &activator)
+        .filter_map(|it| {
+            let position = positions.get(it).ok()?.translation.xy();
+            Some((it, sweep_angle(direction, position - center)))
+        })
+        .collect();
+    result.sort_by(|(_, a1), (_, a2)| a1.total_cmp(a2));
+    result.into_iter().map(|(it, _)| it).collect()
+}
+
+/// Turn the line of every sweep and hit the objects it passes.
+pub(super) fn execute_sweeps(
+    mut sweeps: Query<(
+        Entity,
+        &ActivatorShape,
+        &mut ActivatorSize,
+        &ActivatorState,
+        &ActivatorLimit,
+        &ActivatorSpeed,
+        &mut InactivatedObjects,
+    )>,
+    positions: Query<&Transform>,
+    walls: Query<(&Wall, &Transform)>,
+    config: Res<LevelConfig>,
+    clock: Res<ExecutionClock>,
+    mut hits: EventWriter<ObjectHitEvent>,
+    mut commands: Commands,
+) {
+    let walls = wall_segments(&walls);
+    for (activator, shape, mut size, state, limit, speed, mut inactive) in &mut sweeps {
+        let ActivatorShape::Sweep { beats } = shape else {
+            continue;
+        };
+        if !state.is_active() {
+            continue;
+        }
+        let Ok(transform) = positions.get(activator) else {
+            continue;
+        };
+        let center = transform.translation.xy();
+        let direction = direction(transform);
+
+        let angular_speed = sweep_speed(*beats, speed, &config);
+        let max_angle = max_sweep_angle(limit, angular_speed, &config);
+        size.increment(clock.delta().as_secs_f32() * angular_speed);
+        size.limit(max_angle);
+
+        // the objects are sorted by angle, so all passed ones are at the front
+        let passed = inactive
+            .iter()
+            .take_while(|it| {
+                positions
+                    .get(**it)
+                    .map(|it| sweep_angle(direction, it.translation.xy() - center) < **size)
+                    // despawned objects are dropped as well
+                    .unwrap_or(true)
+            })
+            .count();
+        let hit: Vec<Entity> = inactive
+            .0
+            .drain(..passed)
+            .filter(|it| {
+                positions.get(*it).is_ok_and(|it| {
+                    let position = it.translation.xy();
+                    // the line is too short or blocked by a wall
+                    limit.radius.is_none_or(|it| position.distance(center) < it)
+                        && !is_occluded(center, position, &walls)
+                })
+            })
+            .collect();
+        for other in hit {
+            hit_object(activator, other, &mut hits, &mut commands);
+        }
+
+        if **size >= max_angle {
+            // the sweep ends at its limit and never passes the remaining objects
+            inactive.0.clear();
+        }
+    }
+}
+
+/// The direction of a cone or sweep.
+const ROTATION: ObjectField = ObjectField {
+    name: "Rotation",
+    steps: Some(ROTATION_STEPS),
+    value: |object| match object.get::<ActivatorShape>()? {
+        ActivatorShape::Circle => None,
+        ActivatorShape::Cone { .. } | ActivatorShape::Sweep { .. } => rotation(object),
+    },
+    edit: rotate,
+};
+
+/// Degrees a cone covers.
+const WIDTH: ObjectField = ObjectField {
+    name: "Width",
+    steps: Some(&[-15.0, -5.0, 5.0, 15.0]),
+    value: |object| match object.get::<ActivatorShape>()? {
+        ActivatorShape::Cone { width } => Some(format!("{width} deg")),
+        _ => None,
+    },
+    edit: |object, step| {
+        if let Some(mut shape) = object.get_mut::<ActivatorShape>()
+            && let ActivatorShape::Cone { width } = shape.as_mut()
+        {
+            *width = (*width + step).clamp(5.0, 360.0);
+        }
+    },
+};
+
+/// Beats a sweep needs for one turn.
+const TURN: ObjectField = ObjectField {
+    name: "Turn",
+    steps: Some(&[-1.0, -0.25, 0.25, 1.0]),
+    value: |object| match object.get::<ActivatorShape>()? {
+        ActivatorShape::Sweep { beats } => Some(format!("{beats} beats")),
+        _ => None,
+    },
+    edit: |object, step| {
+        if let Some(mut shape) = object.get_mut::<ActivatorShape>()
+            && let ActivatorShape::Sweep { beats } = shape.as_mut()
+        {
+            *beats = (*beats + step).max(0.25);
+        }
+    },
+};
+
+/// Steps in degrees of a rotation in the inspector.
+pub(super) const ROTATION_STEPS: &[f32] = &[-15.0, -1.0, 1.0, 15.0];
+
+/// The rotation of the object in degrees, which is its [`direction`].
+pub(super) fn rotation(object: &EntityRef) -> Option<String> {
+    let transform = object.get::<Transform>()?;
+    let angle = transform.rotation.to_euler(EulerRot::ZYX).0;
+    Some(format!("{:.1} deg", angle.to_degrees().rem_euclid(360.0)))
+}
+
+/// Turn the object counterclockwise by the degrees.
+pub(super) fn rotate(object: &mut EntityWorldMut, degrees: f32) {
+    if let Some(mut transform) = object.get_mut::<Transform>() {
+        transform.rotate_z(degrees.to_radians());
+    }
+}
+
+fn spawn_cone(entity: &mut EntityWorldMut) {
+    spawn_activator(entity);
+    entity.insert(ActivatorShape::Cone { width: 90.0 });
+}
+
+fn spawn_sweep(entity: &mut EntityWorldMut) {
+    spawn_activator(entity);
+    entity.insert(ActivatorShape::Sweep { beats: 4.0 });
+}
+
+/// Show the direction of cones and sweeps, since they share the icon with the other activators.
+/// The line of an active sweep is drawn at its current angle.
+fn draw_shape_markers(
+    activators: Query<(
+        &ActivatorShape,
+        &ActivatorState,
+        &ActivatorSize,
+        &ActivatorLimit,
+        &ActivatorColor,
+        &Transform,
+    )>,
+    mut gizmos: Gizmos,
+) {
+    for (shape, state, size, limit, color, transform) in &activators {
+        let center = transform.translation.xy();
+        let direction = direction(transform);
+        match shape {
+            ActivatorShape::Circle => {}
+            ActivatorShape::Cone { width } => {
+                let half = width.to_radians() / 2.0;
+                for side in [-half, half] {
+                    let edge = Vec2::from_angle(side).rotate(direction);
+                    gizmos.line_2d(center + edge * 16.0, center + edge * 28.0, color);
+                }
+            }
+            ActivatorShape::Sweep { .. } => {
+                gizmos.line_2d(center + direction * 16.0, center + direction * 28.0, color);
+                if state.is_active() {
+                    let line = Vec2::from_angle(**size).rotate(direction);
+                    let length = limit.radius.unwrap_or(SWEEP_LENGTH);
+                    gizmos.line_2d(center, center + line * length, color);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::f32::consts::{FRAC_PI_2, PI};
+
+    #[test]
+    fn test_cone_covers_its_sector() {
+        let cone = Sector {
+            direction: Vec2::Y,
+            width: FRAC_PI_2,
+        };
+
+        assert!(cone.covers(Vec2::new(0.0, 10.0)));
+        assert!(cone.covers(Vec2::new(9.0, 10.0)));
+        assert!(!cone.covers(Vec2::new(11.0, 10.0)));
+        assert!(!cone.covers(Vec2::new(0.0, -10.0)));
+        assert!(Sector::FULL.covers(Vec2::new(0.0, -10.0)));
+    }
+
+    #[test]
+    fn test_sweep_turns_counterclockwise() {
+        assert_eq!(0.0, sweep_angle(Vec2::X, Vec2::new(10.0, 0.0)));
+        assert!((FRAC_PI_2 - sweep_angle(Vec2::X, Vec2::new(0.0, 10.0))).abs() < 1e-5);
+        assert!((PI + FRAC_PI_2 - sweep_angle(Vec2::X, Vec2::new(0.0, -10.0))).abs() < 1e-5);
+    }
+
+    #[test]
+    fn test_size_to_reach_depends_on_the_shape() {
+        let activator = Transform::from_xyz(100.0, 0.0, 0.0);
+        let position = Vec2::new(100.0, 50.0);
+
+        let ring = size_to_reach(&ActivatorShape::Circle, &activator, position, &[]);
+        assert_eq!(50.0, ring);
+        let sweep = ActivatorShape::Sweep { beats: 4.0 };
+        assert!((FRAC_PI_2 - size_to_reach(&sweep, &activator, position, &[])).abs() < 1e-5);
+    }
+}
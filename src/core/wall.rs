@@ -0,0 +1,273 @@
+//! # Wall
+//!
+//! Walls are segments which rings cannot pass. An object in the shadow of a wall is not hit by the
+//! ring, even though the ring grew past it. A reflective wall mirrors the activator, the ring of
+//! the mirrored activator reaches objects in front of the wall later as an echo. Only single
+//! reflections are considered.
+
+use crate::core::hit::Hittable;
+use crate::core::model::{ActivatorShape, Echo, Wall};
+use crate::core::object::{ObjectAppExt, ObjectField, ObjectType};
+use crate::core::shape::{ROTATION_STEPS, Sector, rotate, rotation};
+use crate::core::zone::{ZoneArea, travel_distance};
+use bevy::prelude::*;
+
+pub(super) struct WallPlugin;
+
+impl Plugin for WallPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_object(
+            ObjectType::<Wall>::new()
+                .item("Wall", |assets| assets.wall_icon.clone(), spawn_wall)
+                .item("Mirror", |assets| assets.mirror_icon.clone(), spawn_mirror)
+                .draw(draw_walls)
+                .field(ROTATION)
+                .save_component(),
+        );
+    }
+}
+
+/// Length of a wall with a scale of one in logical pixels.
+pub(super) const WALL_LENGTH: f32 = 100.0;
+
+/// The segment of a wall in world coordinates.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct WallSegment {
+    pub start: Vec2,
+    pub end: Vec2,
+    pub kind: Wall,
+}
+
+impl WallSegment {
+    pub fn new(kind: Wall, transform: &Transform) -> Self {
+        let center = transform.translation.xy();
+        let half = (transform.rotation * Vec3::X).xy() * WALL_LENGTH * transform.scale.x / 2.0;
+        WallSegment {
+            start: center - half,
+            end: center + half,
+            kind,
+        }
+    }
+
+    /// The point at which the path from `from` to `to` crosses the wall. Touching the wall is not
+    /// crossing it.
+    fn crossing(&self, from: Vec2, to: Vec2) -> Option<Vec2> {
+        let wall = self.end - self.start;
+        let path = to - from;
+        let side_from = wall.perp_dot(from - self.start);
+        let side_to = wall.perp_dot(to - self.start);
+        let side_start = path.perp_dot(self.start - from);
+        let side_end = path.perp_dot(self.end - from);
+        if side_from * side_to >= 0.0 || side_start * side_end >= 0.0 {
+            return None;
+        }
+        Some(from + path * (side_from / (side_from - side_to)))
+    }
+
+    /// The point mirrored at the line of the wall.
+    fn mirror(&self, point: Vec2) -> Vec2 {
+        let direction = (self.end - self.start).normalize_or_zero();
+        let projection = self.start + direction * (point - self.start).dot(direction);
+        projection * 2.0 - point
+    }
+}
+
+/// Any wall stands between the two points.
+pub fn is_occluded(from: Vec2, to: Vec2, walls: &[WallSegment]) -> bool {
+    walls.iter().any(|wall| wall.crossing(from, to).is_some())
+}
+
+/// The travel distances of the ring of an activator at `center` to reach `target` over one of the
+/// reflective walls. The ring has to leave the activator within its sector.
+pub fn echo_distances(
+    center: Vec2,
+    target: Vec2,
+    sector: &Sector,
+    walls: &[WallSegment],
+    zones: &[ZoneArea],
+) -> Vec<f32> {
+    let mut distances = vec![];
+    for (index, wall) in walls.iter().enumerate() {
+        if wall.kind != Wall::Reflective {
+            continue;
+        }
+        let mirrored = wall.mirror(center);
+        let Some(reflection) = wall
+            .crossing(mirrored, target)
+            .filter(|it| sector.covers(*it - center))
+        else {
+            continue;
+        };
+        // the other walls can still block both parts of the detour
+        let others: Vec<WallSegment> = walls
+            .iter()
+            .enumerate()
+            .filter(|(other, _)| *other != index)
+            .map(|(_, it)| *it)
+            .collect();
+        if is_occluded(center, reflection, &others) || is_occluded(reflection, target, &others) {
+            continue;
+        }
+        distances.push(
+            travel_distance(center, reflection, zones) + travel_distance(reflection, target, zones),
+        );
+    }
+    distances
+}
+
+pub(super) fn wall_segments(walls: &Query<(&Wall, &Transform)>) -> Vec<WallSegment> {
+    walls
+        .iter()
+        .map(|(wall, transform)| WallSegment::new(*wall, transform))
+        .collect()
+}
+
+/// All echoes of the ring of an activator, sorted by distance. A sweep has no echoes.
+pub(super) fn sorted_echoes(
+    activator: Entity,
+    shape: &ActivatorShape,
+    objects: &Query<Entity, With<Hittable>>,
+    positions: &Query<&Transform>,
+    walls: &[WallSegment],
+    zones: &[ZoneArea],
+) -> Vec<Echo> {
+    let Ok(transform) = positions.get(activator) else {
+        return vec![];
+    };
+    if let ActivatorShape::Sweep { .. } = shape {
+        return vec![];
+    }
+    let center = transform.translation.xy();
+    let sector = Sector::new(shape, transform);
+
+    let mut echoes: Vec<Echo> = objects
+        .iter()
+        .filter(|it| it != &activator)
+        .filter_map(|target| Some((target, positions.get(target).ok()?.translation.xy())))
+        .flat_map(|(target, position)| {
+            echo_distances(center, position, &sector, walls, zones)
+                .into_iter()
+                .map(move |distance| Echo { target, distance })
+        })
+        .collect();
+    echoes.sort_by(|e1, e2| e1.distance.total_cmp(&e2.distance));
+    echoes
+}
+
+/// Remove the echoes a ring of the given size has reached and return their objects.
+pub(super) fn reached_echoes(echoes: &mut Vec<Echo>, size: f32) -> Vec<Entity> {
+    let reached = echoes.partition_point(|it| it.distance < size);
+    echoes.drain(..reached).map(|it| it.target).collect()
+}
+
+/// The direction of a wall.
+const ROTATION: ObjectField = ObjectField {
+    name: "Rotation",
+    steps: Some(ROTATION_STEPS),
+    value: rotation,
+    edit: rotate,
+};
+
+/// Walls are drawn as lines, their length is the scale.
+fn spawn_wall(entity: &mut EntityWorldMut) {
+    entity.insert(Wall::Blocking);
+}
+
+fn spawn_mirror(entity: &mut EntityWorldMut) {
+    entity.insert(Wall::Reflective);
+}
+
+fn draw_walls(walls: Query<(&Wall, &Transform)>, mut gizmos: Gizmos) {
+    for (wall, transform) in &walls {
+        let segment = WallSegment::new(*wall, transform);
+        match wall {
+            Wall::Blocking => {
+                gizmos.line_2d(segment.start, segment.end, Color::WHITE);
+            }
+            Wall::Reflective => {
+                // a reflective wall is doubled like a mirror
+                let offset = (segment.end - segment.start).perp().normalize_or_zero() * 2.0;
+                let color = Color::srgb(0.6, 0.8, 1.0);
+                gizmos.line_2d(segment.start + offset, segment.end + offset, color);
+                gizmos.line_2d(segment.start - offset, segment.end - offset, color);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn wall(kind: Wall, start: Vec2, end: Vec2) -> WallSegment {
+        WallSegment { start, end, kind }
+    }
+
+    #[test]
+    fn test_wall_from_transform() {
+        let transform = Transform::from_xyz(10.0, 20.0, 0.0)
+            .with_rotation(Quat::from_rotation_z(std::f32::consts::FRAC_PI_2))
+            .with_scale(Vec3::splat(0.5));
+        let segment = WallSegment::new(Wall::Blocking, &transform);
+
+        assert!(segment.start.distance(Vec2::new(10.0, -5.0)) < 1e-4);
+        assert!(segment.end.distance(Vec2::new(10.0, 45.0)) < 1e-4);
+    }
+
+    #[test]
+    fn test_occlusion() {
+        let walls = [wall(
+            Wall::Blocking,
+            Vec2::new(50.0, -10.0),
+            Vec2::new(50.0, 10.0),
+        )];
+
+        assert!(is_occluded(Vec2::ZERO, Vec2::new(100.0, 0.0), &walls));
+        // passes the wall below its end
+        assert!(!is_occluded(Vec2::ZERO, Vec2::new(100.0, 100.0), &walls));
+        // stops in front of the wall
+        assert!(!is_occluded(Vec2::ZERO, Vec2::new(40.0, 0.0), &walls));
+    }
+
+    #[test]
+    fn test_echo_over_reflective_wall() {
+        let mirror = wall(
+            Wall::Reflective,
+            Vec2::new(-100.0, 100.0),
+            Vec2::new(100.0, 100.0),
+        );
+        let target = Vec2::new(0.0, 50.0);
+
+        // the mirrored center is at (0, 200)
+        assert_eq!(
+            vec![150.0],
+            echo_distances(Vec2::ZERO, target, &Sector::FULL, &[mirror], &[])
+        );
+        // a blocking wall does not reflect
+        let blocking = WallSegment {
+            kind: Wall::Blocking,
+            ..mirror
+        };
+        assert!(echo_distances(Vec2::ZERO, target, &Sector::FULL, &[blocking], &[]).is_empty());
+        // behind the wall there is no echo
+        assert!(
+            echo_distances(
+                Vec2::ZERO,
+                Vec2::new(0.0, 150.0),
+                &Sector::FULL,
+                &[mirror],
+                &[]
+            )
+            .is_empty()
+        );
+        // another wall blocks the way back
+        let shield = wall(
+            Wall::Blocking,
+            Vec2::new(-10.0, 75.0),
+            Vec2::new(10.0, 75.0),
+        );
+        assert!(
+            echo_distances(Vec2::ZERO, target, &Sector::FULL, &[mirror, shield], &[]).is_empty()
+        );
+    }
+}
@@ -0,0 +1,282 @@
+//! # Zone
+//!
+//! Rings travel slower or faster inside a zone. The time a ring needs to reach an object depends on
+//! the zones along the straight path from the activator to the object. Instead of the distance, the
+//! ring is compared with the travel distance: the distance the ring would cover outside of any zone
+//! in the same time. The drawn ring follows its wavefront, so it reaches an object
+//! inside a zone just when the object plays.
+
+use crate::core::game::SPEEDS;
+use crate::core::model::{Zone, ZoneShape};
+use crate::core::object::{ObjectAppExt, ObjectField, ObjectType, cycle};
+use bevy::prelude::*;
+
+pub(super) struct ZonePlugin;
+
+impl Plugin for ZonePlugin {
+    fn build(&self, app: &mut App) {
+        app.register_object(
+            ObjectType::<Zone>::new()
+                .item("Zone", |assets| assets.zone_icon.clone(), spawn_zone)
+                .draw(draw_zones)
+                .field(SPEED)
+                .field(SHAPE)
+                .save_component(),
+        );
+    }
+}
+
+/// Size of a zone with a scale of one in logical pixels.
+pub(super) const ZONE_SIZE: f32 = 100.0;
+
+/// The area of a zone in world coordinates.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct ZoneArea {
+    pub shape: ZoneShape,
+    pub center: Vec2,
+    /// half of the width and height, or the radius of a circle
+    pub half_size: Vec2,
+    pub speed: f32,
+}
+
+impl ZoneArea {
+    pub fn new(zone: &Zone, transform: &Transform) -> Self {
+        ZoneArea {
+            shape: zone.shape,
+            center: transform.translation.xy(),
+            half_size: transform.scale.xy() * ZONE_SIZE / 2.0,
+            speed: zone.speed,
+        }
+    }
+
+    fn contains(&self, point: Vec2) -> bool {
+        let offset = point - self.center;
+        match self.shape {
+            ZoneShape::Rectangle => offset.abs().cmplt(self.half_size).all(),
+            ZoneShape::Circle => offset.length() < self.half_size.x,
+        }
+    }
+
+    /// The fractions of the path from `from` to `to` at which it may enter or leave the zone.
+    fn crossings(&self, from: Vec2, to: Vec2) -> Vec<f32> {
+        let path = to - from;
+        let offset = from - self.center;
+        let candidates = match self.shape {
+            ZoneShape::Rectangle => {
+                let mut candidates = vec![];
+                for axis in 0..2 {
+                    if path[axis] == 0.0 {
+                        continue;
+                    }
+                    for edge in [-self.half_size[axis], self.half_size[axis]] {
+                        candidates.push((edge - offset[axis]) / path[axis]);
+                    }
+                }
+                candidates
+            }
+            ZoneShape::Circle => {
+                let a = path.length_squared();
+                let b = 2.0 * path.dot(offset);
+                let c = offset.length_squared() - self.half_size.x * self.half_size.x;
+                let discriminant = b * b - 4.0 * a * c;
+                if a == 0.0 || discriminant <= 0.0 {
+                    vec![]
+                } else {
+                    let root = discriminant.sqrt();
+                    vec![(-b - root) / (2.0 * a), (-b + root) / (2.0 * a)]
+                }
+            }
+        };
+        candidates
+            .into_iter()
+            .filter(|it| 0.0 < *it && *it < 1.0)
+            .collect()
+    }
+}
+
+/// The distance a ring covers outside of any zone in the time it needs from `from` to `to`.
+/// Overlapping zones multiply their speeds.
+pub fn travel_distance(from: Vec2, to: Vec2, zones: &[ZoneArea]) -> f32 {
+    if zones.is_empty() {
+        return from.distance(to);
+    }
+
+    let mut fractions = vec![0.0, 1.0];
+    fractions.extend(zones.iter().flat_map(|it| it.crossings(from, to)));
+    fractions.sort_by(f32::total_cmp);
+
+    let length = from.distance(to);
+    fractions
+        .windows(2)
+        .map(|pair| {
+            let middle = from.lerp(to, (pair[0] + pair[1]) / 2.0);
+            (pair[1] - pair[0]) * length / speed_at(middle, zones)
+        })
+        .sum()
+}
+
+/// How far a ring of the given size reached from `from` in the direction, which is where its
+/// travel distance equals the size.
+pub fn wavefront_distance(from: Vec2, direction: Vec2, size: f32, zones: &[ZoneArea]) -> f32 {
+    if zones.is_empty() {
+        return size;
+    }
+
+    // the ring cannot get further than through all fast zones at once
+    let reach = size * zones.iter().map(|it| it.speed.max(1.0)).product::<f32>();
+    let to = from + direction.normalize_or_zero() * reach;
+    let mut fractions = vec![0.0, 1.0];
+    fractions.extend(zones.iter().flat_map(|it| it.crossings(from, to)));
+    fractions.sort_by(f32::total_cmp);
+
+    let mut travelled = 0.0;
+    for pair in fractions.windows(2) {
+        let speed = speed_at(from.lerp(to, (pair[0] + pair[1]) / 2.0), zones);
+        let length = (pair[1] - pair[0]) * reach;
+        if travelled + length / speed >= size {
+            return pair[0] * reach + (size - travelled) * speed;
+        }
+        travelled += length / speed;
+    }
+    reach
+}
+
+/// Multiplier of the speed at the point, overlapping zones multiply their speeds.
+fn speed_at(point: Vec2, zones: &[ZoneArea]) -> f32 {
+    zones
+        .iter()
+        .filter(|it| it.contains(point))
+        .map(|it| it.speed)
+        .product()
+}
+
+pub(super) fn zone_areas(zones: &Query<(&Zone, &Transform)>) -> Vec<ZoneArea> {
+    zones
+        .iter()
+        .map(|(zone, transform)| ZoneArea::new(zone, transform))
+        .collect()
+}
+
+/// Multiplier of the speed of the rings inside the zone.
+const SPEED: ObjectField = ObjectField {
+    name: "Speed",
+    steps: None,
+    value: |object| object.get::<Zone>().map(|it| format!("x{}", it.speed)),
+    edit: |object, step| {
+        if let Some(mut zone) = object.get_mut::<Zone>()
+            && let Some(next) = cycle(&SPEEDS, Some(zone.speed), step)
+        {
+            zone.speed = next;
+        }
+    },
+};
+
+const SHAPE: ObjectField = ObjectField {
+    name: "Shape",
+    steps: None,
+    value: |object| object.get::<Zone>().map(|it| format!("{:?}", it.shape)),
+    edit: |object, step| {
+        if let Some(mut zone) = object.get_mut::<Zone>() {
+            let shapes = [ZoneShape::Rectangle, ZoneShape::Circle];
+            if let Some(next) = cycle(&shapes, Some(zone.shape), step) {
+                zone.shape = next;
+            }
+        }
+    },
+};
+
+/// Zones are drawn as outlines, their size is the scale.
+fn spawn_zone(entity: &mut EntityWorldMut) {
+    entity.insert(Zone::default());
+}
+
+/// Slow zones are tinted blue and fast zones red.
+fn draw_zones(zones: Query<(&Zone, &Transform)>, mut gizmos: Gizmos) {
+    for (zone, transform) in &zones {
+        let area = ZoneArea::new(zone, transform);
+        let color = if zone.speed < 1.0 {
+            Color::srgba(0.4, 0.6, 1.0, 0.6)
+        } else {
+            Color::srgba(1.0, 0.5, 0.4, 0.6)
+        };
+        let position = Isometry2d::from_translation(area.center);
+        match area.shape {
+            ZoneShape::Rectangle => {
+                gizmos.rect_2d(position, area.half_size * 2.0, color);
+            }
+            ZoneShape::Circle => {
+                gizmos
+                    .circle_2d(position, area.half_size.x, color)
+                    .resolution(64);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn zone(shape: ZoneShape, center: Vec2, speed: f32) -> ZoneArea {
+        ZoneArea {
+            shape,
+            center,
+            half_size: Vec2::splat(50.0),
+            speed,
+        }
+    }
+
+    #[test]
+    fn test_travel_distance_through_zones() {
+        let from = Vec2::ZERO;
+        let to = Vec2::new(300.0, 0.0);
+        let slow = zone(ZoneShape::Rectangle, Vec2::new(100.0, 0.0), 0.5);
+        let fast = zone(ZoneShape::Circle, Vec2::new(200.0, 0.0), 2.0);
+
+        assert_eq!(300.0, travel_distance(from, to, &[]));
+        // 100 px inside the slow zone take as long as 200 px outside
+        assert_eq!(400.0, travel_distance(from, to, &[slow]));
+        assert_eq!(250.0, travel_distance(from, to, &[fast]));
+        // the zones touch each other
+        assert!((350.0 - travel_distance(from, to, &[slow, fast])).abs() < 1e-3);
+        // the path passes the zone
+        assert_eq!(
+            300.0,
+            travel_distance(Vec2::new(0.0, 100.0), Vec2::new(300.0, 100.0), &[slow])
+        );
+    }
+
+    #[test]
+    fn test_wavefront_distance_through_zones() {
+        let slow = zone(ZoneShape::Rectangle, Vec2::new(100.0, 0.0), 0.5);
+        let fast = zone(ZoneShape::Circle, Vec2::new(200.0, 0.0), 2.0);
+        let assert_distance =
+            |expected: f32, from: Vec2, direction: Vec2, size: f32, zones: &[ZoneArea]| {
+                let distance = wavefront_distance(from, direction, size, zones);
+                assert!(
+                    (expected - distance).abs() < 1e-3,
+                    "{expected} != {distance}"
+                );
+            };
+
+        assert_distance(120.0, Vec2::ZERO, Vec2::X, 120.0, &[]);
+        // the ring enters the slow zone at 50 px and covers only half the distance inside
+        assert_distance(75.0, Vec2::ZERO, Vec2::X, 100.0, &[slow]);
+        assert_distance(200.0, Vec2::ZERO, Vec2::X, 300.0, &[slow]);
+        // the ring passes the fast zone before the slow one
+        assert_distance(225.0, Vec2::new(300.0, 0.0), -Vec2::X, 250.0, &[slow, fast]);
+        // other directions do not pass the zones
+        assert_distance(100.0, Vec2::ZERO, Vec2::Y, 100.0, &[slow]);
+    }
+
+    #[test]
+    fn test_overlapping_zones_multiply() {
+        let slow = zone(ZoneShape::Rectangle, Vec2::ZERO, 0.5);
+
+        // starts inside both zones
+        assert_eq!(
+            160.0,
+            travel_distance(Vec2::ZERO, Vec2::new(40.0, 0.0), &[slow, slow])
+        );
+    }
+}
@@ -0,0 +1,421 @@
+//! # Actions
+//!
+//! The game does not read keys or mouse buttons directly. Every input is an [`Action`] which is
+//! bound to one or more [`Binding`]s in the [`ActionMap`]. The bindings can be changed by the user
+//! and are persisted to a config file.
+
+use crate::input::bindings::BindingsScreen;
+use bevy::input::InputSystem;
+use bevy::platform::collections::HashSet;
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+pub(super) struct ActionPlugin;
+
+impl Plugin for ActionPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(ActionMap::load())
+            .init_resource::<ActionState>()
+            .add_systems(PreUpdate, update_action_state.after(InputSystem))
+            .add_systems(
+                Update,
+                save_bindings
+                    .run_if(resource_changed::<ActionMap>.and(not(resource_added::<ActionMap>))),
+            );
+    }
+}
+
+/// Everything the player can do with a key or a button.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+pub enum Action {
+    /// Switch between build and execution mode
+    ToggleExecution,
+    /// Select the next item to place
+    SwitchItem,
+    /// Remove all placed objects
+    Clear,
+    /// Close the game (native only)
+    Quit,
+    /// Place the selected item at the cursor
+    Place,
+    /// Delete the object at the cursor
+    Delete,
+    /// Open or close the bindings screen
+    ShowBindings,
+    /// Select the item in the given toolbar slot
+    SelectSlot(u8),
+    /// Show the properties of the object at the cursor
+    Inspect,
+    /// Switch between no loop, loop and loop synced to the background
+    SwitchLoopMode,
+    /// Pause or resume the execution
+    TogglePause,
+    /// Advance the paused execution by a short time
+    Step,
+    /// Increase the playback rate of the execution
+    Faster,
+    /// Decrease the playback rate of the execution
+    Slower,
+    /// Write the placed objects into the level file (native only)
+    SaveLevel,
+    /// Replace the placed objects with the ones of the level file (native only)
+    LoadLevel,
+}
+
+impl Action {
+    pub fn enumerate() -> Vec<Action> {
+        let mut actions = vec![
+            Action::ToggleExecution,
+            Action::SwitchItem,
+            Action::Clear,
+            Action::Quit,
+            Action::Place,
+            Action::Delete,
+            Action::ShowBindings,
+            Action::Inspect,
+            Action::SwitchLoopMode,
+            Action::TogglePause,
+            Action::Step,
+            Action::Faster,
+            Action::Slower,
+            Action::SaveLevel,
+            Action::LoadLevel,
+        ];
+        let slots = SLOT_HOTKEYS.len() as u8;
+        actions.extend((0..slots).map(Action::SelectSlot));
+        actions
+    }
+
+    pub fn name(&self) -> String {
+        match self {
+            Action::ToggleExecution => "Play / Stop".to_string(),
+            Action::SwitchItem => "Switch Item".to_string(),
+            Action::Clear => "Clear".to_string(),
+            Action::Quit => "Quit".to_string(),
+            Action::Place => "Place".to_string(),
+            Action::Delete => "Delete".to_string(),
+            Action::ShowBindings => "Bindings".to_string(),
+            Action::SelectSlot(slot) => format!("Select Item {}", slot + 1),
+            Action::Inspect => "Inspect".to_string(),
+            Action::SwitchLoopMode => "Loop Mode".to_string(),
+            Action::TogglePause => "Pause".to_string(),
+            Action::Step => "Step".to_string(),
+            Action::Faster => "Faster".to_string(),
+            Action::Slower => "Slower".to_string(),
+            Action::SaveLevel => "Save Level".to_string(),
+            Action::LoadLevel => "Load Level".to_string(),
+        }
+    }
+
+    /// The action which selects the toolbar slot, `None` for the slots after the ones with a
+    /// hotkey. These are only selected by clicking them or switching items.
+    pub fn select_slot(index: usize) -> Option<Action> {
+        (index < SLOT_HOTKEYS.len()).then_some(Action::SelectSlot(index as u8))
+    }
+}
+
+/// A physical input an [`Action`] can be bound to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Binding {
+    Key(KeyCode),
+    Mouse(MouseButton),
+}
+
+impl Binding {
+    pub fn name(&self) -> String {
+        match self {
+            Binding::Key(key) => format!("{key:?}"),
+            Binding::Mouse(button) => format!("Mouse {button:?}"),
+        }
+    }
+
+    /// A name short enough for a hotkey hint, e.g. `1` instead of `Digit1`.
+    pub fn short_name(&self) -> String {
+        match self {
+            Binding::Key(key) => {
+                let name = format!("{key:?}");
+                ["Digit", "Key", "Numpad"]
+                    .iter()
+                    .find_map(|prefix| name.strip_prefix(prefix))
+                    .map(str::to_string)
+                    .unwrap_or(name)
+            }
+            Binding::Mouse(_) => self.name(),
+        }
+    }
+}
+
+/// Default hotkeys for the toolbar slots in their order: the digits, then the row below them.
+const SLOT_HOTKEYS: [KeyCode; 14] = [
+    KeyCode::Digit1,
+    KeyCode::Digit2,
+    KeyCode::Digit3,
+    KeyCode::Digit4,
+    KeyCode::Digit5,
+    KeyCode::Digit6,
+    KeyCode::Digit7,
+    KeyCode::Digit8,
+    KeyCode::Digit9,
+    KeyCode::Digit0,
+    KeyCode::KeyQ,
+    KeyCode::KeyW,
+    KeyCode::KeyE,
+    KeyCode::KeyR,
+];
+
+/// All bindings per action.
+#[derive(Resource, Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ActionMap(BTreeMap<Action, Vec<Binding>>);
+
+impl Default for ActionMap {
+    fn default() -> Self {
+        // Ctrl is used by browser shortcuts, so the web build switches items with Tab.
+        #[cfg(target_family = "wasm")]
+        let switch_item = vec![Binding::Key(KeyCode::Tab)];
+        #[cfg(not(target_family = "wasm"))]
+        let switch_item = vec![
+            Binding::Key(KeyCode::ControlLeft),
+            Binding::Key(KeyCode::ControlRight),
+        ];
+
+        let mut map = BTreeMap::from([
+            (Action::ToggleExecution, vec![Binding::Key(KeyCode::Space)]),
+            (Action::SwitchItem, switch_item),
+            (Action::Clear, vec![Binding::Key(KeyCode::Backspace)]),
+            (Action::Quit, vec![Binding::Key(KeyCode::Escape)]),
+            (Action::Place, vec![Binding::Mouse(MouseButton::Left)]),
+            (Action::Delete, vec![Binding::Mouse(MouseButton::Right)]),
+            (Action::ShowBindings, vec![Binding::Key(KeyCode::F1)]),
+            (
+                Action::Inspect,
+                vec![
+                    Binding::Key(KeyCode::KeyI),
+                    Binding::Mouse(MouseButton::Middle),
+                ],
+            ),
+            (Action::SwitchLoopMode, vec![Binding::Key(KeyCode::KeyL)]),
+            (Action::TogglePause, vec![Binding::Key(KeyCode::KeyP)]),
+            (Action::Step, vec![Binding::Key(KeyCode::Period)]),
+            (Action::Faster, vec![Binding::Key(KeyCode::BracketRight)]),
+            (Action::Slower, vec![Binding::Key(KeyCode::BracketLeft)]),
+            (Action::SaveLevel, vec![Binding::Key(KeyCode::F5)]),
+            (Action::LoadLevel, vec![Binding::Key(KeyCode::F9)]),
+        ]);
+        for (slot, key) in SLOT_HOTKEYS.iter().enumerate() {
+            map.insert(Action::SelectSlot(slot as u8), vec![Binding::Key(*key)]);
+        }
+
+        ActionMap(map)
+    }
+}
+
+impl ActionMap {
+    pub fn get(&self, action: Action) -> &[Binding] {
+        self.0.get(&action).map(Vec::as_slice).unwrap_or_default()
+    }
+
+    /// Replace all bindings of the action with the given one. The binding is removed from every
+    /// other action, so one input never triggers two actions. It is refused if it is the last
+    /// binding of another action, since that action could not be triggered anymore, which is
+    /// returned instead.
+    pub fn rebind(&mut self, action: Action, binding: Binding) -> Result<(), Action> {
+        if let Some((other, _)) = self
+            .0
+            .iter()
+            .find(|(other, bindings)| **other != action && bindings.as_slice() == [binding])
+        {
+            return Err(*other);
+        }
+        for bindings in self.0.values_mut() {
+            bindings.retain(|it| it != &binding);
+        }
+        self.0.insert(action, vec![binding]);
+        Ok(())
+    }
+
+    fn to_ron(&self) -> Result<String> {
+        Ok(ron::ser::to_string_pretty(
+            self,
+            ron::ser::PrettyConfig::default(),
+        )?)
+    }
+
+    fn from_ron(content: &str) -> Result<ActionMap> {
+        let mut map: ActionMap = ron::from_str(content)?;
+        // actions added after the file was written or left without a binding get their defaults
+        for (action, defaults) in ActionMap::default().0 {
+            let bindings = map.0.entry(action).or_default();
+            if bindings.is_empty() {
+                *bindings = defaults;
+            }
+        }
+        Ok(map)
+    }
+
+    #[cfg(target_family = "wasm")]
+    fn load() -> Self {
+        ActionMap::default()
+    }
+
+    #[cfg(not(target_family = "wasm"))]
+    fn load() -> Self {
+        let Ok(content) = std::fs::read_to_string(BINDINGS_PATH) else {
+            return ActionMap::default();
+        };
+
+        ActionMap::from_ron(&content).unwrap_or_else(|err| {
+            warn!("could not read bindings from {BINDINGS_PATH}: {err}");
+            ActionMap::default()
+        })
+    }
+
+    #[cfg(target_family = "wasm")]
+    fn save(&self) -> Result {
+        // there is no config file in the browser
+        Ok(())
+    }
+
+    #[cfg(not(target_family = "wasm"))]
+    fn save(&self) -> Result {
+        std::fs::write(BINDINGS_PATH, self.to_ron()?)?;
+        Ok(())
+    }
+}
+
+#[cfg(not(target_family = "wasm"))]
+const BINDINGS_PATH: &str = "bindings.ron";
+
+/// The actions triggered in the current frame.
+#[derive(Resource, Default, Debug)]
+pub struct ActionState {
+    just_pressed: HashSet<Action>,
+    /// triggered without a binding, e.g. by a UI button
+    pending: HashSet<Action>,
+}
+
+impl ActionState {
+    pub fn just_pressed(&self, action: Action) -> bool {
+        self.just_pressed.contains(&action)
+    }
+
+    /// Trigger the action in the next frame as if one of its bindings was pressed.
+    pub fn trigger(&mut self, action: Action) {
+        self.pending.insert(action);
+    }
+}
+
+fn update_action_state(
+    map: Res<ActionMap>,
+    keys: Res<ButtonInput<KeyCode>>,
+    mouse: Res<ButtonInput<MouseButton>>,
+    screen: Res<BindingsScreen>,
+    mut state: ResMut<ActionState>,
+) {
+    let state = state.as_mut();
+    state.just_pressed.clear();
+    state.just_pressed.extend(state.pending.drain());
+
+    if screen.is_rebinding() {
+        // the next input is captured by the bindings screen
+        return;
+    }
+
+    for action in Action::enumerate() {
+        if screen.is_open() && action != Action::ShowBindings {
+            continue;
+        }
+
+        let just_pressed = map.get(action).iter().any(|binding| match binding {
+            Binding::Key(key) => keys.just_pressed(*key),
+            Binding::Mouse(button) => mouse.just_pressed(*button),
+        });
+        if just_pressed {
+            state.just_pressed.insert(action);
+        }
+    }
+}
+
+fn save_bindings(map: Res<ActionMap>) -> Result {
+    map.save()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_slots_with_an_action_have_a_hotkey() {
+        let map = ActionMap::default();
+        for slot in 0..SLOT_HOTKEYS.len() {
+            let action = Action::select_slot(slot).unwrap();
+            assert_eq!(1, map.get(action).len());
+            assert!(Action::enumerate().contains(&action));
+        }
+        assert_eq!(None, Action::select_slot(SLOT_HOTKEYS.len()));
+    }
+
+    #[test]
+    fn test_rebind_removes_binding_from_other_actions() {
+        let mut map = ActionMap(BTreeMap::from([
+            (Action::Clear, vec![Binding::Key(KeyCode::Backspace)]),
+            (
+                Action::ToggleExecution,
+                vec![Binding::Key(KeyCode::Space), Binding::Key(KeyCode::Enter)],
+            ),
+        ]));
+
+        assert_eq!(
+            Ok(()),
+            map.rebind(Action::Clear, Binding::Key(KeyCode::Space))
+        );
+        assert_eq!(&[Binding::Key(KeyCode::Space)], map.get(Action::Clear));
+        assert_eq!(
+            &[Binding::Key(KeyCode::Enter)],
+            map.get(Action::ToggleExecution)
+        );
+    }
+
+    #[test]
+    fn test_rebind_keeps_last_binding_of_other_actions() {
+        let mut map = ActionMap::default();
+        let show_bindings = map.get(Action::ShowBindings)[0];
+
+        assert_eq!(
+            Err(Action::ShowBindings),
+            map.rebind(Action::Clear, show_bindings)
+        );
+        assert_eq!(ActionMap::default(), map);
+    }
+
+    #[test]
+    fn test_ron_roundtrip() {
+        let mut map = ActionMap::default();
+        map.rebind(Action::Place, Binding::Mouse(MouseButton::Middle))
+            .unwrap();
+
+        let ron = map.to_ron().unwrap();
+        assert_eq!(map, ActionMap::from_ron(&ron).unwrap());
+    }
+
+    #[test]
+    fn test_missing_actions_get_defaults() {
+        let map = ActionMap::from_ron("({Clear: [Key(Delete)]})").unwrap();
+
+        assert_eq!(&[Binding::Key(KeyCode::Delete)], map.get(Action::Clear));
+        assert_eq!(
+            ActionMap::default().get(Action::ToggleExecution),
+            map.get(Action::ToggleExecution)
+        );
+    }
+
+    #[test]
+    fn test_actions_without_bindings_get_defaults() {
+        let map = ActionMap::from_ron("({ShowBindings: []})").unwrap();
+
+        assert_eq!(
+            ActionMap::default().get(Action::ShowBindings),
+            map.get(Action::ShowBindings)
+        );
+    }
+}
@@ -0,0 +1,225 @@
+//! Screen to show and change the [`ActionMap`].
+
+use crate::input::action::{Action, ActionMap, ActionState, Binding};
+use crate::state::MinimalGameState;
+use bevy::input::keyboard::KeyboardInput;
+use bevy::input::mouse::MouseButtonInput;
+use bevy::prelude::*;
+
+pub(super) struct BindingsPlugin;
+
+impl Plugin for BindingsPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<BindingsScreen>().add_systems(
+            Update,
+            (
+                toggle_screen,
+                // runs before the button handling, so the click on a rebind button is discarded in
+                // its own frame and the capture starts with the next input
+                capture_binding,
+                handle_button_interaction,
+                update_labels,
+            )
+                .chain()
+                .run_if(in_state(MinimalGameState::Running)),
+        );
+    }
+}
+
+/// State of the bindings screen.
+#[derive(Resource, Default, Debug)]
+pub struct BindingsScreen {
+    open: bool,
+    /// the action which gets the next pressed input as binding
+    rebinding: Option<Action>,
+    /// the last pressed input was refused, since it is the only binding of the other action
+    refused: Option<(Binding, Action)>,
+}
+
+impl BindingsScreen {
+    pub fn is_open(&self) -> bool {
+        self.open
+    }
+
+    pub fn is_rebinding(&self) -> bool {
+        self.rebinding.is_some()
+    }
+}
+
+#[derive(Component)]
+struct BindingsUi;
+
+#[derive(Component, Debug, Copy, Clone)]
+enum BindingsButton {
+    Rebind(Action),
+    Reset,
+}
+
+fn toggle_screen(
+    actions: Res<ActionState>,
+    mut screen: ResMut<BindingsScreen>,
+    ui: Query<Entity, With<BindingsUi>>,
+    mut commands: Commands,
+) {
+    if !actions.just_pressed(Action::ShowBindings) {
+        return;
+    }
+
+    screen.open = !screen.open;
+    screen.rebinding = None;
+    screen.refused = None;
+
+    if !screen.open {
+        for entity in &ui {
+            commands.entity(entity).despawn();
+        }
+        return;
+    }
+
+    commands
+        .spawn((
+            Name::new("Bindings UI"),
+            BindingsUi,
+            Node {
+                position_type: PositionType::Absolute,
+                left: Val::Percent(30.0),
+                top: Val::Percent(15.0),
+                width: Val::Percent(40.0),
+                flex_direction: FlexDirection::Column,
+                row_gap: Val::Px(6.0),
+                padding: UiRect::all(Val::Px(16.0)),
+                ..default()
+            },
+            BackgroundColor(Color::WHITE),
+            BorderRadius::all(Val::Px(10.0)),
+            GlobalZIndex(10),
+        ))
+        .with_children(|parent| {
+            parent.spawn((
+                Text::new("Bindings - click an entry and press a key"),
+                TextFont {
+                    font_size: 20.0,
+                    ..default()
+                },
+                TextColor(Color::BLACK),
+            ));
+            for action in Action::enumerate() {
+                spawn_button(parent, BindingsButton::Rebind(action));
+            }
+            spawn_button(parent, BindingsButton::Reset);
+        });
+}
+
+fn spawn_button(parent: &mut ChildSpawnerCommands, button: BindingsButton) {
+    parent.spawn((
+        button,
+        Button,
+        Node {
+            padding: UiRect::axes(Val::Px(10.0), Val::Px(4.0)),
+            ..default()
+        },
+        BackgroundColor(Color::srgb(0.9, 0.9, 0.9)),
+        BorderRadius::all(Val::Px(6.0)),
+        Text::default(),
+        TextFont {
+            font_size: 16.0,
+            ..default()
+        },
+        TextColor(Color::BLACK),
+    ));
+}
+
+fn handle_button_interaction(
+    buttons: Query<(&Interaction, &BindingsButton), Changed<Interaction>>,
+    mut screen: ResMut<BindingsScreen>,
+    mut map: ResMut<ActionMap>,
+) {
+    for (interaction, button) in &buttons {
+        if *interaction != Interaction::Pressed {
+            continue;
+        }
+
+        match button {
+            BindingsButton::Rebind(action) => {
+                screen.rebinding = Some(*action);
+                screen.refused = None;
+            }
+            BindingsButton::Reset => {
+                screen.rebinding = None;
+                screen.refused = None;
+                *map = ActionMap::default();
+            }
+        }
+    }
+}
+
+/// Use the next pressed key or mouse button as the new binding.
+fn capture_binding(
+    mut keys: EventReader<KeyboardInput>,
+    mut mouse: EventReader<MouseButtonInput>,
+    mut screen: ResMut<BindingsScreen>,
+    mut map: ResMut<ActionMap>,
+) {
+    let Some(action) = screen.rebinding else {
+        // nothing to capture, discard the events
+        keys.clear();
+        mouse.clear();
+        return;
+    };
+
+    let key = keys
+        .read()
+        .find(|it| it.state.is_pressed())
+        .map(|it| Binding::Key(it.key_code));
+    let button = mouse
+        .read()
+        .find(|it| it.state.is_pressed())
+        .map(|it| Binding::Mouse(it.button));
+
+    if let Some(binding) = key.or(button) {
+        match map.rebind(action, binding) {
+            Ok(()) => {
+                screen.rebinding = None;
+                screen.refused = None;
+            }
+            // keep waiting for another input
+            Err(other) => screen.refused = Some((binding, other)),
+        }
+    }
+}
+
+fn update_labels(
+    map: Res<ActionMap>,
+    screen: Res<BindingsScreen>,
+    mut buttons: Query<(&BindingsButton, &mut Text)>,
+) {
+    if !map.is_changed() && !screen.is_changed() {
+        return;
+    }
+
+    for (button, mut text) in &mut buttons {
+        text.0 = match button {
+            BindingsButton::Rebind(action) if screen.rebinding == Some(*action) => {
+                match screen.refused {
+                    Some((binding, other)) => format!(
+                        "{}: {} is the only binding of {}, press another key...",
+                        action.name(),
+                        binding.name(),
+                        other.name()
+                    ),
+                    None => format!("{}: press a key...", action.name()),
+                }
+            }
+            BindingsButton::Rebind(action) => {
+                let bindings = map
+                    .get(*action)
+                    .iter()
+                    .map(Binding::name)
+                    .collect::<Vec<_>>()
+                    .join(", ");
+                format!("{}: {}", action.name(), bindings)
+            }
+            BindingsButton::Reset => "Reset to defaults".to_string(),
+        };
+    }
+}
@@ -0,0 +1,672 @@
+//! Inspector panel to edit the properties of a placed object.
+//!
+//! The position is edited in polar coordinates relative to a reference activator, since the angle
+//! to the activator defines the played note and the distance defines the timing.
+
+use crate::core::chord::chord_pitches;
+use crate::core::game::LevelConfig;
+use crate::core::model::{
+    Activator, ActivatorShape, Chord, Modifier, Motion, Note, Percussion, Wall, Zone,
+};
+use crate::core::modifier::{modifier_areas, modify_pitch};
+use crate::core::object::{ObjectFields, cycle};
+use crate::input::action::{Action, ActionState};
+use crate::input::picker::cursor_to_world;
+use crate::math::calculate_scale_position;
+use crate::state::{GameState, MinimalGameState};
+use bevy::prelude::*;
+use bevy::window::PrimaryWindow;
+
+pub(super) struct InspectorPlugin;
+
+impl Plugin for InspectorPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Inspector>().add_systems(
+            Update,
+            (
+                select_object.run_if(in_state(GameState::Build)),
+                close_if_despawned,
+                rebuild_panel,
+                handle_field_buttons.run_if(in_state(GameState::Build)),
+                update_values,
+                draw_selection,
+            )
+                .chain()
+                .run_if(in_state(MinimalGameState::Running)),
+        );
+    }
+}
+
+/// The inspected object and the activator its position is relative to.
+#[derive(Resource, Default, Debug)]
+struct Inspector {
+    target: Option<Entity>,
+    reference: Option<Entity>,
+}
+
+#[derive(Component)]
+struct InspectorUi;
+
+/// An editable property of the inspected object. The fields of the mechanics are registered with
+/// their [`ObjectType`](crate::core::object::ObjectType), the inspector only knows the fields
+/// every object has.
+#[derive(Debug, Copy, Clone, PartialEq)]
+enum Field {
+    /// the activator the position is relative to
+    Reference,
+    /// distance to the reference activator
+    Radius,
+    /// angle to the reference activator in degrees
+    Angle,
+    /// size of the icon
+    Size,
+    /// whether the object orbits the reference activator or moves along a path
+    Motion,
+    /// beats of one orbit or of one way along the path and back
+    Period,
+    /// length of the path
+    PathLength,
+    /// direction of the path in degrees
+    PathAngle,
+    /// the registered field with the index in [`ObjectFields`]
+    Object(usize),
+}
+
+/// The steps of the fields which cycle through their values.
+const CYCLE: &[f32] = &[-1.0, 1.0];
+
+impl Field {
+    fn name(&self, fields: &ObjectFields) -> &'static str {
+        match self {
+            Field::Reference => "Relative to",
+            Field::Radius => "Radius",
+            Field::Angle => "Angle",
+            Field::Size => "Size",
+            Field::Motion => "Motion",
+            Field::Period => "Period",
+            Field::PathLength => "Path Length",
+            Field::PathAngle => "Path Angle",
+            Field::Object(index) => fields.get(*index).map(|it| it.name).unwrap_or_default(),
+        }
+    }
+
+    /// The steps of the buttons to change the value, `None` if it cycles through its values.
+    fn steps(&self, fields: &ObjectFields) -> Option<&'static [f32]> {
+        match self {
+            Field::Reference | Field::Motion => None,
+            Field::Radius => Some(&[-10.0, -1.0, 1.0, 10.0]),
+            Field::Angle | Field::PathAngle => Some(&[-15.0, -1.0, 1.0, 15.0]),
+            Field::Size => Some(&[-0.1, 0.1]),
+            Field::Period => Some(&[-1.0, -0.25, 0.25, 1.0]),
+            Field::PathLength => Some(&[-50.0, -10.0, 10.0, 50.0]),
+            Field::Object(index) => fields.get(*index).and_then(|it| it.steps),
+        }
+    }
+
+    fn button_label(&self, fields: &ObjectFields, step: f32) -> String {
+        match (self, self.steps(fields)) {
+            (_, None) if step < 0.0 => "<".to_string(),
+            (_, None) => ">".to_string(),
+            (Field::Size, _) => format!("{:+.0}%", step * 100.0),
+            _ => format!("{step:+}"),
+        }
+    }
+}
+
+#[derive(Component, Debug)]
+struct FieldButton {
+    field: Field,
+    step: f32,
+}
+
+#[derive(Component, Debug)]
+struct FieldValue(Field);
+
+#[derive(Component, Debug)]
+struct Title;
+
+/// The objects which have properties to edit.
+type Inspectable = Or<(
+    With<Note>,
+    With<Activator>,
+    With<Wall>,
+    With<Zone>,
+    With<Modifier>,
+)>;
+
+fn select_object(
+    actions: Res<ActionState>,
+    primary_window: Query<Entity, With<PrimaryWindow>>,
+    window: Query<&Window>,
+    camera: Query<(&Camera, &GlobalTransform)>,
+    objects: Query<(Entity, &Transform), Inspectable>,
+    activators: Query<(Entity, &Transform), With<Activator>>,
+    mut inspector: ResMut<Inspector>,
+) {
+    if !actions.just_pressed(Action::Inspect) {
+        return;
+    }
+    let Ok(primary_window) = primary_window.single() else {
+        return;
+    };
+    let Ok(position) = cursor_to_world(window, camera, primary_window) else {
+        return;
+    };
+
+    let target = object_at(objects.iter(), position);
+
+    // the nearest activator is the most likely one to play the object
+    let reference = target.and_then(|target| {
+        let target_position = objects.get(target).ok()?.1.translation.xy();
+        activators
+            .iter()
+            .filter(|(entity, _)| *entity != target)
+            .min_by(|(_, t1), (_, t2)| {
+                let d1 = t1.translation.xy().distance(target_position);
+                let d2 = t2.translation.xy().distance(target_position);
+                d1.total_cmp(&d2)
+            })
+            .map(|(entity, _)| entity)
+    });
+
+    inspector.target = target;
+    inspector.reference = reference;
+}
+
+/// The object nearest to the position, if it is close enough to be clicked.
+fn object_at<'a>(
+    objects: impl Iterator<Item = (Entity, &'a Transform)>,
+    position: Vec2,
+) -> Option<Entity> {
+    objects
+        .filter(|(_, transform)| transform.translation.xy().distance(position) < 10.0)
+        .min_by(|(_, t1), (_, t2)| {
+            let d1 = t1.translation.xy().distance(position);
+            let d2 = t2.translation.xy().distance(position);
+            d1.total_cmp(&d2)
+        })
+        .map(|(entity, _)| entity)
+}
+
+fn close_if_despawned(mut inspector: ResMut<Inspector>, entities: Query<Entity>) {
+    if let Some(target) = inspector.target
+        && !entities.contains(target)
+    {
+        inspector.target = None;
+        inspector.reference = None;
+    }
+    if let Some(reference) = inspector.reference
+        && !entities.contains(reference)
+    {
+        inspector.reference = None;
+    }
+}
+
+fn rebuild_panel(
+    inspector: Res<Inspector>,
+    ui: Query<Entity, With<InspectorUi>>,
+    objects: Query<EntityRef>,
+    object_fields: Res<ObjectFields>,
+    mut commands: Commands,
+) {
+    if !inspector.is_changed() {
+        return;
+    }
+    for entity in &ui {
+        commands.entity(entity).despawn();
+    }
+    let Some(target) = inspector.target.and_then(|it| objects.get(it).ok()) else {
+        return;
+    };
+
+    let mut fields = vec![Field::Reference];
+    if inspector.reference.is_some() {
+        fields.extend([Field::Radius, Field::Angle]);
+    }
+    fields.extend(
+        object_fields
+            .of(&target)
+            .map(|(index, _)| Field::Object(index)),
+    );
+    // walls, zones and modifiers shape the board and stay in place
+    if !target.contains::<Wall>() && !target.contains::<Zone>() && !target.contains::<Modifier>() {
+        fields.push(Field::Motion);
+        match target.get::<Motion>() {
+            Some(Motion::Orbit { .. }) => fields.push(Field::Period),
+            Some(Motion::Path { .. }) => {
+                fields.extend([Field::Period, Field::PathLength, Field::PathAngle])
+            }
+            None => {}
+        }
+    }
+    fields.push(Field::Size);
+
+    commands
+        .spawn((
+            Name::new("Inspector UI"),
+            InspectorUi,
+            // block clicks on the panel
+            Interaction::default(),
+            Node {
+                position_type: PositionType::Absolute,
+                right: Val::Px(10.0),
+                top: Val::Px(10.0),
+                flex_direction: FlexDirection::Column,
+                row_gap: Val::Px(6.0),
+                padding: UiRect::all(Val::Px(12.0)),
+                ..default()
+            },
+            BackgroundColor(Color::WHITE),
+            BorderRadius::all(Val::Px(10.0)),
+        ))
+        .with_children(|panel| {
+            panel.spawn((
+                Title,
+                Text::default(),
+                text_font(18.0),
+                TextColor(Color::BLACK),
+            ));
+
+            for field in fields {
+                panel
+                    .spawn(Node {
+                        column_gap: Val::Px(4.0),
+                        align_items: AlignItems::Center,
+                        ..default()
+                    })
+                    .with_children(|row| {
+                        row.spawn((
+                            Text::new(field.name(&object_fields)),
+                            text_font(16.0),
+                            TextColor(Color::BLACK),
+                            Node {
+                                width: Val::Px(100.0),
+                                ..default()
+                            },
+                        ));
+                        for &step in field.steps(&object_fields).unwrap_or(CYCLE) {
+                            row.spawn((
+                                FieldButton { field, step },
+                                Button,
+                                Node {
+                                    padding: UiRect::axes(Val::Px(6.0), Val::Px(2.0)),
+                                    ..default()
+                                },
+                                BackgroundColor(Color::srgb(0.9, 0.9, 0.9)),
+                                BorderRadius::all(Val::Px(6.0)),
+                                Text::new(field.button_label(&object_fields, step)),
+                                text_font(14.0),
+                                TextColor(Color::BLACK),
+                            ));
+                        }
+                        row.spawn((
+                            FieldValue(field),
+                            Text::default(),
+                            text_font(16.0),
+                            TextColor(Color::BLACK),
+                            Node {
+                                margin: UiRect::left(Val::Px(8.0)),
+                                ..default()
+                            },
+                        ));
+                    });
+            }
+        });
+}
+
+fn text_font(font_size: f32) -> TextFont {
+    TextFont {
+        font_size,
+        ..default()
+    }
+}
+
+/// Write the changes of a button back to the components of the inspected object.
+fn handle_field_buttons(
+    buttons: Query<(&Interaction, &FieldButton), Changed<Interaction>>,
+    mut inspector: ResMut<Inspector>,
+    activators: Query<Entity, With<Activator>>,
+    mut transforms: Query<&mut Transform>,
+    mut motions: Query<&mut Motion>,
+    object_fields: Res<ObjectFields>,
+    mut commands: Commands,
+) {
+    let Some(target) = inspector.target else {
+        return;
+    };
+
+    for (interaction, button) in &buttons {
+        if *interaction != Interaction::Pressed {
+            continue;
+        }
+
+        match button.field {
+            Field::Reference => {
+                let mut candidates: Vec<Entity> =
+                    activators.iter().filter(|it| *it != target).collect();
+                candidates.sort();
+                inspector.reference = cycle(&candidates, inspector.reference, button.step);
+            }
+            Field::Radius | Field::Angle => {
+                let Some(center) = inspector
+                    .reference
+                    .and_then(|it| transforms.get(it).ok())
+                    .map(|it| it.translation.xy())
+                else {
+                    continue;
+                };
+                let Ok(mut transform) = transforms.get_mut(target) else {
+                    continue;
+                };
+
+                let (mut radius, mut angle) = to_polar(center, transform.translation.xy());
+                if button.field == Field::Radius {
+                    radius = (radius + button.step).max(0.0);
+                } else {
+                    angle += button.step.to_radians();
+                }
+                let position = center + Vec2::from_angle(angle) * radius;
+                transform.translation = position.extend(transform.translation.z);
+            }
+            Field::Size => {
+                if let Ok(mut transform) = transforms.get_mut(target) {
+                    transform.scale *= 1.0 + button.step;
+                }
+            }
+            Field::Motion => {
+                // an orbit needs a reference activator as its center
+                let mut kinds = vec![None];
+                if let Some(center) = inspector.reference {
+                    kinds.push(Some(Motion::Orbit { center, beats: 4.0 }));
+                }
+                kinds.push(Some(Motion::Path {
+                    offset: Vec2::new(100.0, 0.0),
+                    beats: 2.0,
+                }));
+                let current = kinds.iter().position(|kind| {
+                    matches!(
+                        (kind, motions.get(target).ok()),
+                        (Some(Motion::Orbit { .. }), Some(Motion::Orbit { .. }))
+                            | (Some(Motion::Path { .. }), Some(Motion::Path { .. }))
+                            | (None, None)
+                    )
+                });
+                let indices: Vec<usize> = (0..kinds.len()).collect();
+                match cycle(&indices, current, button.step).and_then(|it| kinds[it]) {
+                    Some(motion) => commands.entity(target).insert(motion),
+                    None => commands.entity(target).remove::<Motion>(),
+                };
+                // the fields depend on the kind of motion
+                inspector.set_changed();
+            }
+            Field::Period => {
+                if let Ok(mut motion) = motions.get_mut(target) {
+                    let (Motion::Orbit { beats, .. } | Motion::Path { beats, .. }) =
+                        motion.as_mut();
+                    *beats = (*beats + button.step).max(0.25);
+                }
+            }
+            Field::PathLength | Field::PathAngle => {
+                if let Ok(mut motion) = motions.get_mut(target)
+                    && let Motion::Path { offset, .. } = motion.as_mut()
+                {
+                    let (mut length, mut angle) = to_polar(Vec2::ZERO, *offset);
+                    if button.field == Field::PathLength {
+                        length = (length + button.step).max(10.0);
+                    } else {
+                        angle += button.step.to_radians();
+                    }
+                    *offset = Vec2::from_angle(angle) * length;
+                }
+            }
+            Field::Object(index) => {
+                let Some(edit) = object_fields.get(index).map(|it| it.edit) else {
+                    continue;
+                };
+                let step = button.step;
+                commands
+                    .entity(target)
+                    .queue(move |mut object: EntityWorldMut| edit(&mut object, step));
+            }
+        }
+    }
+}
+
+/// Radius and angle in radians of the point relative to the center.
+fn to_polar(center: Vec2, point: Vec2) -> (f32, f32) {
+    let direction = point - center;
+    (direction.length(), direction.to_angle())
+}
+
+fn update_values(
+    inspector: Res<Inspector>,
+    level: Res<LevelConfig>,
+    names: Query<&Name>,
+    transforms: Query<&Transform>,
+    objects: Query<EntityRef, (Without<Title>, Without<FieldValue>)>,
+    object_fields: Res<ObjectFields>,
+    shapes: Query<&ActivatorShape>,
+    motions: Query<&Motion>,
+    modifiers: Query<(&Modifier, &Transform)>,
+    notes: Query<(Option<&Chord>, Option<&Percussion>), With<Note>>,
+    mut title: Query<&mut Text, With<Title>>,
+    mut values: Query<(&FieldValue, &mut Text), Without<Title>>,
+) {
+    let Some(target) = inspector.target else {
+        return;
+    };
+    let (Ok(transform), Ok(object)) = (transforms.get(target), objects.get(target)) else {
+        return;
+    };
+    let center = inspector
+        .reference
+        .and_then(|it| transforms.get(it).ok())
+        .map(|it| it.translation.xy());
+    let name = |entity: Entity| {
+        names
+            .get(entity)
+            .map(|it| it.to_string())
+            .unwrap_or_else(|_| format!("{entity}"))
+    };
+
+    if let Ok(mut title) = title.single_mut() {
+        let mut text = name(target);
+        if let Ok((_, Some(percussion))) = notes.get(target) {
+            // percussion plays at any angle
+            text = format!("{text} ({percussion:?})");
+        } else if let Some(center) = center
+            && let Ok((chord, _)) = notes.get(target)
+        {
+            let shape = inspector
+                .reference
+                .and_then(|it| shapes.get(it).ok())
+                .unwrap_or(&ActivatorShape::Circle);
+            let index = calculate_scale_position(
+                shape,
+                &center,
+                &transform.translation.xy(),
+                &*level.scale,
+            );
+            let modifiers = modifier_areas(&modifiers);
+            let pitches: Vec<String> = chord_pitches(chord, index, &*level.scale)
+                .into_iter()
+                .map(|it| modify_pitch(it, transform.translation.xy(), &modifiers).to_string())
+                .collect();
+            text = format!("{text} ({})", pitches.join(" "));
+        }
+        title.set_if_neq(Text(text));
+    }
+
+    for (value, mut text) in &mut values {
+        let polar = center.map(|center| to_polar(center, transform.translation.xy()));
+        let value = match value.0 {
+            Field::Reference => inspector.reference.map(name).unwrap_or("none".to_string()),
+            Field::Radius => polar
+                .map(|(radius, _)| format!("{radius:.1}"))
+                .unwrap_or_default(),
+            Field::Angle => polar
+                .map(|(_, angle)| format!("{:.1} deg", angle.to_degrees().rem_euclid(360.0)))
+                .unwrap_or_default(),
+            Field::Size => format!("{:.3}", transform.scale.x),
+            Field::Motion => match motions.get(target) {
+                Ok(Motion::Orbit { center, .. }) => format!("orbit {}", name(*center)),
+                Ok(Motion::Path { .. }) => "path".to_string(),
+                Err(_) => "none".to_string(),
+            },
+            Field::Period => match motions.get(target) {
+                Ok(Motion::Orbit { beats, .. } | Motion::Path { beats, .. }) => {
+                    format!("{beats} beats")
+                }
+                Err(_) => String::new(),
+            },
+            Field::PathLength => match motions.get(target) {
+                Ok(Motion::Path { offset, .. }) => format!("{:.0}", offset.length()),
+                _ => String::new(),
+            },
+            Field::PathAngle => match motions.get(target) {
+                Ok(Motion::Path { offset, .. }) => {
+                    format!("{:.0} deg", offset.to_angle().to_degrees())
+                }
+                _ => String::new(),
+            },
+            Field::Object(index) => object_fields
+                .get(index)
+                .and_then(|it| (it.value)(&object))
+                .unwrap_or_default(),
+        };
+        text.set_if_neq(Text(value));
+    }
+}
+
+fn draw_selection(inspector: Res<Inspector>, transforms: Query<&Transform>, mut gizmos: Gizmos) {
+    let Some(target) = inspector.target.and_then(|it| transforms.get(it).ok()) else {
+        return;
+    };
+    let position = target.translation.xy();
+    gizmos.circle_2d(Isometry2d::from_translation(position), 16.0, Color::WHITE);
+
+    if let Some(reference) = inspector.reference.and_then(|it| transforms.get(it).ok()) {
+        gizmos.line_2d(
+            reference.translation.xy(),
+            position,
+            Color::srgba(1.0, 1.0, 1.0, 0.3),
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::game::tests::{execution_app, place, spawn_main};
+    use crate::core::model::ActivatorColor;
+    use crate::visual::color::ColorPalette;
+
+    #[test]
+    fn test_modifier_is_selected() {
+        let mut world = World::new();
+        world.spawn((Note, Transform::from_xyz(0.0, 0.0, 0.0)));
+        let modifier = world
+            .spawn((Modifier::Sharp, Transform::from_xyz(20.0, 0.0, 0.0)))
+            .id();
+
+        let mut objects = world.query_filtered::<(Entity, &Transform), Inspectable>();
+        let target = object_at(objects.iter(&world), Vec2::new(18.0, 0.0));
+        assert_eq!(Some(modifier), target);
+    }
+
+    /// An app in the build mode with the inspector.
+    fn inspector_app() -> App {
+        let mut app = execution_app();
+        app.init_resource::<ActionState>()
+            .add_plugins(InspectorPlugin);
+        app
+    }
+
+    fn inspect(app: &mut App, target: Entity, reference: Option<Entity>) {
+        *app.world_mut().resource_mut::<Inspector>() = Inspector {
+            target: Some(target),
+            reference,
+        };
+        app.update();
+    }
+
+    fn press(app: &mut App, field: Field, step: f32) {
+        app.world_mut()
+            .spawn((FieldButton { field, step }, Interaction::Pressed));
+        app.update();
+    }
+
+    /// The value of the field in the panel, `None` if the panel does not show it.
+    fn shown(app: &mut App, field: Field) -> Option<String> {
+        let world = app.world_mut();
+        let mut values = world.query::<(&FieldValue, &Text)>();
+        values
+            .iter(world)
+            .find(|(value, _)| value.0 == field)
+            .map(|(_, text)| text.0.clone())
+    }
+
+    /// The registered field with the name.
+    fn object_field(app: &App, name: &str) -> Field {
+        let fields = app.world().resource::<ObjectFields>();
+        let index = (0..)
+            .map_while(|it| fields.get(it))
+            .position(|it| it.name == name);
+        Field::Object(index.unwrap())
+    }
+
+    #[test]
+    fn test_color_of_an_activator_is_cycled() {
+        let mut app = inspector_app();
+        let activator = place(app.world_mut(), "Activator", 100.0, 0.0);
+        let color = object_field(&app, "Color");
+        inspect(&mut app, activator, None);
+        let before = app.world().get::<ActivatorColor>(activator).unwrap().0;
+
+        press(&mut app, color, 1.0);
+
+        let after = app.world().get::<ActivatorColor>(activator).unwrap().0;
+        assert_eq!(
+            cycle(&ColorPalette::enumerate(), Some(before), 1.0),
+            Some(after)
+        );
+        assert_eq!(Some(format!("{after:?}")), shown(&mut app, color));
+    }
+
+    #[test]
+    fn test_position_is_relative_to_the_reference() {
+        let mut app = inspector_app();
+        let world = app.world_mut();
+        let main = spawn_main(world, 100.0, 100.0);
+        let note = place(world, "Note", 200.0, 100.0);
+        inspect(&mut app, note, Some(main));
+        let position = |app: &App| app.world().get::<Transform>(note).unwrap().translation.xy();
+
+        press(&mut app, Field::Radius, 10.0);
+        assert!(position(&app).distance(Vec2::new(210.0, 100.0)) < 0.001);
+        assert_eq!(Some("110.0".to_string()), shown(&mut app, Field::Radius));
+
+        press(&mut app, Field::Angle, 15.0);
+        let expected = Vec2::new(100.0, 100.0) + Vec2::from_angle(15f32.to_radians()) * 110.0;
+        assert!(position(&app).distance(expected) < 0.001);
+        assert_eq!(Some("15.0 deg".to_string()), shown(&mut app, Field::Angle));
+        assert_eq!(Some("110.0".to_string()), shown(&mut app, Field::Radius));
+    }
+
+    #[test]
+    fn test_size_scales_the_icon() {
+        let mut app = inspector_app();
+        let note = place(app.world_mut(), "Note", 200.0, 100.0);
+        let (color, length) = (object_field(&app, "Color"), object_field(&app, "Length"));
+        inspect(&mut app, note, None);
+        // without a reference the position is not editable, notes have no color
+        assert_eq!(None, shown(&mut app, Field::Radius));
+        assert_eq!(None, shown(&mut app, color));
+        assert_eq!(Some("full".to_string()), shown(&mut app, length));
+
+        press(&mut app, Field::Size, 0.1);
+
+        let scale = app.world().get::<Transform>(note).unwrap().scale;
+        assert!(scale.distance(Vec3::splat(0.025 * 1.1)) < 0.0001);
+    }
+}
@@ -1,21 +1,50 @@
+mod action;
+mod bindings;
+mod inspector;
+mod piano_roll;
 mod picker;
-mod ui;
+mod save;
+mod timeline;
+mod toolbar;
+mod touch;
 
+use crate::core::game::{ExecutionClock, LevelConfig};
+use crate::input::action::{Action, ActionPlugin, ActionState};
+use crate::input::bindings::BindingsPlugin;
+use crate::input::inspector::InspectorPlugin;
+use crate::input::piano_roll::PianoRollPlugin;
 use crate::input::picker::{ManuallyPlaced, PickerPlugin};
-use crate::input::ui::UiPlugin;
-use crate::state::{AppState, GameState};
+use crate::input::save::SavePlugin;
+use crate::input::timeline::TimelinePlugin;
+use crate::input::toolbar::ToolbarPlugin;
+use crate::input::touch::TouchPlugin;
+use crate::state::{AppState, ExecutionState, GameState};
 use bevy::prelude::*;
 
 pub struct InputPlugin;
 
 impl Plugin for InputPlugin {
     fn build(&self, app: &mut App) {
-        app.add_plugins((PickerPlugin, UiPlugin))
-            .add_systems(Update, close_on_esc)
-            .add_systems(
-                Update,
-                handle_game_loop_input.run_if(in_state(AppState::Game)),
-            );
+        app.add_plugins((
+            ActionPlugin,
+            BindingsPlugin,
+            InspectorPlugin,
+            PianoRollPlugin,
+            PickerPlugin,
+            SavePlugin,
+            TimelinePlugin,
+            ToolbarPlugin,
+            TouchPlugin,
+        ))
+        .add_systems(Update, close_on_esc)
+        .add_systems(
+            Update,
+            handle_execution_control_input.run_if(in_state(GameState::Execute)),
+        )
+        .add_systems(
+            Update,
+            (handle_game_loop_input, handle_loop_mode_input).run_if(in_state(AppState::Game)),
+        );
     }
 }
 
@@ -28,14 +57,14 @@ fn close_on_esc() {
 fn close_on_esc(
     mut commands: Commands,
     focused_windows: Query<(Entity, &Window)>,
-    input: Res<ButtonInput<KeyCode>>,
+    actions: Res<ActionState>,
 ) {
     for (window, focus) in focused_windows.iter() {
         if !focus.focused {
             continue;
         }
 
-        if input.just_pressed(KeyCode::Escape) {
+        if actions.just_pressed(Action::Quit) {
             commands.entity(window).despawn();
         }
     }
@@ -44,7 +73,7 @@ fn close_on_esc(
 fn handle_game_loop_input(
     current_state: Res<State<GameState>>,
     mut next_state: ResMut<NextState<GameState>>,
-    keys: Res<ButtonInput<KeyCode>>,
+    actions: Res<ActionState>,
     objects: Query<&ManuallyPlaced>,
 ) {
     if objects.iter().count() == 0 {
@@ -53,7 +82,7 @@ fn handle_game_loop_input(
         return;
     }
 
-    if keys.just_pressed(KeyCode::Space) {
+    if actions.just_pressed(Action::ToggleExecution) {
         match current_state.get() {
             GameState::SetupResources => {}
             GameState::SetupGameObjects => {}
@@ -63,3 +92,46 @@ fn handle_game_loop_input(
         }
     }
 }
+
+/// Switch between no loop, loop and loop synced to the background strings.
+fn handle_loop_mode_input(actions: Res<ActionState>, config: Option<ResMut<LevelConfig>>) {
+    let Some(mut config) = config else {
+        return;
+    };
+    if !actions.just_pressed(Action::SwitchLoopMode) {
+        return;
+    }
+
+    let looping = &mut config.looping;
+    (looping.enabled, looping.sync_to_background) =
+        match (looping.enabled, looping.sync_to_background) {
+            (false, _) => (true, false),
+            (true, false) => (true, true),
+            (true, true) => (false, false),
+        };
+    info!("loop mode: {:?}", looping);
+}
+
+/// Pause, step and change the playback rate of the execution.
+fn handle_execution_control_input(
+    actions: Res<ActionState>,
+    state: Res<State<ExecutionState>>,
+    mut next_state: ResMut<NextState<ExecutionState>>,
+    mut clock: ResMut<ExecutionClock>,
+) {
+    if actions.just_pressed(Action::TogglePause) {
+        next_state.set(match state.get() {
+            ExecutionState::Running => ExecutionState::Paused,
+            ExecutionState::Paused => ExecutionState::Running,
+        });
+    }
+    if actions.just_pressed(Action::Step) && *state == ExecutionState::Paused {
+        clock.step();
+    }
+    if actions.just_pressed(Action::Faster) {
+        clock.faster();
+    }
+    if actions.just_pressed(Action::Slower) {
+        clock.slower();
+    }
+}
@@ -0,0 +1,447 @@
+//! Piano roll of the predicted melody in the build mode. Time runs from left to right and the pitch
+//! of a note from bottom to top, percussion has its own row at the bottom. Besides the notes of the
+//! scale, there is a row for every pitch the modifiers shift a note to. Clicking an entry
+//! highlights its note on the board.
+
+use crate::core::chord::played_pitches;
+use crate::core::game::LevelConfig;
+use crate::core::model::{ActivatorColor, ActivatorShape, Chord, Modifier, NoteLength, Percussion};
+use crate::core::modifier::ModifierArea;
+use crate::core::prediction::{ObjectKind, Prediction};
+use crate::math::calculate_scale_position;
+use crate::music::model::{Pitch, Scale};
+use crate::state::GameState;
+use bevy::prelude::*;
+use bevy::ui::FocusPolicy;
+
+pub(super) struct PianoRollPlugin;
+
+impl Plugin for PianoRollPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Highlighted>()
+            .add_systems(OnEnter(GameState::Build), setup)
+            .add_systems(OnExit(GameState::Build), cleanup)
+            .add_systems(
+                Update,
+                (
+                    rebuild_entries,
+                    handle_entry_click,
+                    forget_despawned,
+                    update_entries,
+                    draw_highlight,
+                )
+                    .chain()
+                    .run_if(in_state(GameState::Build)),
+            );
+    }
+}
+
+/// Height of a pitch row in logical pixels.
+const ROW_HEIGHT: f32 = 10.0;
+/// Width of an entry in logical pixels.
+const ENTRY_WIDTH: f32 = 8.0;
+const LABEL_WIDTH: f32 = 30.0;
+
+/// The note highlighted on the board.
+#[derive(Resource, Default, Debug)]
+struct Highlighted(Option<Entity>);
+
+#[derive(Component)]
+struct PianoRoll;
+
+/// The area the entries are placed in.
+#[derive(Component)]
+struct RollArea;
+
+#[derive(Component)]
+struct RollLabels;
+
+/// A predicted note.
+#[derive(Component)]
+struct RollEntry {
+    note: Entity,
+}
+
+fn setup(mut commands: Commands) {
+    commands.spawn((
+        Name::new("Piano Roll"),
+        PianoRoll,
+        // block clicks on the panel
+        Interaction::default(),
+        Node {
+            position_type: PositionType::Absolute,
+            left: Val::Px(20.0),
+            // leave space for the play button
+            right: Val::Px(240.0),
+            bottom: Val::Px(20.0),
+            padding: UiRect::all(Val::Px(4.0)),
+            ..default()
+        },
+        BackgroundColor(Color::srgba(1.0, 1.0, 1.0, 0.15)),
+        BorderRadius::all(Val::Px(4.0)),
+        children![
+            (
+                RollLabels,
+                Node {
+                    width: Val::Px(LABEL_WIDTH),
+                    flex_direction: FlexDirection::ColumnReverse,
+                    ..default()
+                },
+                FocusPolicy::Pass,
+            ),
+            (
+                RollArea,
+                Node {
+                    flex_grow: 1.0,
+                    ..default()
+                },
+                FocusPolicy::Pass,
+            )
+        ],
+    ));
+}
+
+fn cleanup(mut commands: Commands, roll: Query<Entity, With<PianoRoll>>) {
+    for entity in &roll {
+        commands.entity(entity).despawn();
+    }
+}
+
+/// The rows of the roll from bottom to top, the drum row if any percussion is played and then the
+/// pitches from low to high.
+#[derive(Debug)]
+struct RollRows {
+    pitches: Vec<Pitch>,
+    drums: bool,
+}
+
+/// Where the entries of a note are placed, the left edge and the width in percent of the duration.
+#[derive(Debug, PartialEq)]
+struct EntryPlacement {
+    rows: Vec<usize>,
+    left: f32,
+    /// The width of a note with a length, the others are centered on their time.
+    width: Option<f32>,
+}
+
+impl RollRows {
+    /// The rows for the notes of the scale and every played pitch, percussion plays none.
+    fn new<'a>(scale: &dyn Scale, played: impl IntoIterator<Item = Option<&'a [Pitch]>>) -> Self {
+        let mut drums = false;
+        let mut pitches: Vec<Pitch> = (1..=scale.size())
+            .map(|it| Pitch {
+                note: scale.get(it),
+                octave: 0,
+            })
+            .collect();
+        for tones in played {
+            match tones {
+                Some(tones) => pitches.extend_from_slice(tones),
+                None => drums = true,
+            }
+        }
+        pitches.sort_by_key(Pitch::semitones);
+        pitches.dedup();
+        RollRows { pitches, drums }
+    }
+
+    fn len(&self) -> usize {
+        self.pitches.len() + usize::from(self.drums)
+    }
+
+    fn names(&self) -> impl Iterator<Item = String> {
+        self.drums
+            .then(|| "Drum".to_string())
+            .into_iter()
+            .chain(self.pitches.iter().map(Pitch::to_string))
+    }
+
+    /// Place the entries of a note hit at the time. A chord has an entry in the row of every tone,
+    /// percussion is placed in the drum row.
+    fn place(
+        &self,
+        tones: Option<&[Pitch]>,
+        time: f32,
+        length: Option<f32>,
+        beat_length: f32,
+        duration: f32,
+    ) -> EntryPlacement {
+        let rows = match tones {
+            Some(tones) => tones
+                .iter()
+                .map(|tone| {
+                    self.pitches
+                        .iter()
+                        .position(|it| it == tone)
+                        .unwrap_or_default()
+                        + usize::from(self.drums)
+                })
+                .collect(),
+            None => vec![0],
+        };
+        EntryPlacement {
+            rows,
+            left: time / duration * 100.0,
+            width: length.map(|length| length * beat_length / duration * 100.0),
+        }
+    }
+}
+
+/// Show an entry for every predicted note at its time and pitch.
+fn rebuild_entries(
+    prediction: Res<Prediction>,
+    config: Res<LevelConfig>,
+    area: Query<(Entity, Ref<RollArea>)>,
+    labels: Query<Entity, With<RollLabels>>,
+    colors: Query<&ActivatorColor>,
+    shapes: Query<&ActivatorShape>,
+    lengths: Query<Ref<NoteLength>>,
+    percussion: Query<(), With<Percussion>>,
+    chords: Query<Ref<Chord>>,
+    modifiers: Query<(Ref<Modifier>, Ref<Transform>)>,
+    mut removed: RemovedComponents<NoteLength>,
+    mut removed_chords: RemovedComponents<Chord>,
+    mut removed_modifiers: RemovedComponents<Modifier>,
+    mut commands: Commands,
+) -> Result {
+    let (area, added) = area.single()?;
+    // removed components are read every frame, so they are not reported again later
+    let removed =
+        removed.read().count() + removed_chords.read().count() + removed_modifiers.read().count()
+            > 0;
+    let notes_changed = removed
+        || lengths.iter().any(|it| it.is_changed())
+        || chords.iter().any(|it| it.is_changed())
+        || modifiers
+            .iter()
+            .any(|(modifier, transform)| modifier.is_changed() || transform.is_changed());
+    if !prediction.is_changed() && !config.is_changed() && !added.is_added() && !notes_changed {
+        return Ok(());
+    }
+
+    let modifiers: Vec<ModifierArea> = modifiers
+        .iter()
+        .map(|(modifier, transform)| ModifierArea::new(&modifier, &transform))
+        .collect();
+    // the pitches every predicted note plays, percussion has none
+    let played: Vec<_> = prediction
+        .events
+        .iter()
+        .filter(|it| it.kind == ObjectKind::Note)
+        .map(|event| {
+            if percussion.contains(event.target) {
+                return (event, None);
+            }
+            // the same mapping as the played note, index 0 and the size are the root, moving
+            // notes are mapped by their position at the time of the hit
+            let index = calculate_scale_position(
+                shapes.get(event.source).unwrap_or(&ActivatorShape::Circle),
+                &Vec2::ZERO,
+                &event.offset,
+                &*config.scale,
+            );
+            let pitches = played_pitches(
+                chords.get(event.target).ok().as_deref(),
+                index,
+                &*config.scale,
+                event.position,
+                &modifiers,
+            );
+            (event, Some(pitches))
+        })
+        .collect();
+
+    let rows = RollRows::new(
+        &*config.scale,
+        played.iter().map(|(_, tones)| tones.as_deref()),
+    );
+    let area_height = Val::Px(rows.len() as f32 * ROW_HEIGHT);
+    commands
+        .entity(area)
+        .despawn_related::<Children>()
+        .insert(Node {
+            flex_grow: 1.0,
+            height: area_height,
+            ..default()
+        });
+
+    let labels = labels.single()?;
+    commands.entity(labels).despawn_related::<Children>();
+    for name in rows.names() {
+        commands.spawn((
+            Node {
+                height: Val::Px(ROW_HEIGHT),
+                ..default()
+            },
+            Text::new(name),
+            TextFont {
+                font_size: ROW_HEIGHT - 1.0,
+                ..default()
+            },
+            TextColor(Color::WHITE),
+            FocusPolicy::Pass,
+            ChildOf(labels),
+        ));
+    }
+
+    if prediction.duration <= 0.0 {
+        return Ok(());
+    }
+    for (event, tones) in &played {
+        let placement = rows.place(
+            tones.as_deref(),
+            event.time,
+            lengths.get(event.target).ok().map(|it| it.0),
+            config.beat_length(),
+            prediction.duration,
+        );
+        let color = colors
+            .get(event.source)
+            .map(Color::from)
+            .unwrap_or(Color::WHITE);
+        let (width, margin) = match placement.width {
+            Some(width) => (Val::Percent(width), Val::ZERO),
+            None => (Val::Px(ENTRY_WIDTH), Val::Px(-ENTRY_WIDTH / 2.0)),
+        };
+
+        for &row in &placement.rows {
+            commands.spawn((
+                RollEntry { note: event.target },
+                Button,
+                Node {
+                    position_type: PositionType::Absolute,
+                    left: Val::Percent(placement.left),
+                    bottom: Val::Px(row as f32 * ROW_HEIGHT),
+                    width,
+                    height: Val::Px(ROW_HEIGHT),
+                    margin: UiRect::left(margin),
+                    border: UiRect::all(Val::Px(1.0)),
+                    ..default()
+                },
+                BackgroundColor(color),
+                BorderColor(Color::NONE),
+                ChildOf(area),
+            ));
+        }
+    }
+    Ok(())
+}
+
+/// Clicking an entry highlights its note, clicking it again removes the highlight.
+fn handle_entry_click(
+    entries: Query<(&Interaction, &RollEntry), Changed<Interaction>>,
+    mut highlighted: ResMut<Highlighted>,
+) {
+    for (interaction, entry) in &entries {
+        if *interaction == Interaction::Pressed {
+            highlighted.0 = if highlighted.0 == Some(entry.note) {
+                None
+            } else {
+                Some(entry.note)
+            };
+        }
+    }
+}
+
+fn forget_despawned(mut highlighted: ResMut<Highlighted>, entities: Query<Entity>) {
+    if let Some(note) = highlighted.0
+        && !entities.contains(note)
+    {
+        highlighted.0 = None;
+    }
+}
+
+/// Mark all entries of the highlighted note.
+fn update_entries(
+    highlighted: Res<Highlighted>,
+    mut entries: Query<(&RollEntry, &mut BorderColor)>,
+) {
+    for (entry, mut border) in &mut entries {
+        border.set_if_neq(BorderColor(if highlighted.0 == Some(entry.note) {
+            Color::WHITE
+        } else {
+            Color::NONE
+        }));
+    }
+}
+
+fn draw_highlight(
+    highlighted: Res<Highlighted>,
+    transforms: Query<&Transform>,
+    mut gizmos: Gizmos,
+) {
+    let Some(transform) = highlighted.0.and_then(|it| transforms.get(it).ok()) else {
+        return;
+    };
+    let position = Isometry2d::from_translation(transform.translation.xy());
+    gizmos
+        .circle_2d(position, 20.0, Color::WHITE)
+        .resolution(32);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::music::model::{NaturalMinorScale, Note};
+
+    fn pitch(note: Note, octave: i32) -> Pitch {
+        Pitch { note, octave }
+    }
+
+    #[test]
+    fn test_rows_follow_the_pitches() {
+        let scale = NaturalMinorScale::new(Note::A);
+        let chord = [pitch(Note::A, 0), pitch(Note::C, 0), pitch(Note::E, 0)];
+        let rows = RollRows::new(&scale, [Some(&chord[..])]);
+
+        // the chord only plays notes of the scale
+        assert_eq!(rows.len(), 7);
+        assert_eq!(
+            rows.place(Some(&chord), 0.0, None, 1.0, 4.0).rows,
+            vec![0, 2, 4]
+        );
+    }
+
+    #[test]
+    fn test_shifted_pitches_get_their_own_row() {
+        let scale = NaturalMinorScale::new(Note::A);
+        let shifted = [pitch(Note::As, 0)];
+        let higher = [pitch(Note::A, 1)];
+        let rows = RollRows::new(&scale, [Some(&shifted[..]), Some(&higher[..])]);
+
+        assert_eq!(rows.len(), 9);
+        assert_eq!(
+            rows.place(Some(&shifted), 0.0, None, 1.0, 4.0).rows,
+            vec![1]
+        );
+        assert_eq!(rows.place(Some(&higher), 0.0, None, 1.0, 4.0).rows, vec![8]);
+        assert_eq!(rows.names().last(), Some("A+1".to_string()));
+    }
+
+    #[test]
+    fn test_percussion_is_placed_below_the_pitches() {
+        let scale = NaturalMinorScale::new(Note::A);
+        let note = [pitch(Note::A, 0)];
+        let rows = RollRows::new(&scale, [None, Some(&note[..])]);
+
+        assert_eq!(rows.len(), 8);
+        assert_eq!(rows.names().next(), Some("Drum".to_string()));
+        assert_eq!(rows.place(None, 0.0, None, 1.0, 4.0).rows, vec![0]);
+        assert_eq!(rows.place(Some(&note), 0.0, None, 1.0, 4.0).rows, vec![1]);
+    }
+
+    #[test]
+    fn test_notes_with_a_length_span_it() {
+        let scale = NaturalMinorScale::new(Note::A);
+        let rows = RollRows::new(&scale, [None]);
+
+        // two beats of half a second in a prediction of four seconds
+        let placement = rows.place(None, 1.0, Some(2.0), 0.5, 4.0);
+        assert_eq!(placement.left, 25.0);
+        assert_eq!(placement.width, Some(25.0));
+
+        let placement = rows.place(None, 3.0, None, 0.5, 4.0);
+        assert_eq!(placement.left, 75.0);
+        assert_eq!(placement.width, None);
+    }
+}
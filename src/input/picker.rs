@@ -1,49 +1,84 @@
-use crate::core::game::CoreAssets;
-use crate::core::model::{ActivatorColor, ActivatorType, Note};
+use crate::core::game::LevelConfig;
+use crate::core::model::ActivatorType;
+use crate::core::object::{ObjectItem, ObjectItems, spawn_item};
+use crate::input::action::{Action, ActionState};
+use crate::level::creative_mode::CreativeModeState;
+use crate::level::script::ScriptPlaced;
 use crate::state::GameState;
-use crate::visual::color::ColorPalette;
-use bevy::input::mouse::MouseButtonInput;
+use bevy::platform::collections::HashMap;
 use bevy::prelude::*;
-use bevy_svg::prelude::{Origin, Svg2d};
-use std::ops::Add;
+use bevy::window::PrimaryWindow;
 
 pub(super) struct PickerPlugin;
 
 impl Plugin for PickerPlugin {
     fn build(&self, app: &mut App) {
-        app.add_event::<PlaceObjectEvent>()
+        app.init_resource::<Inventory>()
+            .add_event::<PlaceObjectEvent>()
             .add_event::<DeleteObjectEvent>()
+            .add_systems(OnEnter(GameState::SetupGameObjects), setup_inventory)
             .add_systems(
                 Update,
                 (handle_mouse_input, place_object, delete_object, clear)
-                    .run_if(in_state(GameState::Build)),
+                    // objects can also be placed while the execution is running
+                    .run_if(in_state(CreativeModeState::On)),
             );
     }
 }
 
+/// Marks an object placed by the player with the item it was placed as.
 #[derive(Component)]
-pub(super) struct ManuallyPlaced;
+pub(super) struct ManuallyPlaced(pub(super) SelectedItem);
 
-#[derive(Component, PartialEq, Debug, Copy, Clone)]
-pub(super) enum SelectedItem {
-    Activator,
-    Note,
-}
+/// An item of the toolbar by the name it was registered with.
+#[derive(Component, PartialEq, Eq, Hash, Debug, Copy, Clone)]
+pub struct SelectedItem(pub(super) &'static str);
 
 impl SelectedItem {
-    pub(super) fn switch(&self) -> SelectedItem {
-        match self {
-            SelectedItem::Activator => SelectedItem::Note,
-            SelectedItem::Note => SelectedItem::Activator,
-        }
+    /// The item after this one in the order of the toolbar.
+    pub(super) fn switch(&self, items: &ObjectItems) -> SelectedItem {
+        let toolbar: Vec<&ObjectItem> = items.toolbar().collect();
+        let index = toolbar
+            .iter()
+            .position(|it| it.name == self.0)
+            .unwrap_or_default();
+        SelectedItem(toolbar[(index + 1) % toolbar.len()].name)
+    }
+}
+
+/// How many objects of each item can be placed in a level. Items without a limit can be placed
+/// as often as the player likes.
+#[derive(Resource, Default, Debug)]
+pub struct Inventory(HashMap<SelectedItem, u32>);
+
+impl Inventory {
+    pub fn unlimited() -> Self {
+        Inventory::default()
     }
 
-    pub(super) fn name(&self) -> String {
-        match self {
-            SelectedItem::Activator => "Activator".to_string(),
-            SelectedItem::Note => "Note".to_string(),
-        }
+    /// Allow only the given number of objects of the item.
+    pub fn with_limit(mut self, item: &'static str, limit: u32) -> Self {
+        self.0.insert(SelectedItem(item), limit);
+        self
     }
+
+    /// The number of objects of the item which can still be placed, `None` if unlimited.
+    pub(super) fn remaining(&self, item: SelectedItem, placed: usize) -> Option<u32> {
+        self.0
+            .get(&item)
+            .map(|limit| limit.saturating_sub(placed as u32))
+    }
+}
+
+/// Every level starts with the items its config allows.
+fn setup_inventory(config: Res<LevelConfig>, mut commands: Commands) {
+    let inventory = config
+        .item_limits
+        .iter()
+        .fold(Inventory::unlimited(), |inventory, (item, limit)| {
+            inventory.with_limit(item, *limit)
+        });
+    commands.insert_resource(inventory);
 }
 
 #[derive(Component, PartialEq, Deref, Debug)]
@@ -56,17 +91,19 @@ impl InputTimer {
 }
 
 #[derive(Event, Debug)]
-struct PlaceObjectEvent(Vec2);
+pub(super) struct PlaceObjectEvent(pub(super) Vec2);
 
 #[derive(Event, Debug)]
-struct DeleteObjectEvent(Vec2);
+pub(super) struct DeleteObjectEvent(pub(super) Vec2);
 
 fn handle_mouse_input(
     // input backoff
     time: Res<Time>,
     mut timer: Query<(Entity, &mut InputTimer)>,
     // calculate mouse position
-    mut mouse_button_input_events: EventReader<MouseButtonInput>,
+    actions: Res<ActionState>,
+    ui: Query<&Interaction>,
+    primary_window: Query<Entity, With<PrimaryWindow>>,
     window: Query<&Window>,
     camera: Query<(&Camera, &GlobalTransform)>,
     // for interaction
@@ -85,27 +122,26 @@ fn handle_mouse_input(
         }
     }
 
-    // always execute just one event as another check besides the timer
-    let mut events = Vec::new();
-    for event in mouse_button_input_events.read() {
-        events.push(event);
+    // always execute just one action as another check besides the timer
+    let place = actions.just_pressed(Action::Place);
+    let delete = actions.just_pressed(Action::Delete);
+    if !place && !delete {
+        return;
     }
-
-    if events.is_empty() {
+    if ui.iter().any(|it| it != &Interaction::None) {
+        // the click belongs to the UI
         return;
     }
-    let event = events[0];
+    let Ok(primary_window) = primary_window.single() else {
+        return;
+    };
 
     // handle the input
-    if let Ok(position) = cursor_to_world(window, camera, event.window) {
-        match event.button {
-            MouseButton::Left => {
-                place_object.write(PlaceObjectEvent(position));
-            }
-            MouseButton::Right => {
-                delete_object.write(DeleteObjectEvent(position));
-            }
-            _ => {}
+    if let Ok(position) = cursor_to_world(window, camera, primary_window) {
+        if place {
+            place_object.write(PlaceObjectEvent(position));
+        } else {
+            delete_object.write(DeleteObjectEvent(position));
         }
     }
 
@@ -116,17 +152,17 @@ fn handle_mouse_input(
 fn delete_object(
     mut events: EventReader<DeleteObjectEvent>,
     mut commands: Commands,
-    objects: Query<(Entity, &Transform), With<ManuallyPlaced>>,
+    objects: Query<(Entity, &Transform), Or<(With<ManuallyPlaced>, With<ScriptPlaced>)>>,
     main_activator: Query<&ActivatorType>,
 ) {
     for event in events.read() {
         for object in &objects {
             if object.1.translation.xy().distance(event.0) < 10.0 {
-                if let Ok(activator) = main_activator.get(object.0) {
-                    if activator == &ActivatorType::Main {
-                        // main activator cannot be removed
-                        continue;
-                    }
+                if let Ok(activator) = main_activator.get(object.0)
+                    && activator == &ActivatorType::Main
+                {
+                    // main activator cannot be removed
+                    continue;
                 }
 
                 commands.entity(object.0).despawn();
@@ -139,43 +175,32 @@ fn place_object(
     mut events: EventReader<PlaceObjectEvent>,
     mut commands: Commands,
     selected_item: Query<&SelectedItem>,
-    assets: Res<CoreAssets>,
+    placed: Query<&ManuallyPlaced>,
+    items: Res<ObjectItems>,
+    inventory: Res<Inventory>,
 ) {
     for event in events.read() {
-        let world_position = event.0;
-        let item = selected_item.single().expect("SelectedItem must exist");
-
-        match item {
-            SelectedItem::Activator => {
-                let color = ColorPalette::get_random(world_position);
-
-                commands.spawn((
-                    Name::new(item.name().add(" manual")),
-                    ManuallyPlaced,
-                    ActivatorType::Passive,
-                    ActivatorColor(color),
-                    Transform::from_translation(world_position.extend(0.0))
-                        .with_scale(Vec3::splat(0.05)),
-                    Svg2d(assets.activator_icon_play.clone()),
-                    Origin::Center,
-                ));
-            }
-            SelectedItem::Note => {
-                commands.spawn((
-                    Name::new(item.name()),
-                    ManuallyPlaced,
-                    Note,
-                    Transform::from_translation(world_position.extend(0.0))
-                        .with_scale(Vec3::splat(0.025)),
-                    Svg2d(assets.note_icon.clone()),
-                    Origin::Center,
-                ));
-            }
+        let position = event.0;
+        let selected = *selected_item.single().expect("SelectedItem must exist");
+
+        let placed_of_item = placed.iter().filter(|it| it.0 == selected).count();
+        if inventory.remaining(selected, placed_of_item) == Some(0) {
+            // nothing left in the inventory
+            continue;
         }
+
+        // the type of the item spawns the object
+        let Some(item) = items.get(selected.0).copied() else {
+            continue;
+        };
+        commands.queue(move |world: &mut World| {
+            let object = spawn_item(world, &item, position);
+            world.entity_mut(object).insert(ManuallyPlaced(selected));
+        });
     }
 }
 
-fn cursor_to_world(
+pub(super) fn cursor_to_world(
     windows: Query<&Window>,
     camera: Query<(&Camera, &GlobalTransform)>,
     window: Entity,
@@ -196,12 +221,68 @@ fn cursor_to_world(
 
 fn clear(
     mut commands: Commands,
-    entities: Query<Entity, With<ManuallyPlaced>>,
-    input: Res<ButtonInput<KeyCode>>,
+    entities: Query<Entity, Or<(With<ManuallyPlaced>, With<ScriptPlaced>)>>,
+    actions: Res<ActionState>,
 ) {
-    if input.just_pressed(KeyCode::Backspace) {
+    if actions.just_pressed(Action::Clear) {
         for entity in entities.iter() {
             commands.entity(entity).despawn();
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::game::Looping;
+    use crate::core::object::{ObjectAppExt, ObjectType};
+    use crate::music::model::{NaturalMinorScale, Note};
+    use bevy::ecs::system::RunSystemOnce;
+
+    #[derive(Component)]
+    struct Bell;
+
+    #[test]
+    fn test_placing_stops_at_the_limit() {
+        let mut app = App::new();
+        app.add_event::<PlaceObjectEvent>()
+            .insert_resource(LevelConfig {
+                grow_factor: 100.0,
+                scale: Box::new(NaturalMinorScale::new(Note::A)),
+                bpm: 60.0,
+                release: 0.1,
+                looping: Looping {
+                    enabled: false,
+                    sync_to_background: false,
+                },
+                script: None,
+                item_limits: vec![("Bell", 2)],
+            })
+            .register_object(ObjectType::<Bell>::new().item(
+                "Bell",
+                |assets| assets.note_icon.clone(),
+                |entity| {
+                    entity.insert(Bell);
+                },
+            ))
+            .add_systems(Update, place_object);
+        app.world_mut().run_system_once(setup_inventory).unwrap();
+        app.world_mut().spawn(SelectedItem("Bell"));
+
+        for x in 0..3 {
+            app.world_mut()
+                .send_event(PlaceObjectEvent(Vec2::new(x as f32 * 50.0, 0.0)));
+            app.update();
+        }
+
+        let world = app.world_mut();
+        let placed = world
+            .query_filtered::<&ManuallyPlaced, With<Bell>>()
+            .iter(world)
+            .count();
+        assert_eq!(2, placed);
+        let inventory = world.resource::<Inventory>();
+        assert_eq!(Some(0), inventory.remaining(SelectedItem("Bell"), placed));
+        assert_eq!(None, inventory.remaining(SelectedItem("Gong"), placed));
+    }
+}
@@ -0,0 +1,87 @@
+//! Save the objects the player placed into a level file and load them again. Every object is
+//! written with the save hook of its registered type, see [`crate::core::object`].
+
+use crate::core::object::{load_objects, save_objects};
+use crate::input::action::{Action, ActionState};
+use crate::input::picker::{ManuallyPlaced, SelectedItem};
+use crate::state::GameState;
+use bevy::prelude::*;
+
+pub(super) struct SavePlugin;
+
+impl Plugin for SavePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            Update,
+            (save_level, load_level).run_if(in_state(GameState::Build)),
+        );
+    }
+}
+
+#[cfg(not(target_family = "wasm"))]
+const LEVEL_PATH: &str = "level.ron";
+
+fn save_level(world: &mut World) -> Result {
+    if !world
+        .resource::<ActionState>()
+        .just_pressed(Action::SaveLevel)
+    {
+        return Ok(());
+    }
+
+    let mut placed = world.query::<(Entity, &ManuallyPlaced)>();
+    let objects: Vec<(Entity, &'static str)> = placed
+        .iter(world)
+        .map(|(entity, placed)| (entity, placed.0.0))
+        .collect();
+    write_level(&save_objects(world, objects)?)
+}
+
+fn load_level(world: &mut World) -> Result {
+    if !world
+        .resource::<ActionState>()
+        .just_pressed(Action::LoadLevel)
+    {
+        return Ok(());
+    }
+    let Some(content) = read_level() else {
+        return Ok(());
+    };
+
+    let mut placed = world.query_filtered::<Entity, With<ManuallyPlaced>>();
+    let previous: Vec<Entity> = placed.iter(world).collect();
+    for entity in previous {
+        world.despawn(entity);
+    }
+    for (entity, item) in load_objects(world, &content)? {
+        world
+            .entity_mut(entity)
+            .insert(ManuallyPlaced(SelectedItem(item)));
+    }
+    Ok(())
+}
+
+#[cfg(target_family = "wasm")]
+fn write_level(_content: &str) -> Result {
+    // there is no level file in the browser
+    Ok(())
+}
+
+#[cfg(not(target_family = "wasm"))]
+fn write_level(content: &str) -> Result {
+    std::fs::write(LEVEL_PATH, content)?;
+    info!("level saved to {LEVEL_PATH}");
+    Ok(())
+}
+
+#[cfg(target_family = "wasm")]
+fn read_level() -> Option<String> {
+    None
+}
+
+#[cfg(not(target_family = "wasm"))]
+fn read_level() -> Option<String> {
+    std::fs::read_to_string(LEVEL_PATH)
+        .inspect_err(|err| warn!("could not read the level from {LEVEL_PATH}: {err}"))
+        .ok()
+}
@@ -0,0 +1,195 @@
+//! Timeline of the execution. It shows the predicted notes and other hit objects as ticks and a
+//! playhead at the current time. Pressing the bar pauses the execution and scrubs to the time under
+//! the cursor, clicking a tick plays its note.
+
+use crate::core::game::{ExecutionClock, ScrubEvent};
+use crate::core::model::ActivatorColor;
+use crate::core::prediction::{ObjectKind, Prediction};
+use crate::music::game::AuditionNoteEvent;
+use crate::state::{ExecutionState, GameState};
+use bevy::prelude::*;
+use bevy::ui::{FocusPolicy, RelativeCursorPosition};
+
+pub(super) struct TimelinePlugin;
+
+impl Plugin for TimelinePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(OnEnter(GameState::Execute), setup)
+            .add_systems(OnExit(GameState::Execute), cleanup)
+            .add_systems(
+                Update,
+                (
+                    rebuild_ticks,
+                    handle_scrubbing,
+                    handle_tick_click,
+                    update_playhead,
+                )
+                    .chain()
+                    .run_if(in_state(GameState::Execute)),
+            );
+    }
+}
+
+/// Height of the bar in logical pixels.
+const HEIGHT: f32 = 40.0;
+/// Width of a tick in logical pixels.
+const TICK_WIDTH: f32 = 4.0;
+
+#[derive(Component)]
+struct Timeline;
+
+/// A predicted note.
+#[derive(Component)]
+struct TimelineTick {
+    source: Entity,
+    note: Entity,
+}
+
+#[derive(Component)]
+struct Playhead;
+
+fn setup(mut commands: Commands) {
+    commands.spawn((
+        Name::new("Timeline"),
+        Timeline,
+        Interaction::default(),
+        RelativeCursorPosition::default(),
+        Node {
+            position_type: PositionType::Absolute,
+            left: Val::Px(20.0),
+            // leave space for the play button
+            right: Val::Px(240.0),
+            bottom: Val::Px(20.0),
+            height: Val::Px(HEIGHT),
+            ..default()
+        },
+        BackgroundColor(Color::srgba(1.0, 1.0, 1.0, 0.15)),
+        BorderRadius::all(Val::Px(4.0)),
+        children![(
+            Playhead,
+            Node {
+                position_type: PositionType::Absolute,
+                width: Val::Px(2.0),
+                height: Val::Percent(100.0),
+                ..default()
+            },
+            BackgroundColor(Color::WHITE),
+            // do not block the bar below
+            FocusPolicy::Pass,
+        )],
+    ));
+}
+
+fn cleanup(mut commands: Commands, timeline: Query<Entity, With<Timeline>>) {
+    for entity in &timeline {
+        commands.entity(entity).despawn();
+    }
+}
+
+/// Show a tick for every predicted hit, except the ones of activators.
+fn rebuild_ticks(
+    prediction: Res<Prediction>,
+    timeline: Query<(Entity, Ref<Timeline>)>,
+    ticks: Query<Entity, With<TimelineTick>>,
+    colors: Query<&ActivatorColor>,
+    mut commands: Commands,
+) {
+    let Ok((timeline, added)) = timeline.single() else {
+        return;
+    };
+    if !prediction.is_changed() && !added.is_added() {
+        return;
+    }
+
+    for tick in &ticks {
+        commands.entity(tick).despawn();
+    }
+    if prediction.duration <= 0.0 {
+        return;
+    }
+
+    for event in &prediction.events {
+        if let ObjectKind::Activator { .. } = event.kind {
+            continue;
+        }
+        let color = colors
+            .get(event.source)
+            .map(Color::from)
+            .unwrap_or(Color::WHITE);
+        commands.spawn((
+            TimelineTick {
+                source: event.source,
+                note: event.target,
+            },
+            Button,
+            Node {
+                position_type: PositionType::Absolute,
+                left: Val::Percent(event.time / prediction.duration * 100.0),
+                top: Val::Percent(20.0),
+                width: Val::Px(TICK_WIDTH),
+                height: Val::Percent(60.0),
+                margin: UiRect::left(Val::Px(-TICK_WIDTH / 2.0)),
+                ..default()
+            },
+            BackgroundColor(color),
+            ChildOf(timeline),
+        ));
+    }
+}
+
+/// While the bar is pressed, the execution is paused at the time under the cursor.
+fn handle_scrubbing(
+    timeline: Query<(&Interaction, &RelativeCursorPosition), With<Timeline>>,
+    prediction: Res<Prediction>,
+    mut scrubbed: Local<Option<f32>>,
+    mut next_state: ResMut<NextState<ExecutionState>>,
+    mut scrub: EventWriter<ScrubEvent>,
+) {
+    let Ok((interaction, cursor)) = timeline.single() else {
+        return;
+    };
+    if *interaction != Interaction::Pressed {
+        *scrubbed = None;
+        return;
+    }
+    let Some(position) = cursor.normalized else {
+        return;
+    };
+
+    let time = position.x.clamp(0.0, 1.0) * prediction.duration;
+    if *scrubbed == Some(time) {
+        return;
+    }
+    *scrubbed = Some(time);
+    next_state.set(ExecutionState::Paused);
+    scrub.write(ScrubEvent(time));
+}
+
+fn handle_tick_click(
+    ticks: Query<(&Interaction, &TimelineTick), Changed<Interaction>>,
+    mut audition: EventWriter<AuditionNoteEvent>,
+) {
+    for (interaction, tick) in &ticks {
+        if *interaction == Interaction::Pressed {
+            audition.write(AuditionNoteEvent {
+                source: tick.source,
+                note: tick.note,
+            });
+        }
+    }
+}
+
+fn update_playhead(
+    clock: Res<ExecutionClock>,
+    prediction: Res<Prediction>,
+    mut playhead: Query<&mut Node, With<Playhead>>,
+) {
+    let progress = if prediction.duration > 0.0 {
+        (clock.elapsed().as_secs_f32() / prediction.duration).min(1.0)
+    } else {
+        0.0
+    };
+    for mut node in &mut playhead {
+        node.left = Val::Percent(progress * 100.0);
+    }
+}
@@ -0,0 +1,300 @@
+//! Toolbar with one slot per placeable item. A slot shows the icon of the item, its hotkey and how
+//! many objects of it are left in the [`Inventory`].
+
+use crate::core::game::CoreAssets;
+use crate::core::object::ObjectItems;
+use crate::input::action::{Action, ActionMap, ActionState};
+use crate::input::picker::{Inventory, ManuallyPlaced, SelectedItem};
+use crate::state::{GameState, MinimalGameState};
+use bevy::prelude::*;
+use bevy_svg::prelude::{Origin, Svg2d};
+
+pub(super) struct ToolbarPlugin;
+
+impl Plugin for ToolbarPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(OnEnter(GameState::SetupGameObjects), setup)
+            .add_systems(
+                Update,
+                (
+                    handle_item_switch_input,
+                    handle_slot_click,
+                    update_slots,
+                    update_labels,
+                    update_icons,
+                )
+                    .chain()
+                    .run_if(in_state(MinimalGameState::Running)),
+            );
+    }
+}
+
+/// Size of a slot in logical pixels.
+const SLOT_SIZE: f32 = 56.0;
+/// Size of an icon in logical pixels.
+const ICON_SIZE: f32 = 32.0;
+/// All icons have a view box of 512 x 512.
+const ICON_VIEW_BOX: f32 = 512.0;
+/// Draw the icons above the placed objects.
+const ICON_Z: f32 = 10.0;
+
+/// The toolbar holds the currently [`SelectedItem`].
+#[derive(Component)]
+struct Toolbar;
+
+#[derive(Component, Deref)]
+struct ToolbarSlot(SelectedItem);
+
+#[derive(Component)]
+struct HotkeyLabel(Action);
+
+#[derive(Component, Deref)]
+struct CountLabel(SelectedItem);
+
+/// The UI cannot render SVGs, so the icon of a slot is drawn in the world above the slot.
+#[derive(Component)]
+struct ToolbarIcon {
+    slot: Entity,
+}
+
+fn setup(mut commands: Commands, items: Res<ObjectItems>, assets: Res<CoreAssets>) {
+    let toolbar = commands
+        .spawn((
+            Name::new("Toolbar"),
+            Toolbar,
+            SelectedItem(
+                items
+                    .toolbar()
+                    .next()
+                    .expect("an item must be registered")
+                    .name,
+            ),
+            // block clicks between the slots
+            Interaction::default(),
+            Node {
+                flex_direction: FlexDirection::Row,
+                flex_wrap: FlexWrap::Wrap,
+                max_width: Val::Percent(60.0),
+                margin: UiRect::all(Val::Px(10.0)),
+                column_gap: Val::Px(8.0),
+                row_gap: Val::Px(8.0),
+                align_self: AlignSelf::FlexStart,
+                ..default()
+            },
+        ))
+        .id();
+
+    for (index, item) in items.toolbar().enumerate() {
+        let slot = commands
+            .spawn((
+                Name::new(format!("Toolbar Slot {}", item.name)),
+                ToolbarSlot(SelectedItem(item.name)),
+                Button,
+                Node {
+                    width: Val::Px(SLOT_SIZE),
+                    height: Val::Px(SLOT_SIZE),
+                    border: UiRect::all(Val::Px(3.0)),
+                    ..default()
+                },
+                BorderColor(Color::NONE),
+                BorderRadius::all(Val::Px(10.0)),
+                ChildOf(toolbar),
+            ))
+            .with_children(|slot| {
+                // the slots after the hotkeys are only selected by clicking or switching
+                if let Some(action) = Action::select_slot(index) {
+                    slot.spawn((
+                        HotkeyLabel(action),
+                        Node {
+                            position_type: PositionType::Absolute,
+                            top: Val::Px(2.0),
+                            left: Val::Px(4.0),
+                            ..default()
+                        },
+                        Text::default(),
+                        TextFont {
+                            font_size: 12.0,
+                            ..default()
+                        },
+                        TextColor(Color::WHITE),
+                    ));
+                }
+                slot.spawn((
+                    CountLabel(SelectedItem(item.name)),
+                    Node {
+                        position_type: PositionType::Absolute,
+                        bottom: Val::Px(2.0),
+                        right: Val::Px(4.0),
+                        ..default()
+                    },
+                    Text::default(),
+                    TextFont {
+                        font_size: 14.0,
+                        ..default()
+                    },
+                    TextColor(Color::WHITE),
+                ));
+            })
+            .id();
+
+        commands.spawn((
+            Name::new(format!("Toolbar Icon {}", item.name)),
+            ToolbarIcon { slot },
+            Svg2d(item.icon.map(|icon| icon(&assets)).unwrap_or_default()),
+            Origin::Center,
+            Transform::default(),
+            // hidden until the slot has a layout
+            Visibility::Hidden,
+        ));
+    }
+}
+
+fn handle_item_switch_input(
+    mut toolbar: Query<&mut SelectedItem, With<Toolbar>>,
+    items: Res<ObjectItems>,
+    actions: Res<ActionState>,
+) {
+    let Ok(mut selected) = toolbar.single_mut() else {
+        return;
+    };
+
+    if actions.just_pressed(Action::SwitchItem) {
+        *selected = selected.switch(&items);
+        return;
+    }
+
+    let hotkey = items.toolbar().enumerate().find(|(index, _)| {
+        Action::select_slot(*index).is_some_and(|action| actions.just_pressed(action))
+    });
+    if let Some((_, item)) = hotkey {
+        *selected = SelectedItem(item.name);
+    }
+}
+
+fn handle_slot_click(
+    slots: Query<(&Interaction, &ToolbarSlot), Changed<Interaction>>,
+    mut toolbar: Query<&mut SelectedItem, With<Toolbar>>,
+) {
+    let Ok(mut selected) = toolbar.single_mut() else {
+        return;
+    };
+
+    for (interaction, slot) in &slots {
+        if *interaction == Interaction::Pressed {
+            *selected = **slot;
+        }
+    }
+}
+
+/// Highlight the selected and the hovered slot.
+fn update_slots(
+    toolbar: Query<&SelectedItem, With<Toolbar>>,
+    mut slots: Query<(&ToolbarSlot, &Interaction, &mut BorderColor)>,
+) {
+    let Ok(selected) = toolbar.single() else {
+        return;
+    };
+
+    for (slot, interaction, mut border) in &mut slots {
+        border.set_if_neq(BorderColor(if **slot == *selected {
+            Color::WHITE
+        } else if *interaction != Interaction::None {
+            Color::srgba(1.0, 1.0, 1.0, 0.4)
+        } else {
+            Color::NONE
+        }));
+    }
+}
+
+fn update_labels(
+    map: Res<ActionMap>,
+    inventory: Res<Inventory>,
+    placed: Query<&ManuallyPlaced>,
+    mut hotkeys: Query<(&HotkeyLabel, &mut Text), Without<CountLabel>>,
+    mut counts: Query<(&CountLabel, &mut Text), Without<HotkeyLabel>>,
+) {
+    for (label, mut text) in &mut hotkeys {
+        let hotkey = map
+            .get(label.0)
+            .first()
+            .map(|it| it.short_name())
+            .unwrap_or_default();
+        text.set_if_neq(Text(hotkey));
+    }
+
+    for (label, mut text) in &mut counts {
+        let placed_of_item = placed.iter().filter(|it| it.0 == **label).count();
+        let count = inventory
+            .remaining(**label, placed_of_item)
+            .map(|it| it.to_string())
+            .unwrap_or_default();
+        text.set_if_neq(Text(count));
+    }
+}
+
+/// Move the icons to the screen position of their slot.
+fn update_icons(
+    mut icons: Query<(&ToolbarIcon, &mut Transform, &mut Visibility)>,
+    slots: Query<(&GlobalTransform, &ComputedNode)>,
+    camera: Query<(&Camera, &GlobalTransform)>,
+) {
+    let Ok((camera, camera_transform)) = camera.single() else {
+        return;
+    };
+
+    for (icon, mut transform, mut visibility) in &mut icons {
+        let Ok((slot, node)) = slots.get(icon.slot) else {
+            continue;
+        };
+        // the UI layout is in physical pixels, the camera expects logical ones
+        let center = slot.translation().xy() * node.inverse_scale_factor;
+
+        let (Ok(position), Ok(next_pixel)) = (
+            camera.viewport_to_world_2d(camera_transform, center),
+            camera.viewport_to_world_2d(camera_transform, center + Vec2::X),
+        ) else {
+            continue;
+        };
+
+        transform.translation = position.extend(ICON_Z);
+        transform.scale = Vec3::splat(ICON_SIZE / ICON_VIEW_BOX * position.distance(next_pixel));
+        *visibility = Visibility::Inherited;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::game::CoreGamePlugin;
+    use crate::core::object::{ObjectAppExt, ObjectType};
+    use bevy::ecs::system::RunSystemOnce;
+
+    /// A type with more items than there are hotkeys left.
+    #[derive(Component)]
+    struct Bell;
+
+    #[test]
+    fn test_items_after_the_hotkeys_get_a_slot() {
+        let mut app = App::new();
+        app.insert_resource(CoreAssets::empty())
+            .add_plugins(CoreGamePlugin)
+            .register_object(
+                ObjectType::<Bell>::new()
+                    .item("Small Bell", |assets| assets.note_icon.clone(), |_| {})
+                    .item("Large Bell", |assets| assets.note_icon.clone(), |_| {}),
+            );
+        let world = app.world_mut();
+        let items = world.resource::<ObjectItems>().toolbar().count();
+        world.run_system_once(setup).unwrap();
+
+        let slots = world.query::<&ToolbarSlot>().iter(world).count();
+        let hotkeys: Vec<Action> = world
+            .query::<&HotkeyLabel>()
+            .iter(world)
+            .map(|it| it.0)
+            .collect();
+        assert_eq!(items, slots);
+        assert!(hotkeys.len() < items);
+        assert!(hotkeys.iter().all(|it| Action::enumerate().contains(it)));
+    }
+}
@@ -0,0 +1,493 @@
+//! # Touch
+//!
+//! Touch input for phones and tablets: tap to place, long-press to delete and two fingers to pan
+//! and zoom the camera. Since there is no space key, an on-screen button starts and stops the
+//! execution.
+
+use crate::core::game::{ExecutionClock, LevelConfig, Looping};
+use crate::input::action::{Action, ActionState};
+use crate::input::picker::{DeleteObjectEvent, PlaceObjectEvent};
+use crate::level::creative_mode::CreativeModeState;
+use crate::state::{ExecutionState, GameState, MinimalGameState};
+use bevy::input::touch::{TouchInput, TouchPhase};
+use bevy::platform::collections::HashMap;
+use bevy::prelude::*;
+use std::time::Duration;
+
+pub(super) struct TouchPlugin;
+
+impl Plugin for TouchPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<GestureRecognizer>()
+            .add_systems(OnEnter(GameState::SetupGameObjects), setup_play_button)
+            .add_systems(
+                Update,
+                (handle_touch_input, handle_play_button, update_play_button)
+                    .run_if(in_state(MinimalGameState::Running)),
+            );
+    }
+}
+
+/// A touch has to be held this long to be a long-press.
+const LONG_PRESS: Duration = Duration::from_millis(500);
+/// A touch moving further than this (in logical pixels) is not a tap anymore.
+const TAP_SLOP: f32 = 10.0;
+const MIN_ZOOM: f32 = 0.5;
+const MAX_ZOOM: f32 = 3.0;
+
+/// Gestures in window coordinates.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Gesture {
+    Tap(Vec2),
+    LongPress(Vec2),
+    /// two fingers moved by the delta
+    Pan(Vec2),
+    /// the distance between two fingers changed by the factor
+    Zoom(f32),
+}
+
+#[derive(Debug, Clone)]
+struct TrackedTouch {
+    start: Vec2,
+    position: Vec2,
+    started_at: Duration,
+    /// moved, long-pressed or part of a multi touch, so it cannot be a tap anymore
+    consumed: bool,
+}
+
+/// Turns the raw [`TouchInput`] events into [`Gesture`]s.
+#[derive(Resource, Default, Debug)]
+struct GestureRecognizer {
+    touches: HashMap<u64, TrackedTouch>,
+    /// ids of touches which started on the UI
+    ignored: Vec<u64>,
+}
+
+impl GestureRecognizer {
+    fn handle(&mut self, event: &TouchInput, now: Duration) -> Vec<Gesture> {
+        if self.ignored.contains(&event.id) {
+            if matches!(event.phase, TouchPhase::Ended | TouchPhase::Canceled) {
+                self.ignored.retain(|it| *it != event.id);
+            }
+            return vec![];
+        }
+
+        match event.phase {
+            TouchPhase::Started => {
+                let multi_touch = !self.touches.is_empty();
+                for touch in self.touches.values_mut() {
+                    touch.consumed = true;
+                }
+                self.touches.insert(
+                    event.id,
+                    TrackedTouch {
+                        start: event.position,
+                        position: event.position,
+                        started_at: now,
+                        consumed: multi_touch,
+                    },
+                );
+                vec![]
+            }
+            TouchPhase::Moved => {
+                let before = self.pinch();
+                let Some(touch) = self.touches.get_mut(&event.id) else {
+                    return vec![];
+                };
+                touch.position = event.position;
+                if touch.start.distance(event.position) > TAP_SLOP {
+                    touch.consumed = true;
+                }
+
+                match (before, self.pinch()) {
+                    (Some((center_before, distance_before)), Some((center, distance))) => {
+                        let mut gestures = vec![Gesture::Pan(center - center_before)];
+                        if distance_before > 0.0 && distance > 0.0 {
+                            gestures.push(Gesture::Zoom(distance / distance_before));
+                        }
+                        gestures
+                    }
+                    _ => vec![],
+                }
+            }
+            TouchPhase::Ended => match self.touches.remove(&event.id) {
+                Some(touch)
+                    if !touch.consumed && touch.start.distance(event.position) <= TAP_SLOP =>
+                {
+                    vec![Gesture::Tap(event.position)]
+                }
+                _ => vec![],
+            },
+            TouchPhase::Canceled => {
+                self.touches.remove(&event.id);
+                vec![]
+            }
+        }
+    }
+
+    /// Check for touches which are held long enough to be a long-press.
+    fn update(&mut self, now: Duration) -> Vec<Gesture> {
+        let mut gestures = vec![];
+        for touch in self.touches.values_mut() {
+            if !touch.consumed && now.saturating_sub(touch.started_at) >= LONG_PRESS {
+                touch.consumed = true;
+                gestures.push(Gesture::LongPress(touch.position));
+            }
+        }
+        gestures
+    }
+
+    /// Ignore all further events of the touch.
+    fn ignore(&mut self, id: u64) {
+        self.touches.remove(&id);
+        self.ignored.push(id);
+    }
+
+    /// Center and distance of the first two touches.
+    fn pinch(&self) -> Option<(Vec2, f32)> {
+        let mut ids: Vec<&u64> = self.touches.keys().collect();
+        ids.sort();
+        let [first, second, ..] = ids.as_slice() else {
+            return None;
+        };
+        let first = self.touches[*first].position;
+        let second = self.touches[*second].position;
+        Some(((first + second) / 2.0, first.distance(second)))
+    }
+}
+
+fn handle_touch_input(
+    mut events: EventReader<TouchInput>,
+    mut recognizer: ResMut<GestureRecognizer>,
+    time: Res<Time>,
+    creative_mode: Res<State<CreativeModeState>>,
+    ui: Query<&Interaction>,
+    mut camera: Query<(&Camera, &GlobalTransform, &mut Transform, &mut Projection)>,
+    mut place_object: EventWriter<PlaceObjectEvent>,
+    mut delete_object: EventWriter<DeleteObjectEvent>,
+) {
+    let now = time.elapsed();
+    let over_ui = ui.iter().any(|it| it != &Interaction::None);
+
+    let mut gestures = vec![];
+    for event in events.read() {
+        if event.phase == TouchPhase::Started && over_ui {
+            // the touch belongs to the UI
+            recognizer.ignore(event.id);
+            continue;
+        }
+        gestures.extend(recognizer.handle(event, now));
+    }
+    gestures.extend(recognizer.update(now));
+    if gestures.is_empty() {
+        return;
+    }
+
+    let Ok((camera, camera_transform, mut transform, mut projection)) = camera.single_mut() else {
+        return;
+    };
+    let Projection::Orthographic(projection) = projection.as_mut() else {
+        return;
+    };
+    let editable = *creative_mode == CreativeModeState::On;
+
+    for gesture in gestures {
+        match gesture {
+            Gesture::Tap(position) if editable => {
+                if let Ok(position) = camera.viewport_to_world_2d(camera_transform, position) {
+                    place_object.write(PlaceObjectEvent(position));
+                }
+            }
+            Gesture::LongPress(position) if editable => {
+                if let Ok(position) = camera.viewport_to_world_2d(camera_transform, position) {
+                    delete_object.write(DeleteObjectEvent(position));
+                }
+            }
+            Gesture::Pan(delta) => {
+                // the window y axis points down, the world y axis up
+                transform.translation.x -= delta.x * projection.scale;
+                transform.translation.y += delta.y * projection.scale;
+            }
+            Gesture::Zoom(factor) => {
+                projection.scale = (projection.scale / factor).clamp(MIN_ZOOM, MAX_ZOOM);
+            }
+            _ => {}
+        }
+    }
+}
+
+#[derive(Component)]
+struct PlayButton;
+
+fn setup_play_button(mut commands: Commands) {
+    commands.spawn((
+        Name::new("Play Button"),
+        PlayButton,
+        Button,
+        Node {
+            position_type: PositionType::Absolute,
+            right: Val::Px(20.0),
+            bottom: Val::Px(20.0),
+            padding: UiRect::axes(Val::Px(24.0), Val::Px(12.0)),
+            ..default()
+        },
+        BackgroundColor(Color::WHITE),
+        BorderRadius::all(Val::Px(10.0)),
+        Text::new("Play"),
+        TextFont {
+            font_size: 24.0,
+            ..default()
+        },
+        TextColor(Color::BLACK),
+    ));
+}
+
+fn handle_play_button(
+    buttons: Query<&Interaction, (Changed<Interaction>, With<PlayButton>)>,
+    mut actions: ResMut<ActionState>,
+) {
+    for interaction in &buttons {
+        if *interaction == Interaction::Pressed {
+            actions.trigger(Action::ToggleExecution);
+        }
+    }
+}
+
+fn update_play_button(
+    state: Res<State<GameState>>,
+    execution: Option<Res<State<ExecutionState>>>,
+    clock: Res<ExecutionClock>,
+    config: Res<LevelConfig>,
+    mut button: Query<&mut Text, With<PlayButton>>,
+) {
+    let label = match (state.get(), config.looping) {
+        (GameState::Execute, _) => {
+            let paused = execution.is_some_and(|it| *it == ExecutionState::Paused);
+            format!(
+                "Stop ({}x{})",
+                clock.rate(),
+                if paused { ", paused" } else { "" }
+            )
+        }
+        (_, Looping { enabled: false, .. }) => "Play".to_string(),
+        (
+            _,
+            Looping {
+                sync_to_background: false,
+                ..
+            },
+        ) => "Play (Loop)".to_string(),
+        _ => "Play (Sync)".to_string(),
+    };
+    for mut text in &mut button {
+        text.set_if_neq(Text::new(label.clone()));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn touch(id: u64, phase: TouchPhase, x: f32, y: f32) -> TouchInput {
+        TouchInput {
+            phase,
+            position: Vec2::new(x, y),
+            window: Entity::PLACEHOLDER,
+            force: None,
+            id,
+        }
+    }
+
+    fn ms(millis: u64) -> Duration {
+        Duration::from_millis(millis)
+    }
+
+    #[test]
+    fn test_tap() {
+        let mut recognizer = GestureRecognizer::default();
+        assert!(
+            recognizer
+                .handle(&touch(1, TouchPhase::Started, 10.0, 10.0), ms(0))
+                .is_empty()
+        );
+        assert!(recognizer.update(ms(100)).is_empty());
+        assert_eq!(
+            vec![Gesture::Tap(Vec2::new(12.0, 10.0))],
+            recognizer.handle(&touch(1, TouchPhase::Ended, 12.0, 10.0), ms(200))
+        );
+    }
+
+    #[test]
+    fn test_moved_touch_is_no_tap() {
+        let mut recognizer = GestureRecognizer::default();
+        recognizer.handle(&touch(1, TouchPhase::Started, 10.0, 10.0), ms(0));
+        recognizer.handle(&touch(1, TouchPhase::Moved, 50.0, 10.0), ms(50));
+        assert!(
+            recognizer
+                .handle(&touch(1, TouchPhase::Ended, 50.0, 10.0), ms(100))
+                .is_empty()
+        );
+    }
+
+    #[test]
+    fn test_long_press() {
+        let mut recognizer = GestureRecognizer::default();
+        recognizer.handle(&touch(1, TouchPhase::Started, 10.0, 10.0), ms(0));
+        assert!(recognizer.update(ms(400)).is_empty());
+        assert_eq!(
+            vec![Gesture::LongPress(Vec2::new(10.0, 10.0))],
+            recognizer.update(ms(600))
+        );
+        // only once and no tap afterwards
+        assert!(recognizer.update(ms(700)).is_empty());
+        assert!(
+            recognizer
+                .handle(&touch(1, TouchPhase::Ended, 10.0, 10.0), ms(800))
+                .is_empty()
+        );
+    }
+
+    #[test]
+    fn test_pan_and_zoom() {
+        let mut recognizer = GestureRecognizer::default();
+        recognizer.handle(&touch(1, TouchPhase::Started, 0.0, 0.0), ms(0));
+        recognizer.handle(&touch(2, TouchPhase::Started, 100.0, 0.0), ms(0));
+
+        assert_eq!(
+            vec![Gesture::Pan(Vec2::new(50.0, 0.0)), Gesture::Zoom(2.0)],
+            recognizer.handle(&touch(2, TouchPhase::Moved, 200.0, 0.0), ms(50))
+        );
+        // neither finger is a tap or long-press
+        assert!(recognizer.update(ms(1000)).is_empty());
+        assert!(
+            recognizer
+                .handle(&touch(1, TouchPhase::Ended, 0.0, 0.0), ms(1000))
+                .is_empty()
+        );
+    }
+
+    #[test]
+    fn test_ignored_touch() {
+        let mut recognizer = GestureRecognizer::default();
+        recognizer.handle(&touch(1, TouchPhase::Started, 0.0, 0.0), ms(0));
+        recognizer.ignore(1);
+        assert!(
+            recognizer
+                .handle(&touch(1, TouchPhase::Ended, 0.0, 0.0), ms(100))
+                .is_empty()
+        );
+        assert!(recognizer.ignored.is_empty());
+    }
+
+    #[test]
+    fn test_touch_events_place_delete_and_move_the_camera() {
+        use bevy::render::camera::{ManualTextureViews, camera_system};
+        use bevy::window::{PrimaryWindow, WindowCreated, WindowResized, WindowScaleFactorChanged};
+
+        let mut app = App::new();
+        app.init_resource::<GestureRecognizer>()
+            .init_resource::<Time>()
+            .init_resource::<Assets<Image>>()
+            .init_resource::<ManualTextureViews>()
+            .insert_resource(State::new(CreativeModeState::On))
+            .add_event::<TouchInput>()
+            .add_event::<PlaceObjectEvent>()
+            .add_event::<DeleteObjectEvent>()
+            .add_event::<WindowCreated>()
+            .add_event::<WindowResized>()
+            .add_event::<WindowScaleFactorChanged>()
+            .add_event::<AssetEvent<Image>>()
+            // the camera system computes the viewport of the camera for the window
+            .add_systems(Update, (camera_system, handle_touch_input).chain());
+        app.world_mut().spawn((Window::default(), PrimaryWindow));
+        app.world_mut().spawn((
+            Camera::default(),
+            Projection::from(OrthographicProjection::default_2d()),
+            Transform::default(),
+            GlobalTransform::default(),
+        ));
+
+        let send = |app: &mut App, events: &[TouchInput], millis: u64| {
+            for event in events {
+                app.world_mut().send_event(*event);
+            }
+            app.world_mut()
+                .resource_mut::<Time>()
+                .advance_by(ms(millis));
+            app.update();
+        };
+        let center = Window::default().size() / 2.0;
+
+        // a tap places right of and above the center
+        send(
+            &mut app,
+            &[touch(
+                1,
+                TouchPhase::Started,
+                center.x + 10.0,
+                center.y - 20.0,
+            )],
+            0,
+        );
+        send(
+            &mut app,
+            &[touch(
+                1,
+                TouchPhase::Ended,
+                center.x + 10.0,
+                center.y - 20.0,
+            )],
+            100,
+        );
+        let placed: Vec<Vec2> = app
+            .world()
+            .resource::<Events<PlaceObjectEvent>>()
+            .iter_current_update_events()
+            .map(|it| it.0)
+            .collect();
+        // the viewport conversion is not exact
+        assert!(matches!(placed[..], [it] if it.abs_diff_eq(Vec2::new(10.0, 20.0), 0.01)));
+
+        // a long-press deletes at the center
+        send(
+            &mut app,
+            &[touch(2, TouchPhase::Started, center.x, center.y)],
+            0,
+        );
+        send(&mut app, &[], 600);
+        let deleted: Vec<Vec2> = app
+            .world()
+            .resource::<Events<DeleteObjectEvent>>()
+            .iter_current_update_events()
+            .map(|it| it.0)
+            .collect();
+        assert!(matches!(deleted[..], [it] if it.abs_diff_eq(Vec2::ZERO, 0.01)));
+        send(
+            &mut app,
+            &[touch(2, TouchPhase::Ended, center.x, center.y)],
+            0,
+        );
+
+        // two fingers move the camera left and zoom in
+        send(
+            &mut app,
+            &[
+                touch(3, TouchPhase::Started, center.x - 100.0, center.y),
+                touch(4, TouchPhase::Started, center.x + 100.0, center.y),
+            ],
+            0,
+        );
+        send(
+            &mut app,
+            &[touch(4, TouchPhase::Moved, center.x + 300.0, center.y)],
+            50,
+        );
+        let mut camera = app.world_mut().query::<(&Transform, &Projection)>();
+        let (transform, projection) = camera.single(app.world()).unwrap();
+        assert_eq!(-100.0, transform.translation.x);
+        let Projection::Orthographic(projection) = projection else {
+            panic!("the camera is orthographic");
+        };
+        assert_eq!(0.5, projection.scale);
+    }
+}
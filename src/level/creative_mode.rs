@@ -4,7 +4,7 @@
 //! This is the goal for the game jam, a tutorial and puzzle mode would be even cooler, but not
 //! possible in the time.
 
-use crate::core::game::{CoreAssets, LevelConfig};
+use crate::core::game::{CoreAssets, LevelConfig, Looping};
 use crate::core::model::ActivatorType;
 use crate::music::model::{NaturalMinorScale, Note};
 use crate::state::GameState;
@@ -56,6 +56,16 @@ fn setup_config(mut commands: Commands) {
     commands.insert_resource(LevelConfig {
         grow_factor: 100.0,
         scale: Box::new(NaturalMinorScale::new(Note::A)),
+        // the background strings play four chords in 15 seconds, one per bar
+        bpm: 64.0,
+        release: 0.1,
+        looping: Looping {
+            enabled: false,
+            sync_to_background: false,
+        },
+        script: None,
+        // every item can be placed as often as the player likes
+        item_limits: Vec::new(),
     });
 }
 
@@ -1 +1,2 @@
 pub mod creative_mode;
+pub mod script;
@@ -0,0 +1,670 @@
+//! # Script
+//!
+//! A level can ship a [Rhai](https://rhai.rs) script with its own objects and logic. The level
+//! names the script asset in its [`LevelConfig`], it is loaded with the level and compiled once
+//! the build mode starts. Scripted objects are registered as hittable like every core type, so the
+//! waves reach them through the same [`WaveHitEvent`] as notes and activators.
+//!
+//! The script may define these callbacks, all of them are optional:
+//!
+//! * `setup()` when the script is loaded, e.g. to place the objects of the level
+//! * `on_start()` when an execution starts
+//! * `on_hit(kind, object, source)` when a wave of the activator `source` hits a scripted object
+//! * `on_note(note, source)` when a wave of the activator `source` plays a note
+//!
+//! and call these functions of the game:
+//!
+//! * `place(kind, x, y)` places a scripted object of the kind at the position
+//! * `place_note(x, y)` places a note at the position
+//!
+//! The objects are spawned by the items of their [`ObjectType`], like the ones the player places.
+//! * `enable(activator)` starts a new wave of the activator, like a hit would
+//! * `win()` ends the execution and the level is won
+//!
+//! Objects are passed to the script as integer ids. The callbacks share the state in `this`,
+//! which is reset before every execution. The objects placed during an execution are removed
+//! when it ends, the ones placed by `setup` belong to the level until it is cleared.
+//!
+//! A callback runs within the frame, so the script is limited in the operations it may run and how
+//! deep it may nest calls and expressions. A script which exceeds a limit, e.g. in an endless
+//! loop, is stopped and disabled until the level is loaded again.
+
+use crate::core::game::{ActivatorEnabledEvent, LevelConfig, NotePlayedEvent};
+use crate::core::hit::WaveHitEvent;
+use crate::core::model::Activator;
+use crate::core::object::{ObjectAppExt, ObjectItems, ObjectType, spawn_item};
+use crate::core::prediction::ObjectKind;
+use crate::state::GameState;
+use bevy::asset::io::Reader;
+use bevy::asset::{AssetLoader, LoadContext};
+use bevy::prelude::*;
+use rhai::{AST, CallFnOptions, Dynamic, Engine, EvalAltResult, FLOAT, FuncArgs, INT, Map, Scope};
+use serde::{Deserialize, Serialize};
+use std::sync::{Arc, Mutex};
+
+pub struct ScriptPlugin;
+
+/// Operations a single callback may run.
+const MAX_OPERATIONS: u64 = 100_000;
+/// Depth of nested function calls within a callback.
+const MAX_CALL_LEVELS: usize = 32;
+/// Depth of nested expressions, at the top level and within functions.
+const MAX_EXPR_DEPTH: usize = 32;
+
+impl Plugin for ScriptPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_asset::<ScriptSource>()
+            .init_asset_loader::<ScriptLoader>()
+            .init_resource::<ScriptRuntime>()
+            .add_event::<LevelWonEvent>()
+            .register_object(scripted_objects())
+            .add_systems(OnEnter(GameState::SetupGameObjects), load_level_script)
+            .add_systems(
+                Update,
+                (
+                    load_script
+                        .run_if(in_state(GameState::Build).and(resource_exists::<LevelScript>)),
+                    run_on_note.run_if(in_state(GameState::Execute)),
+                    show_win_banner,
+                ),
+            )
+            .add_systems(OnEnter(GameState::Execute), (run_on_start, hide_win_banner))
+            .add_systems(OnExit(GameState::Execute), remove_execution_objects);
+    }
+}
+
+/// The item of the scripted objects, the kind is set once it spawned the object.
+const SCRIPTED_ITEM: &str = "Scripted Object";
+
+/// The item of the notes a script places.
+const NOTE_ITEM: &str = "Note";
+
+/// The script of the level, loaded from the assets named by [`LevelConfig::script`].
+#[derive(Resource, Debug)]
+pub struct LevelScript(pub Handle<ScriptSource>);
+
+/// Source code of a level script.
+#[derive(Asset, TypePath, Debug)]
+pub struct ScriptSource(String);
+
+#[derive(Default)]
+struct ScriptLoader;
+
+impl AssetLoader for ScriptLoader {
+    type Asset = ScriptSource;
+    type Settings = ();
+    type Error = std::io::Error;
+
+    async fn load(
+        &self,
+        reader: &mut dyn Reader,
+        _settings: &(),
+        _load_context: &mut LoadContext<'_>,
+    ) -> Result<ScriptSource, std::io::Error> {
+        let mut bytes = Vec::new();
+        reader.read_to_end(&mut bytes).await?;
+        String::from_utf8(bytes)
+            .map(ScriptSource)
+            .map_err(std::io::Error::other)
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["rhai"]
+    }
+}
+
+/// An object whose hit behavior is defined by the level script. The kind tells the script which
+/// of its objects was hit.
+#[derive(Component, Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ScriptedObject(pub String);
+
+fn scripted_objects() -> ObjectType<ScriptedObject> {
+    ObjectType::<ScriptedObject>::new()
+        .hidden_item(SCRIPTED_ITEM, |entity| {
+            entity.insert(ScriptedObject::default());
+        })
+        .draw(draw_scripted_objects)
+        .save_component()
+        .on_hit(|_| ObjectKind::Object, run_on_hit)
+}
+
+/// Marks an object the level script placed, like the player's objects are marked.
+#[derive(Component, Debug, Copy, Clone, PartialEq, Eq)]
+pub enum ScriptPlaced {
+    /// placed by `setup`, it stays until the level is cleared
+    Level,
+    /// placed by a callback during an execution, it is removed when the execution ends
+    Execution,
+}
+
+/// The level script called `win()`.
+#[derive(Event, Debug)]
+pub struct LevelWonEvent;
+
+/// Shows that the level is won until the next execution starts.
+#[derive(Component)]
+struct WinBanner;
+
+/// What the script asked the game to do during a callback.
+#[derive(Debug, Clone, PartialEq)]
+enum ScriptAction {
+    Place { kind: String, position: Vec2 },
+    PlaceNote { position: Vec2 },
+    Enable(Entity),
+    Win,
+}
+
+/// The engine with the compiled level script and its state.
+#[derive(Resource)]
+struct ScriptRuntime {
+    engine: Engine,
+    /// Filled by the functions of the game while a callback runs.
+    actions: Arc<Mutex<Vec<ScriptAction>>>,
+    ast: Option<AST>,
+    /// The script is compiled, or failed to compile.
+    loaded: bool,
+    state: Dynamic,
+}
+
+impl Default for ScriptRuntime {
+    fn default() -> Self {
+        let actions = Arc::new(Mutex::new(Vec::new()));
+        ScriptRuntime {
+            engine: script_engine(&actions),
+            actions,
+            ast: None,
+            loaded: false,
+            state: Map::new().into(),
+        }
+    }
+}
+
+impl ScriptRuntime {
+    fn compile(&mut self, source: &str) {
+        self.loaded = true;
+        self.ast = self
+            .engine
+            .compile(source)
+            .inspect_err(|err| warn!("level script does not compile: {err}"))
+            .ok();
+    }
+
+    /// Run the callback if the script defines it and return the actions it asked for.
+    fn call(&mut self, name: &str, args: impl FuncArgs) -> Vec<ScriptAction> {
+        let Some(ast) = &self.ast else {
+            return Vec::new();
+        };
+        if ast.iter_functions().any(|it| it.name == name) {
+            let options = CallFnOptions::new()
+                .eval_ast(false)
+                .bind_this_ptr(&mut self.state);
+            let result = self.engine.call_fn_with_options::<Dynamic>(
+                options,
+                &mut Scope::new(),
+                ast,
+                name,
+                args,
+            );
+            if let Err(err) = result {
+                match err.unwrap_inner() {
+                    EvalAltResult::ErrorTooManyOperations(_)
+                    | EvalAltResult::ErrorStackOverflow(_)
+                    | EvalAltResult::ErrorDataTooLarge(..) => {
+                        warn!("level script is disabled, it exceeded a limit in {name}: {err}");
+                        self.ast = None;
+                        // the actions of the stopped callback are dropped
+                        self.actions.lock().unwrap().clear();
+                    }
+                    _ => warn!("level script failed in {name}: {err}"),
+                }
+            }
+        }
+        std::mem::take(&mut *self.actions.lock().unwrap())
+    }
+}
+
+/// The engine with the functions of the game, which collect their actions.
+fn script_engine(actions: &Arc<Mutex<Vec<ScriptAction>>>) -> Engine {
+    let mut engine = Engine::new();
+    engine
+        .set_max_operations(MAX_OPERATIONS)
+        .set_max_call_levels(MAX_CALL_LEVELS)
+        .set_max_expr_depths(MAX_EXPR_DEPTH, MAX_EXPR_DEPTH);
+    engine.on_print(|text| info!("level script: {text}"));
+
+    let queue = actions.clone();
+    engine.register_fn("place", move |kind: &str, x: FLOAT, y: FLOAT| {
+        queue.lock().unwrap().push(ScriptAction::Place {
+            kind: kind.to_string(),
+            position: Vec2::new(x as f32, y as f32),
+        });
+    });
+    let queue = actions.clone();
+    engine.register_fn("place_note", move |x: FLOAT, y: FLOAT| {
+        queue.lock().unwrap().push(ScriptAction::PlaceNote {
+            position: Vec2::new(x as f32, y as f32),
+        });
+    });
+    let queue = actions.clone();
+    engine.register_fn(
+        "enable",
+        move |activator: INT| -> Result<(), Box<EvalAltResult>> {
+            let entity = from_script(activator).ok_or("enable: unknown activator")?;
+            queue.lock().unwrap().push(ScriptAction::Enable(entity));
+            Ok(())
+        },
+    );
+    let queue = actions.clone();
+    engine.register_fn("win", move || {
+        queue.lock().unwrap().push(ScriptAction::Win);
+    });
+    engine
+}
+
+fn to_script(entity: Entity) -> INT {
+    entity.to_bits() as INT
+}
+
+fn from_script(id: INT) -> Option<Entity> {
+    Entity::try_from_bits(id as u64).ok()
+}
+
+/// Spawn an object with the registered item, like the player places it.
+fn place(
+    items: &ObjectItems,
+    name: &str,
+    position: Vec2,
+    components: impl Bundle,
+    commands: &mut Commands,
+) {
+    let Some(item) = items.get(name).copied() else {
+        warn!("level script: the item {name} is not registered");
+        return;
+    };
+    commands.queue(move |world: &mut World| {
+        let object = spawn_item(world, &item, position);
+        world.entity_mut(object).insert(components);
+    });
+}
+
+fn apply_actions(
+    actions: Vec<ScriptAction>,
+    placed: ScriptPlaced,
+    items: &ObjectItems,
+    activators: &Query<(), With<Activator>>,
+    commands: &mut Commands,
+) {
+    for action in actions {
+        match action {
+            ScriptAction::Place { kind, position } => {
+                let name = Name::new(format!("Scripted {kind}"));
+                let object = (placed, name, ScriptedObject(kind));
+                place(items, SCRIPTED_ITEM, position, object, commands);
+            }
+            ScriptAction::PlaceNote { position } => {
+                place(items, NOTE_ITEM, position, placed, commands);
+            }
+            ScriptAction::Enable(target) if !activators.contains(target) => {
+                warn!("level script: enable({target}) is not an activator");
+            }
+            ScriptAction::Enable(target) => {
+                commands.trigger(ActivatorEnabledEvent {
+                    source: None,
+                    target,
+                });
+            }
+            ScriptAction::Win => {
+                info!("level is won");
+                commands.send_event(LevelWonEvent);
+                commands.set_state(GameState::Build);
+            }
+        }
+    }
+}
+
+/// Start loading the script the level names, if any.
+fn load_level_script(
+    config: Res<LevelConfig>,
+    asset_server: Res<AssetServer>,
+    mut runtime: ResMut<ScriptRuntime>,
+    mut commands: Commands,
+) {
+    // a new level compiles its own script
+    *runtime = ScriptRuntime::default();
+    match &config.script {
+        Some(path) => commands.insert_resource(LevelScript(asset_server.load(path.clone()))),
+        None => commands.remove_resource::<LevelScript>(),
+    }
+}
+
+/// Compile the level script once it is loaded and let it set the level up.
+fn load_script(
+    level: Res<LevelScript>,
+    sources: Res<Assets<ScriptSource>>,
+    items: Res<ObjectItems>,
+    activators: Query<(), With<Activator>>,
+    mut runtime: ResMut<ScriptRuntime>,
+    mut commands: Commands,
+) {
+    if runtime.loaded {
+        return;
+    }
+    let Some(source) = sources.get(&level.0) else {
+        return;
+    };
+    runtime.compile(&source.0);
+    let actions = runtime.call("setup", ());
+    apply_actions(
+        actions,
+        ScriptPlaced::Level,
+        &items,
+        &activators,
+        &mut commands,
+    );
+}
+
+fn run_on_start(
+    items: Res<ObjectItems>,
+    activators: Query<(), With<Activator>>,
+    mut runtime: ResMut<ScriptRuntime>,
+    mut commands: Commands,
+) {
+    runtime.state = Map::new().into();
+    let actions = runtime.call("on_start", ());
+    apply_actions(
+        actions,
+        ScriptPlaced::Execution,
+        &items,
+        &activators,
+        &mut commands,
+    );
+}
+
+fn run_on_hit(
+    hit: Trigger<WaveHitEvent>,
+    objects: Query<&ScriptedObject>,
+    items: Res<ObjectItems>,
+    activators: Query<(), With<Activator>>,
+    mut runtime: ResMut<ScriptRuntime>,
+    mut commands: Commands,
+) {
+    let Ok(object) = objects.get(hit.target()) else {
+        return;
+    };
+    let args = (
+        object.0.clone(),
+        to_script(hit.target()),
+        to_script(hit.source),
+    );
+    let actions = runtime.call("on_hit", args);
+    apply_actions(
+        actions,
+        ScriptPlaced::Execution,
+        &items,
+        &activators,
+        &mut commands,
+    );
+}
+
+fn run_on_note(
+    mut note_played: EventReader<NotePlayedEvent>,
+    items: Res<ObjectItems>,
+    activators: Query<(), With<Activator>>,
+    mut runtime: ResMut<ScriptRuntime>,
+    mut commands: Commands,
+) {
+    for event in note_played.read() {
+        let actions = runtime.call("on_note", (to_script(event.note), to_script(event.source)));
+        apply_actions(
+            actions,
+            ScriptPlaced::Execution,
+            &items,
+            &activators,
+            &mut commands,
+        );
+    }
+}
+
+fn remove_execution_objects(placed: Query<(Entity, &ScriptPlaced)>, mut commands: Commands) {
+    for (entity, placed) in &placed {
+        if *placed == ScriptPlaced::Execution {
+            commands.entity(entity).despawn();
+        }
+    }
+}
+
+fn show_win_banner(
+    mut won: EventReader<LevelWonEvent>,
+    banners: Query<(), With<WinBanner>>,
+    mut commands: Commands,
+) {
+    if won.read().count() == 0 || !banners.is_empty() {
+        return;
+    }
+
+    commands.spawn((
+        Name::new("Win Banner"),
+        WinBanner,
+        Node {
+            position_type: PositionType::Absolute,
+            top: Val::Percent(40.0),
+            width: Val::Percent(100.0),
+            justify_content: JustifyContent::Center,
+            ..default()
+        },
+        children![(
+            Text::new("Level won!"),
+            TextFont {
+                font_size: 48.0,
+                ..default()
+            },
+            TextColor(Color::WHITE),
+        )],
+    ));
+}
+
+fn hide_win_banner(banners: Query<Entity, With<WinBanner>>, mut commands: Commands) {
+    for banner in &banners {
+        commands.entity(banner).despawn();
+    }
+}
+
+/// Scripted objects have no icon, they are drawn as diamonds.
+fn draw_scripted_objects(objects: Query<&Transform, With<ScriptedObject>>, mut gizmos: Gizmos) {
+    for transform in &objects {
+        let isometry = Isometry2d::new(transform.translation.xy(), Rot2::degrees(45.0));
+        gizmos.rect_2d(isometry, Vec2::splat(14.0), Color::srgb(0.9, 0.8, 0.4));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::game::{CoreAssets, CoreGamePlugin};
+    use crate::core::hit::Hittable;
+    use crate::core::model::Note;
+    use bevy::ecs::system::RunSystemOnce;
+
+    fn runtime(source: &str) -> ScriptRuntime {
+        let mut runtime = ScriptRuntime::default();
+        runtime.compile(source);
+        assert!(runtime.ast.is_some(), "script must compile");
+        runtime
+    }
+
+    #[test]
+    fn test_example_script_places_a_bell() {
+        let mut runtime = runtime(include_str!("../../assets/scripts/bell.rhai"));
+        assert_eq!(
+            vec![ScriptAction::Place {
+                kind: "bell".to_string(),
+                position: Vec2::new(0.0, 200.0),
+            }],
+            runtime.call("setup", ())
+        );
+    }
+
+    #[test]
+    fn test_on_hit_asks_for_actions() {
+        let mut runtime = runtime(
+            r#"
+            fn on_hit(kind, object, source) {
+                if kind == "gong" {
+                    enable(source);
+                    place("bell", 10.0, 20.0);
+                }
+            }
+            "#,
+        );
+        let source = Entity::from_raw(7);
+        let object = Entity::from_raw(8);
+
+        let args = ("gong".to_string(), to_script(object), to_script(source));
+        assert_eq!(
+            vec![
+                ScriptAction::Enable(source),
+                ScriptAction::Place {
+                    kind: "bell".to_string(),
+                    position: Vec2::new(10.0, 20.0),
+                },
+            ],
+            runtime.call("on_hit", args)
+        );
+        let args = ("bell".to_string(), to_script(object), to_script(source));
+        assert!(runtime.call("on_hit", args).is_empty());
+        // callbacks which are not defined are skipped
+        assert!(runtime.call("setup", ()).is_empty());
+    }
+
+    #[test]
+    fn test_callbacks_share_their_state() {
+        let mut runtime = runtime(
+            r#"
+            fn on_start() { this.played = 0; }
+            fn on_note(note, source) {
+                this.played += 1;
+                if this.played == 2 { win(); }
+            }
+            "#,
+        );
+        let note = to_script(Entity::from_raw(1));
+        let source = to_script(Entity::from_raw(2));
+
+        runtime.call("on_start", ());
+        assert!(runtime.call("on_note", (note, source)).is_empty());
+        assert_eq!(
+            vec![ScriptAction::Win],
+            runtime.call("on_note", (note, source))
+        );
+    }
+
+    #[test]
+    fn test_script_exceeding_a_limit_is_disabled() {
+        let mut endless = runtime(
+            r#"
+            fn on_start() {
+                win();
+                loop {}
+            }
+            fn setup() { win(); }
+            "#,
+        );
+
+        // the endless loop is stopped and its actions are dropped
+        assert!(endless.call("on_start", ()).is_empty());
+        assert!(endless.ast.is_none());
+        assert!(endless.call("setup", ()).is_empty());
+
+        let mut recursive = runtime(
+            r#"
+            fn on_note(note, source) { on_note(note, source); }
+            "#,
+        );
+        let note = to_script(Entity::from_raw(1));
+        assert!(recursive.call("on_note", (note, note)).is_empty());
+        assert!(recursive.ast.is_none());
+    }
+
+    #[test]
+    fn test_enable_only_reaches_activators() {
+        #[derive(Resource, Default)]
+        struct Enabled(Vec<Entity>);
+
+        let mut app = App::new();
+        app.init_resource::<Enabled>().add_observer(
+            |event: Trigger<ActivatorEnabledEvent>, mut enabled: ResMut<Enabled>| {
+                enabled.0.push(event.target);
+            },
+        );
+        let activator = app.world_mut().spawn(Activator).id();
+        let note = app.world_mut().spawn(Note).id();
+        let unknown = Entity::from_raw(1000);
+
+        app.world_mut()
+            .run_system_once(
+                move |activators: Query<(), With<Activator>>, mut commands: Commands| {
+                    let actions = vec![
+                        ScriptAction::Enable(note),
+                        ScriptAction::Enable(activator),
+                        ScriptAction::Enable(unknown),
+                    ];
+                    apply_actions(
+                        actions,
+                        ScriptPlaced::Execution,
+                        &ObjectItems::default(),
+                        &activators,
+                        &mut commands,
+                    );
+                },
+            )
+            .unwrap();
+
+        assert_eq!(vec![activator], app.world().resource::<Enabled>().0);
+    }
+
+    #[test]
+    fn test_placed_objects_are_spawned_by_their_items() {
+        let mut app = App::new();
+        app.insert_resource(CoreAssets::empty())
+            .add_plugins(CoreGamePlugin)
+            .register_object(scripted_objects());
+
+        app.world_mut()
+            .run_system_once(
+                |items: Res<ObjectItems>,
+                 activators: Query<(), With<Activator>>,
+                 mut commands: Commands| {
+                    let actions = vec![
+                        ScriptAction::Place {
+                            kind: "bell".to_string(),
+                            position: Vec2::new(0.0, 200.0),
+                        },
+                        ScriptAction::PlaceNote {
+                            position: Vec2::new(100.0, 0.0),
+                        },
+                    ];
+                    apply_actions(
+                        actions,
+                        ScriptPlaced::Level,
+                        &items,
+                        &activators,
+                        &mut commands,
+                    );
+                },
+            )
+            .unwrap();
+
+        let world = app.world_mut();
+        let mut bells = world.query_filtered::<(&ScriptedObject, &ScriptPlaced), With<Hittable>>();
+        let (bell, placed) = bells.single(world).unwrap();
+        assert_eq!(&ScriptedObject("bell".to_string()), bell);
+        assert_eq!(&ScriptPlaced::Level, placed);
+        // the note is spawned with its icon and scale like a placed one
+        let mut notes =
+            world.query_filtered::<(&Transform, &ScriptPlaced), (With<Note>, With<Hittable>)>();
+        let (transform, placed) = notes.single(world).unwrap();
+        assert_eq!(Vec3::new(100.0, 0.0, 0.0), transform.translation);
+        assert_eq!(Vec3::splat(0.025), transform.scale);
+        assert_eq!(&ScriptPlaced::Level, placed);
+    }
+}
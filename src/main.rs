@@ -9,6 +9,7 @@ mod visual;
 use crate::core::game::CoreGamePlugin;
 use crate::input::InputPlugin;
 use crate::level::creative_mode::CreativeModePlugin;
+use crate::level::script::ScriptPlugin;
 use crate::music::audio::AudioPlugin;
 use crate::music::game::MusicPlugin;
 use crate::state::GameStatePlugin;
@@ -53,6 +54,7 @@ fn main() {
         .add_plugins(MusicPlugin)
         .add_plugins(VisualPlugin)
         // level plugins
+        .add_plugins(ScriptPlugin)
         .add_plugins(CreativeModePlugin)
         // camera
         .insert_resource(ClearColor(Color::BLACK))
@@ -1,7 +1,38 @@
+use crate::core::model::ActivatorShape;
 use crate::music::model::Scale;
 use bevy::prelude::*;
 use std::f32::consts::PI;
 
+/// Distance in logical pixels between two steps of the scale for notes played by a sweep.
+const PITCH_DISTANCE: f32 = 50.0;
+
+/// The scale position of a note played by an activator with the given shape. A sweep plays its
+/// notes in the order of their angle, so the pitch comes from their distance instead.
+pub fn calculate_scale_position(
+    shape: &ActivatorShape,
+    center: &Vec2,
+    point: &Vec2,
+    scale: &(impl Scale + ?Sized),
+) -> u8 {
+    match shape {
+        ActivatorShape::Sweep { .. } => calculate_scale_position_by_distance(center, point, scale),
+        _ => calculate_scale_position_by_angle(center, point, scale),
+    }
+}
+
+/// One step of the scale every [`PITCH_DISTANCE`], further points wrap around to the root.
+pub fn calculate_scale_position_by_distance(
+    center: &Vec2,
+    point: &Vec2,
+    scale: &(impl Scale + ?Sized),
+) -> u8 {
+    let steps = (center.distance(*point) / PITCH_DISTANCE).ceil() as u32;
+    match steps {
+        0 => 0,
+        steps => ((steps - 1) % scale.size() as u32 + 1) as u8,
+    }
+}
+
 pub fn calculate_scale_position_by_angle(
     center: &Vec2,
     point: &Vec2,
@@ -112,6 +143,30 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_distance_wraps_around_the_scale() {
+        let s = MockScale(8);
+        let c = Vec2::ZERO;
+
+        assert_eq!(0, calculate_scale_position_by_distance(&c, &Vec2::ZERO, &s));
+        assert_eq!(
+            1,
+            calculate_scale_position_by_distance(&c, &Vec2::new(0.0, 50.0), &s)
+        );
+        assert_eq!(
+            2,
+            calculate_scale_position_by_distance(&c, &Vec2::new(-60.0, 0.0), &s)
+        );
+        assert_eq!(
+            8,
+            calculate_scale_position_by_distance(&c, &Vec2::new(400.0, 0.0), &s)
+        );
+        assert_eq!(
+            1,
+            calculate_scale_position_by_distance(&c, &Vec2::new(410.0, 0.0), &s)
+        );
+    }
+
     #[test]
     fn test_calculate_90deg() {
         // 90°
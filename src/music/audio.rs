@@ -1,5 +1,6 @@
 //! Technical implementation of musical stuff
 
+use crate::core::model::Percussion;
 use crate::music::model::Note;
 use crate::state::{GameState, MinimalGameState};
 use bevy::prelude::*;
@@ -10,7 +11,9 @@ pub struct AudioPlugin;
 impl Plugin for AudioPlugin {
     fn build(&self, app: &mut App) {
         app.init_resource::<BackgroundAudioAssets>()
+            .add_event::<BackgroundCycleEvent>()
             .init_resource::<PianoAudioAssets>()
+            .init_resource::<PercussionAudioAssets>()
             .add_systems(
                 Update,
                 setup_audio_objects.run_if(in_state(GameState::SetupGameObjects)),
@@ -38,6 +41,10 @@ impl FromWorld for BackgroundAudioAssets {
     }
 }
 
+/// Sent whenever a new cycle of the background strings starts.
+#[derive(Event, Debug)]
+pub struct BackgroundCycleEvent;
+
 #[derive(Resource)]
 pub struct PianoAudioAssets {
     pub piano_a: Handle<AudioSource>,
@@ -92,6 +99,36 @@ impl PianoAudioAssets {
     }
 }
 
+#[derive(Resource)]
+pub struct PercussionAudioAssets {
+    pub kick: Handle<AudioSource>,
+    pub snare: Handle<AudioSource>,
+    pub hat: Handle<AudioSource>,
+    pub clap: Handle<AudioSource>,
+}
+
+impl FromWorld for PercussionAudioAssets {
+    fn from_world(world: &mut World) -> Self {
+        PercussionAudioAssets {
+            kick: world.load_asset("audio/drum_kick.wav"),
+            snare: world.load_asset("audio/drum_snare.wav"),
+            hat: world.load_asset("audio/drum_hat.wav"),
+            clap: world.load_asset("audio/drum_clap.wav"),
+        }
+    }
+}
+
+impl PercussionAudioAssets {
+    pub fn play(&self, percussion: Percussion) -> Handle<AudioSource> {
+        match percussion {
+            Percussion::Kick => self.kick.clone(),
+            Percussion::Snare => self.snare.clone(),
+            Percussion::Hat => self.hat.clone(),
+            Percussion::Clap => self.clap.clone(),
+        }
+    }
+}
+
 /// Simple wrapper to count repetitions for the background strings.
 struct BackgroundRepetition(u8);
 
@@ -136,12 +173,17 @@ fn setup_audio_objects(mut commands: Commands) {
     commands.spawn((Name::new("Background Audio Timer"), BackgroundTimer::new()));
 }
 
-fn initial(mut commands: Commands, assets: Res<BackgroundAudioAssets>) {
+fn initial(
+    mut commands: Commands,
+    assets: Res<BackgroundAudioAssets>,
+    mut cycles: EventWriter<BackgroundCycleEvent>,
+) {
     commands.spawn((
         Name::new("Background Audio"),
         AudioPlayer(assets.strings_1.clone()),
         PlaybackSettings::DESPAWN,
     ));
+    cycles.write(BackgroundCycleEvent);
 }
 
 fn background(
@@ -149,10 +191,14 @@ fn background(
     time: Res<Time>,
     assets: Res<BackgroundAudioAssets>,
     mut commands: Commands,
+    mut cycles: EventWriter<BackgroundCycleEvent>,
 ) {
     let mut timer = timer.single_mut().expect("Background timer must exist");
 
     let tick = timer.tick(time.delta());
+    if tick.is_some() {
+        cycles.write(BackgroundCycleEvent);
+    }
 
     match tick {
         None => {}
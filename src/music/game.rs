@@ -1,8 +1,14 @@
-use crate::core::game::{LevelConfig, NotePlayedEvent};
-use crate::core::model::{Activator, Note};
-use crate::math::calculate_scale_position_by_angle;
-use crate::music::audio::PianoAudioAssets;
-use bevy::audio::Volume;
+use crate::core::chord::played_pitches;
+use crate::core::game::{ExecutionClock, LevelConfig, NotePlayedEvent};
+use crate::core::model::{
+    Activator, ActivatorShape, Chord, Modifier, Note, NoteLength, Percussion,
+};
+use crate::core::modifier::modifier_areas;
+use crate::math::calculate_scale_position;
+use crate::music::audio::{PercussionAudioAssets, PianoAudioAssets};
+use crate::music::model::Pitch;
+use crate::state::GameState;
+use bevy::audio::{AudioSinkPlayback, Volume};
 use bevy::platform::collections::HashMap;
 use bevy::prelude::*;
 
@@ -11,56 +17,213 @@ pub struct MusicPlugin;
 impl Plugin for MusicPlugin {
     fn build(&self, app: &mut App) {
         app.init_resource::<ActivePlayer>()
-            .add_systems(Update, handle_note_played);
+            .add_event::<AuditionNoteEvent>()
+            .add_systems(Update, (handle_note_played, release_notes));
     }
 }
 
+/// Volume a note is played with.
+const NOTE_VOLUME: f32 = 0.3;
+
+/// Stops a note once its length and the release are over, it fades out during the release.
+#[derive(Component, Debug)]
+struct Release {
+    timer: Timer,
+    /// seconds of the fade
+    fade: f32,
+}
+
+impl Release {
+    fn new(length: &NoteLength, level: &LevelConfig) -> Self {
+        Release {
+            timer: Timer::from_seconds(
+                **length * level.beat_length() + level.release,
+                TimerMode::Once,
+            ),
+            fade: level.release,
+        }
+    }
+}
+
+/// Play a note as if the activator hit it, without affecting the execution.
+#[derive(Event, Debug)]
+pub struct AuditionNoteEvent {
+    pub source: Entity,
+    pub note: Entity,
+}
+
 #[derive(Resource, Default, Debug)]
-struct ActivePlayer(HashMap<crate::music::model::Note, Entity>);
+struct ActivePlayer(HashMap<Pitch, Entity>);
 
 fn handle_note_played(
     mut note_played_events: EventReader<NotePlayedEvent>,
-    activators: Query<(&Activator, &Transform)>,
-    notes: Query<(&Note, &Transform)>,
+    mut audition_events: EventReader<AuditionNoteEvent>,
+    activators: Query<(&Activator, &ActivatorShape, &Transform)>,
+    notes: Query<
+        (
+            &Transform,
+            Option<&Chord>,
+            Option<&NoteLength>,
+            Option<&Percussion>,
+        ),
+        With<Note>,
+    >,
+    modifiers: Query<(&Modifier, &Transform)>,
     level: Res<LevelConfig>,
     piano: Res<PianoAudioAssets>,
+    drums: Res<PercussionAudioAssets>,
     mut commands: Commands,
     mut active_player: ResMut<ActivePlayer>,
 ) {
-    for event in note_played_events.read() {
-        let Ok((_, activator)) = activators.get(event.source) else {
+    let played_notes = note_played_events
+        .read()
+        .map(|it| (it.source, it.note))
+        .chain(audition_events.read().map(|it| (it.source, it.note)));
+    let modifiers = modifier_areas(&modifiers);
+    for (source, note) in played_notes {
+        let Ok((_, shape, activator)) = activators.get(source) else {
             continue;
         };
-        let Ok((_, note)) = notes.get(event.note) else {
+        let Ok((note, chord, length, percussion)) = notes.get(note) else {
             continue;
         };
 
-        // calculate the note from angle
-        let index = calculate_scale_position_by_angle(
+        // percussion plays its drum regardless of the angle
+        if let Some(percussion) = percussion {
+            let mut player = commands.spawn((
+                Name::new("Percussion"),
+                AudioPlayer(drums.play(*percussion)),
+                PlaybackSettings::DESPAWN.with_volume(Volume::Linear(NOTE_VOLUME)),
+            ));
+            if let Some(length) = length {
+                player.insert(Release::new(length, &level));
+            }
+            continue;
+        }
+
+        // calculate the note from angle, or distance for a sweep
+        let index = calculate_scale_position(
+            shape,
             &activator.translation.xy(),
             &note.translation.xy(),
             &*level.scale,
         );
-        let played = level.scale.get(index);
+        // a chord plays all of its tones at once
+        let pitches = played_pitches(
+            chord,
+            index,
+            &*level.scale,
+            note.translation.xy(),
+            &modifiers,
+        );
+        for played in pitches {
+            // if a player for the selected note exists, remove it
+            match active_player.0.get(&played) {
+                None => {}
+                Some(id) => {
+                    if let Ok(mut e) = commands.get_entity(*id) {
+                        e.insert(PlaybackSettings::DESPAWN.with_volume(Volume::Linear(0.0)));
+                    };
+                }
+            }
 
-        // if a player for the selected note exists, remove it
-        match active_player.0.get(&played) {
-            None => {}
-            Some(id) => {
-                if let Ok(mut e) = commands.get_entity(*id) {
-                    e.insert(PlaybackSettings::DESPAWN.with_volume(Volume::Linear(0.0)));
-                };
+            // play note
+            let mut player = commands.spawn((
+                Name::new("Note"),
+                AudioPlayer(piano.play(played.note)),
+                PlaybackSettings::DESPAWN
+                    .with_volume(Volume::Linear(NOTE_VOLUME))
+                    .with_speed(played.speed()),
+            ));
+            if let Some(length) = length {
+                player.insert(Release::new(length, &level));
             }
+            active_player.0.insert(played, player.id());
         }
+    }
+}
 
-        // play note
-        let id = commands
-            .spawn((
-                Name::new("Note"),
-                AudioPlayer(piano.play(played)),
-                PlaybackSettings::DESPAWN.with_volume(Volume::Linear(0.3)),
-            ))
+/// Fade out the notes with a length during their release and stop them afterwards. The lengths
+/// follow the playback rate and stand still while the execution is paused, the notes still sounding
+/// after the execution fade out in real time.
+fn release_notes(
+    time: Res<Time>,
+    clock: Res<ExecutionClock>,
+    state: Res<State<GameState>>,
+    // the sink is only added once the sample started playing
+    mut players: Query<(Entity, &mut Release, Option<&mut AudioSink>)>,
+    mut commands: Commands,
+) {
+    let delta = if *state == GameState::Execute {
+        clock.delta()
+    } else {
+        time.delta()
+    };
+    for (entity, mut release, mut sink) in &mut players {
+        release.timer.tick(delta);
+        if release.timer.finished() {
+            commands.entity(entity).despawn();
+            continue;
+        }
+        let fade = release.timer.remaining_secs() / release.fade;
+        if fade < 1.0
+            && let Some(sink) = sink.as_mut()
+        {
+            sink.set_volume(Volume::Linear(NOTE_VOLUME * fade));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::game::Looping;
+    use crate::music::model::NaturalMinorScale;
+    use crate::state::ExecutionState;
+    use std::time::Duration;
+
+    #[test]
+    fn test_release_follows_the_execution_clock() {
+        let mut app = App::new();
+        app.init_resource::<Time>()
+            .init_resource::<ExecutionClock>()
+            .insert_resource(State::new(GameState::Execute))
+            .add_systems(Update, release_notes);
+        // two beats of half a second and a long release
+        let level = LevelConfig {
+            grow_factor: 100.0,
+            scale: Box::new(NaturalMinorScale::new(crate::music::model::Note::A)),
+            bpm: 120.0,
+            release: 0.3,
+            looping: Looping {
+                enabled: false,
+                sync_to_background: false,
+            },
+            script: None,
+            item_limits: Vec::new(),
+        };
+        let note = app
+            .world_mut()
+            .spawn(Release::new(&NoteLength(2.0), &level))
             .id();
-        active_player.0.insert(played, id);
+        let tick = |app: &mut App, state: ExecutionState| {
+            app.world_mut()
+                .resource_mut::<ExecutionClock>()
+                .tick(Duration::from_millis(100), &state);
+            app.update();
+            app.world().get_entity(note).is_ok()
+        };
+
+        for _ in 0..10 {
+            assert!(tick(&mut app, ExecutionState::Running));
+        }
+        // the note stands still while paused
+        for _ in 0..10 {
+            assert!(tick(&mut app, ExecutionState::Paused));
+        }
+        // gone after 1.3 seconds
+        assert!(tick(&mut app, ExecutionState::Running));
+        assert!(tick(&mut app, ExecutionState::Running));
+        assert!(!tick(&mut app, ExecutionState::Running));
     }
 }
@@ -59,6 +59,49 @@ impl Note {
     }
 }
 
+/// A note which is played the given number of octaves higher or lower.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Pitch {
+    pub note: Note,
+    pub octave: i32,
+}
+
+impl Pitch {
+    /// The pitch the given number of semitones higher or lower. The samples span A to G#, so a
+    /// note shifted beyond them continues in the next or previous octave.
+    pub fn transpose(&self, semitones: i32) -> Pitch {
+        let semitones = self.semitones() + semitones;
+        let mut note = A;
+        for _ in 0..semitones.rem_euclid(12) {
+            note = note.next(&Half);
+        }
+        Pitch {
+            note,
+            octave: semitones.div_euclid(12),
+        }
+    }
+
+    /// The semitones above the A of the samples, which orders the pitches from low to high.
+    pub fn semitones(&self) -> i32 {
+        // the notes are declared in the order of the samples
+        self.octave * 12 + self.note as i32
+    }
+
+    /// The playback speed of the sample of the note, which shifts it by its octave.
+    pub fn speed(&self) -> f32 {
+        2f32.powi(self.octave)
+    }
+}
+
+impl std::fmt::Display for Pitch {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.octave {
+            0 => write!(f, "{:?}", self.note),
+            octave => write!(f, "{:?}{octave:+}", self.note),
+        }
+    }
+}
+
 pub trait Scale: Send + Sync + 'static {
     fn size(&self) -> u8 {
         self.steps().len() as u8 + 1
@@ -136,4 +179,13 @@ mod tests {
         assert_eq!(G, scale.get(14));
         assert_eq!(A, scale.get(15));
     }
+
+    #[test]
+    fn test_transpose_carries_the_octave() {
+        let pitch = |note, octave| Pitch { note, octave };
+        assert_eq!(pitch(C, 0), pitch(B, 0).transpose(1));
+        assert_eq!(pitch(A, 1), pitch(Gs, 0).transpose(1));
+        assert_eq!(pitch(Gs, -1), pitch(A, 0).transpose(-1));
+        assert_eq!(pitch(A, 2), pitch(A, 0).transpose(24));
+    }
 }
@@ -29,6 +29,17 @@ pub enum GameState {
     Over,
 }
 
+/// State of a running execution.
+#[derive(SubStates, Default, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[source(GameState=GameState::Execute)]
+pub enum ExecutionState {
+    /// the execution time passes with the playback rate
+    #[default]
+    Running,
+    /// the execution time only passes by single steps
+    Paused,
+}
+
 #[derive(Default, Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum MinimalGameState {
     #[default]
@@ -57,6 +68,7 @@ impl Plugin for GameStatePlugin {
     fn build(&self, app: &mut App) {
         app.init_state::<AppState>()
             .add_sub_state::<GameState>()
+            .add_sub_state::<ExecutionState>()
             .add_computed_state::<MinimalGameState>()
             .add_systems(
                 PostUpdate,
@@ -5,8 +5,9 @@ use bevy::color::palettes::css::{
 };
 use bevy::log::warn;
 use bevy::prelude::{Vec2, Vec4};
+use serde::{Deserialize, Serialize};
 
-#[derive(Debug, Default, PartialEq, Eq, Hash, Copy, Clone)]
+#[derive(Debug, Default, PartialEq, Eq, Hash, Copy, Clone, Serialize, Deserialize)]
 pub enum ColorPalette {
     #[default]
     BlueViolet,
@@ -2,7 +2,6 @@ use crate::state::AppState;
 use bevy::prelude::*;
 use bevy::render::render_resource::{AsBindGroup, ShaderRef};
 use bevy::sprite::{Material2d, Material2dPlugin};
-use bevy::window::WindowResized;
 
 const SHADER_PATH: &str = "shaders/silk.wgsl";
 
@@ -14,7 +13,7 @@ impl Plugin for ShaderPlugin {
             .add_systems(Startup, setup)
             .add_systems(
                 Update,
-                (update, handle_window_resize).run_if(in_state(AppState::Game)),
+                (update, fit_to_view).run_if(in_state(AppState::Game)),
             );
     }
 }
@@ -71,14 +70,23 @@ fn update(time: Res<Time>, mut backgrounds: ResMut<Assets<SilkMaterial>>) {
     })
 }
 
-fn handle_window_resize(
-    mut events: EventReader<WindowResized>,
+/// Keep the background covering the whole window, even if the camera is moved or zoomed.
+fn fit_to_view(
+    windows: Query<&Window>,
+    camera: Query<(&Transform, &Projection), (With<Camera>, Without<Shader>)>,
     mut shader: Query<&mut Transform, With<Shader>>,
 ) {
-    for event in events.read() {
-        shader
-            .single_mut()
-            .expect("there must be exactly one background shader")
-            .scale = Vec3::new(event.width, event.height, 0.);
-    }
+    let (Ok(window), Ok((camera, projection))) = (windows.single(), camera.single()) else {
+        return;
+    };
+    let zoom = match projection {
+        Projection::Orthographic(projection) => projection.scale,
+        _ => 1.0,
+    };
+
+    let mut shader = shader
+        .single_mut()
+        .expect("there must be exactly one background shader");
+    shader.translation = camera.translation.xy().extend(shader.translation.z);
+    shader.scale = (window.resolution.size() * zoom).extend(0.0);
 }